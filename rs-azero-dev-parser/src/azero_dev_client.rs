@@ -0,0 +1,86 @@
+use log::error;
+use reqwest::header::{HeaderMap, HeaderValue};
+use rs_utils::clients::http_client::HttpClient;
+use serde_json::{json, Value};
+use std::env;
+
+static ACCOUNT_FIELDS: &str = "id identity { display judgement subName subOf { id display } }";
+
+/// GraphQL client of the indexer behind azero.dev.
+#[derive(Clone, Debug)]
+pub struct AzeroDevClient {
+    http_client: HttpClient,
+    api_url: String,
+}
+
+impl AzeroDevClient {
+    pub async fn new() -> Self {
+        let api_url = env::var("AZERO_DEV_API_URL").unwrap();
+        let http_client = HttpClient::new("azero_dev_client").await;
+        AzeroDevClient {
+            http_client,
+            api_url,
+        }
+    }
+
+    /// Last block processed by the indexer.
+    pub async fn get_head_block_number(&mut self) -> Option<u64> {
+        let data = self
+            .graphql_request("{ squidStatus { height } }", json!({}))
+            .await?;
+
+        data.get("squidStatus")?.get("height")?.as_u64()
+    }
+
+    /// Successful native transfers within the block range (inclusive) along with the
+    /// accounts on both sides of them.
+    pub async fn get_transfers(
+        &mut self,
+        from_block_number: u64,
+        to_block_number: u64,
+        limit: u32,
+        offset: u32,
+    ) -> Option<Vec<Value>> {
+        let query = format!(
+            "query($from: Int!, $to: Int!, $limit: Int!, $offset: Int!) {{ \
+                transfers(where: {{blockNumber_gte: $from, blockNumber_lte: $to, success_eq: true}}, \
+                    orderBy: id_ASC, limit: $limit, offset: $offset) {{ \
+                    id blockNumber extrinsicIndex timestamp amount success \
+                    from {{ {ACCOUNT_FIELDS} }} to {{ {ACCOUNT_FIELDS} }} \
+                }} \
+            }}"
+        );
+        let variables = json!({
+            "from": from_block_number,
+            "to": to_block_number,
+            "limit": limit,
+            "offset": offset,
+        });
+        let data = self.graphql_request(&query, variables).await?;
+
+        Some(data.get("transfers")?.as_array()?.clone())
+    }
+
+    async fn graphql_request(&mut self, query: &str, variables: Value) -> Option<Value> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+
+        let payload = json!({"query": query, "variables": variables});
+        let resp: Value = self
+            .http_client
+            .post_request(&self.api_url, headers, payload)
+            .await;
+
+        if let Some(e) = resp.get("errors") {
+            error!(target: "azero_dev_client", "Request failed: {e}");
+            return None;
+        }
+
+        let data = resp.get("data")?;
+        if data.is_null() {
+            return None;
+        }
+
+        Some(data.clone())
+    }
+}
@@ -0,0 +1,209 @@
+use crate::azero_dev_client::AzeroDevClient;
+use bson::DateTime;
+use itertools::Itertools;
+use rs_subscan_parser::{
+    mongodb_client_identities::MongoDbClientIdentity,
+    subscan_cursor::{parse_pages_since_cursor, CURSOR_PAGE_SIZE},
+    subscan_enrichment::{EnrichmentPipeline, EnrichmentStageKind},
+    subscan_parser::{Network, AZERO_DENOMINATOR, EMPTY_ADDRESS},
+    Identity, OperationType, SubscanCursor, SubscanOperation,
+};
+use serde_json::Value;
+use std::collections::HashSet;
+
+pub static AZERO_DEV_TRANSFERS_CURSOR_STREAM: &str = "azero_dev_transfers";
+
+// transfers Subscan delivered first are skipped by their extrinsic index
+static AZERO_DEV_ENRICHMENT_STAGES: [EnrichmentStageKind; 5] = [
+    EnrichmentStageKind::SkipExisting,
+    EnrichmentStageKind::FilterDust,
+    EnrichmentStageKind::ValueInUsd,
+    EnrichmentStageKind::SetHash,
+    EnrichmentStageKind::ClassifyTransfers,
+];
+
+pub async fn parse_azero_dev_transfers(
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, SubscanCursor)> {
+    let (subscan_operations, identities, cursor) = parse_pages_since_cursor(
+        AZERO_DEV_TRANSFERS_CURSOR_STREAM,
+        head_block_number,
+        parse_transfers_page,
+    )
+    .await?;
+
+    let subscan_operations = enrich_azero_dev_transfers(subscan_operations, identities).await?;
+
+    Some((subscan_operations, cursor))
+}
+
+async fn parse_transfers_page(
+    page: u32,
+    (from_block_number, to_block_number): (u64, u64),
+) -> Option<(Vec<SubscanOperation>, Vec<Identity>, usize)> {
+    let mut azero_dev_client = AzeroDevClient::new().await;
+    let transfers = azero_dev_client
+        .get_transfers(
+            from_block_number,
+            to_block_number,
+            CURSOR_PAGE_SIZE,
+            page * CURSOR_PAGE_SIZE,
+        )
+        .await?;
+
+    let (subscan_operations, identities) = convert_transfers(&transfers, &Network::from_env());
+    Some((subscan_operations, identities, transfers.len()))
+}
+
+/// Values transfers in USD, classifies them by exchange wallets and saves identities of
+/// the accounts seen in them. The stages can be changed with `AZERO_DEV_ENRICHMENT_STAGES`.
+async fn enrich_azero_dev_transfers(
+    subscan_operations: Vec<SubscanOperation>,
+    identities: Vec<Identity>,
+) -> Option<Vec<SubscanOperation>> {
+    let identities: HashSet<_> = HashSet::from_iter(identities);
+    let identities = identities.into_iter().collect_vec();
+
+    let subscan_operations =
+        EnrichmentPipeline::from_env("AZERO_DEV_ENRICHMENT_STAGES", &AZERO_DEV_ENRICHMENT_STAGES)
+            .run(subscan_operations)
+            .await?;
+
+    // saving newly parsed identities
+    let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
+    mongodb_client_identity
+        .import_or_update_identities(identities)
+        .await;
+
+    Some(subscan_operations)
+}
+
+/// Transfers of an indexer page together with identities of their accounts. Transfers
+/// which failed or can't be parsed are skipped.
+pub fn convert_transfers(
+    transfers: &[Value],
+    network: &Network,
+) -> (Vec<SubscanOperation>, Vec<Identity>) {
+    let subscan_operations = transfers
+        .iter()
+        .filter_map(|p| convert_transfer(p, network))
+        .collect();
+
+    let identities = transfers
+        .iter()
+        .filter(|p| p.get("success").and_then(|s| s.as_bool()) == Some(true))
+        .flat_map(|p| [p.get("from"), p.get("to")])
+        .flatten()
+        .filter_map(convert_account)
+        .collect();
+
+    (subscan_operations, identities)
+}
+
+pub fn convert_transfer(transfer: &Value, network: &Network) -> Option<SubscanOperation> {
+    if !transfer.get("success")?.as_bool()? {
+        return None;
+    }
+
+    let block_number = transfer.get("blockNumber")?.as_u64()?;
+    // same `{block}-{index}` format Subscan uses, so both sources dedupe against each other
+    let extrinsic_index = format!(
+        "{}-{}",
+        block_number,
+        transfer.get("extrinsicIndex")?.as_u64()?
+    );
+    let operation_timestamp =
+        chrono::DateTime::parse_from_rfc3339(transfer.get("timestamp")?.as_str()?).ok()?;
+    let operation_quantity =
+        transfer.get("amount")?.as_str()?.parse::<f64>().ok()? / AZERO_DENOMINATOR;
+    let from_wallet = transfer.get("from")?.get("id")?.as_str()?.to_string();
+    let to_wallet = transfer.get("to")?.get("id")?.as_str()?.to_string();
+
+    Some(SubscanOperation {
+        hash: String::new(),
+        block_number,
+        extrinsic_index,
+        operation_timestamp: DateTime::from_millis(operation_timestamp.timestamp_millis()),
+        operation_quantity,
+        operation_usd: 0.0,
+        operation_type: OperationType::Transfer,
+        from_wallet,
+        controller_wallet: EMPTY_ADDRESS.to_string(),
+        to_wallet,
+        pool_id: None,
+        pool_name: None,
+        targets: Vec::new(),
+        bridge: None,
+        network: network.clone(),
+    })
+}
+
+/// Identity of an indexer account. A sub-identity is shown as `Parent/child` like the
+/// Subscan ones and keeps its own judgement.
+pub fn convert_account(account: &Value) -> Option<Identity> {
+    let get_str = |value: &Value, name: &str| {
+        value
+            .get(name)
+            .and_then(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string())
+    };
+
+    let address = get_str(account, "id")?;
+    let identity = account.get("identity").filter(|p| !p.is_null())?;
+    let parent = identity.get("subOf").filter(|p| !p.is_null());
+    let display = match parent {
+        Some(parent) => format!(
+            "{}/{}",
+            get_str(parent, "display")?,
+            get_str(identity, "subName").or(get_str(identity, "display"))?
+        ),
+        None => get_str(identity, "display")?,
+    };
+
+    Some(Identity {
+        address,
+        identity: display,
+        parent: parent.and_then(|p| get_str(p, "id")),
+        judgement: get_str(identity, "judgement"),
+        updated_timestamp: Some(DateTime::now()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::azero_dev_parser::convert_transfers;
+    use rs_subscan_parser::{subscan_parser::Network, OperationType};
+    use serde_json::Value;
+
+    #[test]
+    fn convert_transfers_works() {
+        let fixture =
+            serde_json::from_str::<Value>(include_str!("../tests/fixtures/transfers.json"))
+                .unwrap();
+        let transfers = fixture["data"]["transfers"].as_array().unwrap();
+
+        // the failed transfer and the one without an amount are skipped
+        let (subscan_operations, identities) = convert_transfers(transfers, &Network::Alephzero);
+        assert_eq!(subscan_operations.len(), 2);
+        assert_eq!(subscan_operations[0].extrinsic_index, "64000001-2");
+        assert_eq!(subscan_operations[0].operation_quantity, 250_000.0);
+        assert_eq!(subscan_operations[0].operation_usd, 0.0);
+        assert_eq!(
+            subscan_operations[0].operation_timestamp.timestamp_millis(),
+            1_700_000_000_000
+        );
+        assert_eq!(
+            subscan_operations[0].operation_type,
+            OperationType::Transfer
+        );
+        assert_eq!(subscan_operations[1].to_wallet, "5Cold");
+
+        // accounts without an identity are not saved
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[0].identity, "Exchange/Hot Wallet 2");
+        assert_eq!(identities[0].parent.as_deref(), Some("5Exchange"));
+        assert_eq!(identities[1].identity, "Whale");
+        assert_eq!(identities[1].judgement.as_deref(), Some("Reasonable"));
+    }
+}
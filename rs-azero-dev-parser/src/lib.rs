@@ -0,0 +1,2 @@
+pub mod azero_dev_client;
+pub mod azero_dev_parser;
@@ -0,0 +1,76 @@
+use futures::{future::BoxFuture, FutureExt};
+use log::info;
+use rs_azero_dev_parser::{
+    azero_dev_client::AzeroDevClient, azero_dev_parser::parse_azero_dev_transfers,
+};
+use rs_subscan_parser::{
+    mongodb_client_cursor::MongoDbClientCursor,
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_cursor::{run_cursor_worker, CursorWorker},
+    SubscanCursor, SubscanOperation,
+};
+use rs_utils::utils::logger::initialize_logger;
+
+#[tokio::main(worker_threads = 10)]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    info!(target: "azero_dev_parser", "Started azero.dev parser worker.");
+
+    create_indexes().await;
+    start_worker().await;
+}
+
+async fn create_indexes() {
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    mongodb_client_subscan.create_index().await;
+
+    let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
+    mongodb_client_identity.create_index().await;
+
+    let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+    mongodb_client_cursor.create_index().await;
+}
+
+async fn start_worker() {
+    let azero_dev_client = AzeroDevClient::new().await;
+    run_cursor_worker(AzeroDevWorker { azero_dev_client }).await;
+}
+
+struct AzeroDevWorker {
+    azero_dev_client: AzeroDevClient,
+}
+
+impl CursorWorker for AzeroDevWorker {
+    type Item = SubscanOperation;
+
+    fn get_target(&self) -> &'static str {
+        "azero_dev_parser"
+    }
+
+    fn get_items_name(&self) -> &'static str {
+        "azero.dev transfers"
+    }
+
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>> {
+        self.azero_dev_client.get_head_block_number().boxed()
+    }
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<SubscanOperation>, SubscanCursor)>> {
+        parse_azero_dev_transfers(head_block_number).boxed()
+    }
+
+    fn import(&mut self, subscan_operations: Vec<SubscanOperation>) -> BoxFuture<'_, ()> {
+        async move {
+            let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+            mongodb_client_subscan
+                .import_subscan_operations(subscan_operations)
+                .await;
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,23 @@
+#!/usr/bin/env bash
+# Records transfers.json from the azero.dev GraphQL API with the query the client sends.
+# Pick a block range with a few transfers, then update the assertions of
+# convert_transfers_works to the recorded transfers.
+#
+# Usage: AZERO_DEV_API_URL=https://... ./record.sh <from_block> <to_block>
+set -euo pipefail
+cd "$(dirname "$0")"
+trap 'rm -f transfers.json.tmp' EXIT
+
+ACCOUNT_FIELDS='id identity { display judgement subName subOf { id display } }'
+QUERY="query(\$from: Int!, \$to: Int!, \$limit: Int!, \$offset: Int!) { \
+transfers(where: {blockNumber_gte: \$from, blockNumber_lte: \$to, success_eq: true}, \
+orderBy: id_ASC, limit: \$limit, offset: \$offset) { \
+id blockNumber extrinsicIndex timestamp amount success \
+from { $ACCOUNT_FIELDS } to { $ACCOUNT_FIELDS } } }"
+
+jq -n --arg query "$QUERY" --argjson from "$1" --argjson to "$2" \
+    '{query: $query, variables: {from: $from, to: $to, limit: 10, offset: 0}}' |
+    curl -sSf -X POST -H 'Content-Type: application/json' -d @- "$AZERO_DEV_API_URL" |
+    jq --arg source "Recorded from $AZERO_DEV_API_URL for blocks $1-$2 with record.sh." \
+        '{source: $source} + .' >transfers.json.tmp
+mv transfers.json.tmp transfers.json
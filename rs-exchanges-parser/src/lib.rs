@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
 
 pub mod exchange_parsers;
+pub mod mongodb_client_exchange_wallets;
 pub mod mongodb_client_exchanges;
 
 #[derive(
@@ -27,6 +28,8 @@ pub enum Exchanges {
     Kucoin,
     Gate,
     CoinDCX,
+    Common,
+    PanoramaSwap,
 }
 
 impl Exchanges {
@@ -36,9 +39,16 @@ impl Exchanges {
             Exchanges::Kucoin => "🦚 Kucoin",
             Exchanges::Gate => "🚪 Gate",
             Exchanges::CoinDCX => "🇮🇳 CoinDCX",
+            Exchanges::Common => "🌀 Common",
+            Exchanges::PanoramaSwap => "🌄 Panorama Swap",
         }
         .to_string()
     }
+
+    /// On-chain AMMs, their trades are decoded from swap events of pair contracts.
+    pub fn is_dex(&self) -> bool {
+        matches!(self, Exchanges::Common | Exchanges::PanoramaSwap)
+    }
 }
 
 #[derive(
@@ -70,13 +80,85 @@ pub enum ExchangesWallets {
 }
 
 impl ExchangesWallets {
-    pub fn get_beautiful_name(&self) -> String {
+    pub fn get_exchange(&self) -> Exchanges {
         match self {
             ExchangesWallets::Mexc => Exchanges::Mexc,
             ExchangesWallets::Kucoin => Exchanges::Kucoin,
             ExchangesWallets::Gate => Exchanges::Gate,
         }
-        .get_beautiful_name()
+    }
+
+    pub fn get_beautiful_name(&self) -> String {
+        self.get_exchange().get_beautiful_name()
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum ExchangeWalletRole {
+    #[default]
+    Hot,
+    Cold,
+    Deposit,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum ExchangeWalletStatus {
+    #[default]
+    Confirmed,
+    Proposed,
+}
+
+/// One of the many wallets an exchange runs. Kept in the database, so wallets can be
+/// added or retired without a release. Proposed wallets come from deposit address
+/// discovery and are ignored until confirmed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ExchangeWallet {
+    pub address: String,
+    pub exchange: Exchanges,
+    pub role: ExchangeWalletRole,
+    #[serde(default)]
+    pub status: ExchangeWalletStatus,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+}
+
+impl ExchangeWallet {
+    pub fn get_beautiful_name(&self) -> String {
+        format!(
+            "{} ({} wallet)",
+            self.exchange.get_beautiful_name(),
+            self.role.to_string().to_lowercase()
+        )
     }
 }
 
@@ -169,4 +251,19 @@ impl ExchangeTrade {
             self.exchange,
         ));
     }
+
+    /// Trades of one block share its timestamp, so on-chain trades are told apart by the
+    /// index of their event.
+    pub fn set_event_hash(&mut self, event_index: &str) {
+        self.hash = sha256::digest(format!(
+            "{}_{}_{}_{}_{}_{}_{}",
+            self.trade_timestamp,
+            self.trade_quantity,
+            self.trade_type,
+            self.primary_token,
+            self.secondary_token,
+            self.exchange,
+            event_index,
+        ));
+    }
 }
@@ -1,30 +1,93 @@
 use futures::{stream::FuturesUnordered, StreamExt};
-use log::info;
+use log::{error, info};
 use rs_exchanges_parser::{
     exchange_parsers::{
         coindcx_parser::CoinDcxParser, gate_parser::GateParser, kucoin_parser::KucoinParser,
         mexc_parser::MexcParser,
     },
+    mongodb_client_exchange_wallets::{
+        format_exchange_wallets, parse_exchange_wallets, MongoDbClientExchangeWallets,
+    },
     mongodb_client_exchanges::MongoDbClientExchanges,
     Exchanges, PrimaryToken, SecondaryToken,
 };
 use rs_utils::utils::logger::initialize_logger;
-use std::time::Duration;
+use std::{env, fs, time::Duration};
 use tokio::time::sleep;
 
 #[tokio::main(worker_threads = 10)]
 async fn main() {
     initialize_logger().expect("failed to initialize logging.");
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(|p| p.as_str()) == Some("wallets") {
+        run_wallets_command(&args[1..]).await;
+        return;
+    }
+
     info!(target: "exchange_parser", "Started exchange parser worker.");
 
     start_worker().await;
 }
 
+/// Exchange wallets are edited as a JSON list of `{address, exchange, role}`, proposed
+/// deposit addresses are confirmed one by one.
+async fn run_wallets_command(args: &[String]) {
+    let mut mongodb_client_exchange_wallets = MongoDbClientExchangeWallets::new().await;
+    mongodb_client_exchange_wallets.create_index().await;
+
+    match (args.first().map(|p| p.as_str()), args.get(1)) {
+        (Some("import"), Some(path)) => {
+            let exchange_wallets = fs::read_to_string(path)
+                .ok()
+                .and_then(|p| parse_exchange_wallets(&p));
+            let Some(exchange_wallets) = exchange_wallets else {
+                error!(target: "exchange_wallets", "Unable to read exchange wallets from {path}");
+                return;
+            };
+
+            info!(target: "exchange_wallets", "Importing {} exchange wallets", exchange_wallets.len());
+            mongodb_client_exchange_wallets
+                .import_or_update_exchange_wallets(exchange_wallets)
+                .await;
+        }
+        (Some("export"), Some(path)) => {
+            let exchange_wallets = mongodb_client_exchange_wallets.get_exchange_wallets().await;
+            let content = format_exchange_wallets(&exchange_wallets);
+            if fs::write(path, content).is_err() {
+                error!(target: "exchange_wallets", "Unable to write exchange wallets to {path}");
+                return;
+            }
+
+            info!(target: "exchange_wallets", "Exported {} exchange wallets", exchange_wallets.len());
+        }
+        (Some("confirm"), Some(address)) => {
+            if !mongodb_client_exchange_wallets
+                .confirm_exchange_wallet(address)
+                .await
+            {
+                error!(target: "exchange_wallets", "No proposed exchange wallet {address}");
+                return;
+            }
+
+            info!(target: "exchange_wallets", "Confirmed exchange wallet {address}");
+        }
+        _ => error!(
+            target: "exchange_wallets", "Usage: rs-exchanges-parser wallets <import|export> <file.json> | confirm <address>",
+        ),
+    }
+}
+
 async fn start_worker() {
     let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
     mongodb_client_exchanges.create_index().await;
 
+    let mut mongodb_client_exchange_wallets = MongoDbClientExchangeWallets::new().await;
+    mongodb_client_exchange_wallets.create_index().await;
+    mongodb_client_exchange_wallets
+        .seed_exchange_wallets()
+        .await;
+
     loop {
         let mut tasks = FuturesUnordered::new();
         tasks.push(tokio::spawn(async move {
@@ -0,0 +1,272 @@
+use crate::{ExchangeWallet, ExchangeWalletStatus, Exchanges, ExchangesWallets};
+use bson::doc;
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+use strum::IntoEnumIterator;
+
+pub struct MongoDbClientExchangeWallets {
+    pub client_exchange_wallets: MongoDbClient<ExchangeWallet>,
+}
+
+impl MongoDbClientExchangeWallets {
+    pub async fn new() -> MongoDbClientExchangeWallets {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_EXCHANGE_WALLET").unwrap();
+        let client_name = "mongodb_exchange_wallets";
+        let client_exchange_wallets = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_exchange_wallets,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"address": 1u32})
+            .options(options)
+            .build();
+        self.client_exchange_wallets.create_index(model, None).await;
+
+        let indexes = vec!["exchange"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_exchange_wallets.create_index(model, None).await;
+        }
+    }
+
+    /// Adds the wallets that used to be hard-coded, unless they are registered already.
+    pub async fn seed_exchange_wallets(&mut self) {
+        for p in ExchangesWallets::iter() {
+            let exchange_wallet = ExchangeWallet {
+                address: p.to_string(),
+                exchange: p.get_exchange(),
+                role: Default::default(),
+                status: ExchangeWalletStatus::Confirmed,
+                confidence: None,
+            };
+            if self
+                .client_exchange_wallets
+                .find_one(doc! { "address": exchange_wallet.address.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_exchange_wallets
+                    .insert_one(exchange_wallet, None)
+                    .await;
+            }
+        }
+    }
+
+    pub async fn import_or_update_exchange_wallets(
+        &mut self,
+        exchange_wallets: Vec<ExchangeWallet>,
+    ) {
+        for doc in exchange_wallets {
+            if self
+                .client_exchange_wallets
+                .find_one(doc! { "address": doc.address.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_exchange_wallets.insert_one(doc, None).await;
+                continue;
+            }
+
+            self.update_exchange_wallet(doc).await;
+        }
+    }
+
+    async fn update_exchange_wallet(&mut self, doc: ExchangeWallet) {
+        self.client_exchange_wallets
+            .update_one(
+                doc! { "address": doc.address },
+                doc! { "$set": {
+                    "exchange": doc.exchange.to_string(),
+                    "role": doc.role.to_string(),
+                    "status": doc.status.to_string(),
+                    "confidence": doc.confidence,
+                }},
+                None,
+            )
+            .await;
+    }
+
+    /// Proposes discovered deposit addresses. Confirmed wallets are left alone, the
+    /// confidence of proposals is refreshed.
+    pub async fn propose_exchange_wallets(&mut self, exchange_wallets: Vec<ExchangeWallet>) {
+        for doc in exchange_wallets {
+            let existing = self
+                .client_exchange_wallets
+                .find_one(doc! { "address": doc.address.clone() }, None)
+                .await;
+            let is_new = existing.is_none();
+            let Some(doc) = merge_proposed_exchange_wallet(existing, doc) else {
+                continue;
+            };
+
+            if is_new {
+                self.client_exchange_wallets.insert_one(doc, None).await;
+            } else {
+                self.update_exchange_wallet(doc).await;
+            }
+        }
+    }
+
+    /// Returns whether a proposed wallet was found and confirmed.
+    pub async fn confirm_exchange_wallet(&mut self, address: &str) -> bool {
+        let res = self
+            .client_exchange_wallets
+            .update_one(
+                doc! {
+                    "address": address,
+                    "status": ExchangeWalletStatus::Proposed.to_string(),
+                },
+                doc! { "$set": { "status": ExchangeWalletStatus::Confirmed.to_string() }},
+                None,
+            )
+            .await;
+
+        res.modified_count > 0
+    }
+
+    /// Confirmed wallet of the address, wallets stored before confirmation existed count
+    /// as confirmed.
+    pub async fn get_exchange_wallet_by_address(
+        &mut self,
+        address: &str,
+    ) -> Option<ExchangeWallet> {
+        let query = doc! {
+            "address": address,
+            "status": { "$ne": ExchangeWalletStatus::Proposed.to_string() },
+        };
+
+        self.client_exchange_wallets.find_one(query, None).await
+    }
+
+    pub async fn get_confirmed_exchange_wallets(&mut self) -> Vec<ExchangeWallet> {
+        let query = doc! {
+            "status": { "$ne": ExchangeWalletStatus::Proposed.to_string() },
+        };
+
+        self.client_exchange_wallets.find(query, None).await
+    }
+
+    pub async fn get_exchange_wallets_by_exchange(
+        &mut self,
+        exchange: Exchanges,
+    ) -> Vec<ExchangeWallet> {
+        self.client_exchange_wallets
+            .find(doc! { "exchange": exchange.to_string() }, None)
+            .await
+    }
+
+    pub async fn get_exchange_wallets(&mut self) -> Vec<ExchangeWallet> {
+        self.client_exchange_wallets.find(doc! {}, None).await
+    }
+}
+
+/// Wallet to store for a proposal, `None` when the registered wallet must stay as is.
+fn merge_proposed_exchange_wallet(
+    existing: Option<ExchangeWallet>,
+    proposed: ExchangeWallet,
+) -> Option<ExchangeWallet> {
+    let Some(existing) = existing else {
+        return Some(proposed);
+    };
+    if existing.status == ExchangeWalletStatus::Confirmed {
+        return None;
+    }
+
+    Some(ExchangeWallet {
+        exchange: proposed.exchange,
+        confidence: proposed.confidence,
+        ..existing
+    })
+}
+
+/// Reads the JSON list edited by `wallets import`.
+pub fn parse_exchange_wallets(content: &str) -> Option<Vec<ExchangeWallet>> {
+    serde_json::from_str(content).ok()
+}
+
+/// Writes the JSON list produced by `wallets export`.
+pub fn format_exchange_wallets(exchange_wallets: &[ExchangeWallet]) -> String {
+    serde_json::to_string_pretty(exchange_wallets).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_exchange_wallets, merge_proposed_exchange_wallet, parse_exchange_wallets};
+    use crate::{ExchangeWallet, ExchangeWalletRole, ExchangeWalletStatus, Exchanges};
+
+    fn get_exchange_wallet(
+        status: ExchangeWalletStatus,
+        confidence: Option<f64>,
+    ) -> ExchangeWallet {
+        ExchangeWallet {
+            address: "5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY".to_string(),
+            exchange: Exchanges::Mexc,
+            role: ExchangeWalletRole::Deposit,
+            status,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn exchange_wallets_file_round_trips() {
+        let exchange_wallets = vec![
+            get_exchange_wallet(ExchangeWalletStatus::Confirmed, None),
+            get_exchange_wallet(ExchangeWalletStatus::Proposed, Some(0.75)),
+        ];
+
+        let content = format_exchange_wallets(&exchange_wallets);
+
+        assert_eq!(parse_exchange_wallets(&content), Some(exchange_wallets));
+        assert_eq!(parse_exchange_wallets("{\"address\": \"5GNJ\"}"), None);
+    }
+
+    #[test]
+    fn exchange_wallets_file_defaults_to_confirmed() {
+        let content = r#"[{
+            "address": "5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY",
+            "exchange": "Mexc",
+            "role": "Deposit"
+        }]"#;
+
+        let exchange_wallets = parse_exchange_wallets(content).unwrap();
+
+        assert_eq!(
+            exchange_wallets,
+            vec![get_exchange_wallet(ExchangeWalletStatus::Confirmed, None)]
+        );
+    }
+
+    #[test]
+    fn proposals_never_override_confirmed_wallets() {
+        let proposed = ExchangeWallet {
+            exchange: Exchanges::Gate,
+            role: ExchangeWalletRole::Hot,
+            ..get_exchange_wallet(ExchangeWalletStatus::Proposed, Some(0.9))
+        };
+
+        let new = merge_proposed_exchange_wallet(None, proposed.clone());
+        assert_eq!(new, Some(proposed.clone()));
+
+        let confirmed = get_exchange_wallet(ExchangeWalletStatus::Confirmed, None);
+        let unchanged = merge_proposed_exchange_wallet(Some(confirmed), proposed.clone());
+        assert_eq!(unchanged, None);
+
+        let existing = get_exchange_wallet(ExchangeWalletStatus::Proposed, Some(0.5));
+        let refreshed = merge_proposed_exchange_wallet(Some(existing), proposed).unwrap();
+        assert_eq!(refreshed.exchange, Exchanges::Gate);
+        assert_eq!(refreshed.confidence, Some(0.9));
+        assert_eq!(refreshed.role, ExchangeWalletRole::Deposit);
+        assert_eq!(refreshed.status, ExchangeWalletStatus::Proposed);
+    }
+}
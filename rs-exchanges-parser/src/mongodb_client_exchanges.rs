@@ -101,4 +101,27 @@ impl MongoDbClientExchanges {
         let item = self.client_exchanges.find_one(query, options).await?;
         Some(item.trade_price)
     }
+
+    pub async fn get_usd_price_at(
+        &mut self,
+        primary_token: PrimaryToken,
+        secondary_token: SecondaryToken,
+        timestamp: i64,
+    ) -> Option<f64> {
+        let options = Some(
+            FindOneOptions::builder()
+                .sort(doc! {"trade_timestamp": -1i32})
+                .build(),
+        );
+        let query = doc! {
+            "primary_token": primary_token.to_string(),
+            "secondary_token": secondary_token.to_string(),
+            "trade_timestamp": {
+                "$lte": DateTime::from_millis(timestamp * 1000),
+            }
+        };
+
+        let item = self.client_exchanges.find_one(query, options).await?;
+        Some(item.trade_price)
+    }
 }
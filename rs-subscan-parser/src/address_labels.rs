@@ -0,0 +1,118 @@
+use crate::{mongodb_client_label::MongoDbClientLabel, Label};
+use log::error;
+use std::{fs, path::Path};
+use strum_macros::{Display, EnumString};
+
+#[derive(Clone, Debug, EnumString, Display, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum LabelFormat {
+    Csv,
+    Json,
+}
+
+impl LabelFormat {
+    pub fn from_path(path: &str) -> Option<LabelFormat> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        extension.parse().ok()
+    }
+}
+
+/// Imports labels from a CSV or JSON file, existing labels of the same address are
+/// replaced. Returns the number of labels imported.
+pub async fn import_labels(path: &str) -> Option<usize> {
+    let format = LabelFormat::from_path(path)?;
+    let content = fs::read_to_string(path)
+        .map_err(|e| error!(target: "address_labels", "Unable to read {path}: {e}"))
+        .ok()?;
+    let labels = parse_labels(&content, &format)?;
+
+    let labels_len = labels.len();
+    let mut mongodb_client_label = MongoDbClientLabel::new().await;
+    mongodb_client_label.import_or_update_labels(labels).await;
+
+    Some(labels_len)
+}
+
+/// Writes all labels into a CSV or JSON file. Returns the number of labels exported.
+pub async fn export_labels(path: &str) -> Option<usize> {
+    let format = LabelFormat::from_path(path)?;
+    let mut mongodb_client_label = MongoDbClientLabel::new().await;
+    let labels = mongodb_client_label.get_labels().await;
+
+    let content = format_labels(&labels, &format)?;
+    fs::write(path, content)
+        .map_err(|e| error!(target: "address_labels", "Unable to write {path}: {e}"))
+        .ok()?;
+
+    Some(labels.len())
+}
+
+/// Labels with a confidence outside of 0 to 1 fail the whole file.
+pub fn parse_labels(content: &str, format: &LabelFormat) -> Option<Vec<Label>> {
+    let labels = match format {
+        LabelFormat::Csv => csv::Reader::from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<Label>, _>>()
+            .map_err(|e| error!(target: "address_labels", "Invalid labels csv: {e}"))
+            .ok()?,
+        LabelFormat::Json => serde_json::from_str::<Vec<Label>>(content)
+            .map_err(|e| error!(target: "address_labels", "Invalid labels json: {e}"))
+            .ok()?,
+    };
+
+    if let Some(label) = labels.iter().find(|p| !(0.0..=1.0).contains(&p.confidence)) {
+        error!(target: "address_labels", "Invalid confidence of {}", label.address);
+        return None;
+    }
+
+    Some(labels)
+}
+
+pub fn format_labels(labels: &[Label], format: &LabelFormat) -> Option<String> {
+    match format {
+        LabelFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for label in labels {
+                writer.serialize(label).ok()?;
+            }
+            String::from_utf8(writer.into_inner().ok()?).ok()
+        }
+        LabelFormat::Json => serde_json::to_string_pretty(labels).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address_labels::{format_labels, parse_labels, LabelFormat},
+        subscan_parser::Network,
+        LabelCategory,
+    };
+
+    #[test]
+    fn labels_roundtrip_works() {
+        let content = "address,label,category,source,confidence\n\
+            5Treasury,Treasury,Treasury,manual,1.0\n\
+            5MarketMaker,Market Maker 1,MarketMaker,research,0.6\n";
+        let labels = parse_labels(content, &LabelFormat::Csv).unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[1].category, LabelCategory::MarketMaker);
+        assert_eq!(labels[1].confidence, 0.6);
+        assert_eq!(labels[1].network, Network::Alephzero);
+
+        for format in [LabelFormat::Csv, LabelFormat::Json] {
+            let content = format_labels(&labels, &format).unwrap();
+            assert_eq!(parse_labels(&content, &format).unwrap(), labels);
+        }
+
+        assert_eq!(
+            LabelFormat::from_path("labels.JSON"),
+            Some(LabelFormat::Json)
+        );
+        assert!(parse_labels(
+            "address,label,category,source,confidence\n5X,X,Other,manual,2\n",
+            &LabelFormat::Csv
+        )
+        .is_none());
+    }
+}
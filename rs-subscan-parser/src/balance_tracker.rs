@@ -0,0 +1,260 @@
+use crate::{
+    mongodb_client_balance::MongoDbClientBalance,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR},
+    Balance,
+};
+use bson::DateTime;
+use chrono::Utc;
+use futures::{stream::FuturesUnordered, StreamExt};
+use itertools::Itertools;
+use rs_exchanges_parser::mongodb_client_exchange_wallets::MongoDbClientExchangeWallets;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
+
+pub static BALANCE_REFRESH_INTERVAL_SECS: u64 = 60 * 60;
+static TRACKED_OPERATION_MIN_USD: f64 = 100_000.0;
+static BALANCE_REFRESH_BATCH: i64 = 200;
+static DEFAULT_BALANCE_CHANGE_ALERT_SHARE: f64 = 0.1;
+static BALANCE_CHANGE_ALERT_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Starts tracking wallets seen in large operations since the last run and refreshes
+/// tracked balances older than the refresh interval. Exchange wallets are not tracked.
+/// Returns the number of balances refreshed.
+pub async fn refresh_balances() -> Option<usize> {
+    let network = Network::from_env();
+    let alert_share = env::var("BALANCE_CHANGE_ALERT_SHARE")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_BALANCE_CHANGE_ALERT_SHARE);
+    let mut subscan_parser = SubscanParser::new(network.clone()).await;
+    let total_issuance = subscan_parser.parse_subscan_total_issuance().await?;
+
+    let mut mongodb_client_exchange_wallets = MongoDbClientExchangeWallets::new().await;
+    let exchange_wallets = mongodb_client_exchange_wallets
+        .get_confirmed_exchange_wallets()
+        .await
+        .into_iter()
+        .map(|p| p.address)
+        .collect::<HashSet<_>>();
+
+    let from_timestamp = Utc::now().timestamp() - BALANCE_REFRESH_INTERVAL_SECS as i64;
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let new_addresses = mongodb_client_subscan
+        .get_filtered_operations(&network, from_timestamp, None)
+        .await
+        .into_iter()
+        .filter(|p| p.operation_usd >= TRACKED_OPERATION_MIN_USD)
+        .flat_map(|p| [p.from_wallet, p.to_wallet])
+        .filter(|p| !SubscanParser::is_address_empty(p) && !exchange_wallets.contains(p))
+        .collect::<Vec<_>>();
+
+    let mut mongodb_client_balance = MongoDbClientBalance::new().await;
+    let stale_addresses = mongodb_client_balance
+        .get_stale_balances(
+            DateTime::from_millis(from_timestamp * 1_000),
+            BALANCE_REFRESH_BATCH,
+        )
+        .await
+        .into_iter()
+        .map(|p| p.address);
+    let addresses = new_addresses
+        .into_iter()
+        .chain(stale_addresses)
+        .unique()
+        .collect::<Vec<_>>();
+
+    let previous_balances = mongodb_client_balance
+        .get_balances_by_addresses(addresses.clone())
+        .await
+        .into_iter()
+        .map(|p| (p.address.clone(), p))
+        .collect::<HashMap<_, _>>();
+
+    let mut tasks = FuturesUnordered::new();
+    for a in addresses {
+        let network = network.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut subscan_parser = SubscanParser::new(network).await;
+            subscan_parser
+                .parse_subscan_balance(&a, total_issuance)
+                .await
+        }));
+    }
+
+    let mut balances = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some(mut balance)) = res else {
+            continue;
+        };
+
+        track_balance_change(
+            previous_balances.get(&balance.address),
+            &mut balance,
+            alert_share,
+        );
+        balances.push(balance);
+    }
+
+    let balances_len = balances.len();
+    mongodb_client_balance
+        .import_or_update_balances(balances)
+        .await;
+
+    Some(balances_len)
+}
+
+/// Keeps the last alert of the wallet, or raises a new one when the total moved by more
+/// than `alert_share` since the last alert or the start of the alert window, so slow
+/// moves over several refreshes add up. A new window starts once the baseline is older
+/// than BALANCE_CHANGE_ALERT_WINDOW_SECS.
+pub fn track_balance_change(previous: Option<&Balance>, balance: &mut Balance, alert_share: f64) {
+    let Some(previous) = previous else {
+        balance.baseline_total = Some(balance.total);
+        balance.baseline_timestamp = Some(balance.updated_timestamp);
+        return;
+    };
+
+    balance.previous_total = previous.previous_total;
+    balance.changed_timestamp = previous.changed_timestamp;
+
+    // wallets tracked before baselines were kept start their window at the previous refresh
+    let window_start =
+        balance.updated_timestamp.timestamp_millis() - BALANCE_CHANGE_ALERT_WINDOW_SECS * 1_000;
+    let (baseline_total, baseline_timestamp) =
+        match (previous.baseline_total, previous.baseline_timestamp) {
+            (Some(total), Some(timestamp)) if timestamp.timestamp_millis() >= window_start => {
+                (total, timestamp)
+            }
+            _ => (previous.total, previous.updated_timestamp),
+        };
+    balance.baseline_total = Some(baseline_total);
+    balance.baseline_timestamp = Some(baseline_timestamp);
+
+    if baseline_total > 0.0
+        && ((balance.total - baseline_total) / baseline_total).abs() > alert_share
+    {
+        balance.previous_total = Some(baseline_total);
+        balance.changed_timestamp = Some(balance.updated_timestamp);
+        balance.baseline_total = Some(balance.total);
+        balance.baseline_timestamp = Some(balance.updated_timestamp);
+    }
+}
+
+/// Balance of a subscan search `account`. Subscan gives the balance and reserved amounts
+/// in AZERO and the bonded amount in plancks.
+pub fn convert_account_balance(
+    address: &str,
+    account: &Value,
+    total_issuance: f64,
+    network: &Network,
+) -> Option<Balance> {
+    let get_f64 = |name: &str| match account.get(name)? {
+        Value::String(s) => s.parse::<f64>().ok(),
+        v => v.as_f64(),
+    };
+
+    let total = get_f64("balance")?;
+    let reserved = get_f64("reserved").unwrap_or_default();
+    let staked = get_f64("bonded").unwrap_or_default() / AZERO_DENOMINATOR;
+    let supply_share = if total_issuance > 0.0 {
+        total / total_issuance
+    } else {
+        0.0
+    };
+
+    Some(Balance {
+        address: address.to_string(),
+        free: total - reserved,
+        reserved,
+        staked,
+        total,
+        supply_share,
+        previous_total: None,
+        changed_timestamp: None,
+        updated_timestamp: DateTime::now(),
+        baseline_total: None,
+        baseline_timestamp: None,
+        network: network.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        balance_tracker::{
+            convert_account_balance, track_balance_change, BALANCE_CHANGE_ALERT_WINDOW_SECS,
+            DEFAULT_BALANCE_CHANGE_ALERT_SHARE,
+        },
+        subscan_parser::Network,
+        Balance,
+    };
+    use bson::DateTime;
+    use serde_json::json;
+
+    fn refresh(previous: &Balance, total: f64, after_secs: i64) -> Balance {
+        let mut balance = previous.clone();
+        balance.total = total;
+        balance.updated_timestamp = DateTime::from_millis(
+            previous.updated_timestamp.timestamp_millis() + after_secs * 1_000,
+        );
+        track_balance_change(
+            Some(previous),
+            &mut balance,
+            DEFAULT_BALANCE_CHANGE_ALERT_SHARE,
+        );
+        balance
+    }
+
+    #[test]
+    fn track_balance_change_works() {
+        let account = json!({
+            "address": "5Whale",
+            "balance": "3200000.5",
+            "reserved": "0.5",
+            "bonded": "1000000000000000000",
+        });
+        let mut previous =
+            convert_account_balance("5Whale", &account, 400_000_000.0, &Network::Alephzero)
+                .unwrap();
+        assert_eq!(previous.free, 3_200_000.0);
+        assert_eq!(previous.staked, 1_000_000.0);
+        assert_eq!(previous.supply_share, 3_200_000.5 / 400_000_000.0);
+        track_balance_change(None, &mut previous, DEFAULT_BALANCE_CHANGE_ALERT_SHARE);
+        assert_eq!(previous.baseline_total, Some(3_200_000.5));
+
+        // a small move keeps no alert
+        let balance = refresh(&previous, 3_300_000.0, 3_600);
+        assert_eq!(balance.changed_timestamp, None);
+        assert_eq!(balance.baseline_total, Some(3_200_000.5));
+
+        let balance = refresh(&previous, 1_600_000.0, 3_600);
+        assert_eq!(balance.previous_total, Some(3_200_000.5));
+        assert_eq!(balance.changed_timestamp, Some(balance.updated_timestamp));
+        assert_eq!(balance.baseline_total, Some(1_600_000.0));
+        assert!(balance.get_change_share().unwrap() < -0.49);
+
+        // hourly moves below the share add up against the window start
+        let first = refresh(&previous, 3_040_000.0, 3_600);
+        let second = refresh(&first, 2_880_000.0, 3_600);
+        assert_eq!(first.changed_timestamp, None);
+        assert_eq!(second.previous_total, Some(3_200_000.5));
+        assert_eq!(second.changed_timestamp, Some(second.updated_timestamp));
+
+        // a stricter share alerts on the small move
+        let mut balance = previous.clone();
+        balance.total = 3_300_000.0;
+        track_balance_change(Some(&previous), &mut balance, 0.01);
+        assert_eq!(balance.changed_timestamp, Some(balance.updated_timestamp));
+
+        // an expired window starts over from the previous refresh
+        let first = refresh(&previous, 3_040_000.0, BALANCE_CHANGE_ALERT_WINDOW_SECS + 1);
+        assert_eq!(first.baseline_total, Some(3_200_000.5));
+        assert_eq!(first.baseline_timestamp, Some(previous.updated_timestamp));
+        let second = refresh(&first, 2_880_000.0, 3_600);
+        assert_eq!(second.changed_timestamp, None);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    contract_events_parser::{load_contract_abi, parse_contract_events_since_cursor},
+    ink_event_decoder::InkContractAbi,
+    substrate_rpc_decoder::ContractEvent,
+    substrate_rpc_parser::SubstrateRpcParser,
+    BridgeTransfer, SubscanCursor,
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, env, fs};
+
+pub static BRIDGE_EVENTS_CURSOR_STREAM: &str = "bridge_events";
+static COUNTERPARTY_ARGS: [&str; 4] = ["dest_receiver_address", "receiver", "recipient", "to"];
+
+/// One entry of the `BRIDGES_PATH` json file. Transfers to and from `address` are bridge
+/// transfers to `chain`. Bridges with a `metadata_path`, relative to the file itself,
+/// emit `request_event` with the receiver on the other chain for outgoing transfers.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BridgeConfig {
+    pub address: String,
+    pub name: String,
+    pub chain: String,
+    #[serde(default)]
+    pub metadata_path: Option<String>,
+    #[serde(default)]
+    pub request_event: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Bridge {
+    pub config: BridgeConfig,
+    pub abi: Option<InkContractAbi>,
+}
+
+impl Bridge {
+    pub fn get_bridge_transfer(&self) -> BridgeTransfer {
+        BridgeTransfer {
+            bridge: self.config.name.clone(),
+            chain: Some(self.config.chain.clone()),
+            counterparty: None,
+        }
+    }
+}
+
+/// Configured bridges by address, `None` when `BRIDGES_PATH` is not set. A bridge with
+/// an invalid metadata fails the whole file.
+pub fn load_bridges_from_env() -> Option<HashMap<String, Bridge>> {
+    load_bridges(&env::var("BRIDGES_PATH").ok()?)
+}
+
+pub fn load_bridges(path: &str) -> Option<HashMap<String, Bridge>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| error!(target: "bridge_parser", "Unable to read {path}: {e}"))
+        .ok()?;
+    let configs = serde_json::from_str::<Vec<BridgeConfig>>(&content)
+        .map_err(|e| error!(target: "bridge_parser", "Invalid bridges json: {e}"))
+        .ok()?;
+
+    configs
+        .into_iter()
+        .map(|config| {
+            let abi = match &config.metadata_path {
+                Some(metadata_path) => Some(load_contract_abi(path, metadata_path)?),
+                None => None,
+            };
+            Some((config.address.clone(), Bridge { config, abi }))
+        })
+        .collect()
+}
+
+/// Receivers on the other chain of outgoing transfers found after the bridge events
+/// cursor, by extrinsic index.
+pub async fn parse_bridge_counterparties(
+    substrate_rpc_parser: &mut SubstrateRpcParser,
+    bridges: &HashMap<String, Bridge>,
+    head_block_number: u64,
+) -> (Vec<(String, String)>, SubscanCursor) {
+    parse_contract_events_since_cursor(
+        substrate_rpc_parser,
+        BRIDGE_EVENTS_CURSOR_STREAM,
+        head_block_number,
+        |e| convert_bridge_request(bridges.get(&e.contract)?, e),
+    )
+    .await
+}
+
+pub fn convert_bridge_request(
+    bridge: &Bridge,
+    contract_event: &ContractEvent,
+) -> Option<(String, String)> {
+    let (label, args) = bridge.abi.as_ref()?.decode_event(&contract_event.data)?;
+    if Some(&label) != bridge.config.request_event.as_ref() {
+        return None;
+    }
+
+    let counterparty = COUNTERPARTY_ARGS
+        .iter()
+        .find_map(|p| format_counterparty(args.get(*p)?))?;
+
+    Some((contract_event.extrinsic_index.clone()?, counterparty))
+}
+
+/// Substrate accounts are already ss58 addresses. Raw bytes are shown as hex, 32 bytes
+/// starting with 12 zero bytes hold an EVM address.
+fn format_counterparty(value: &Value) -> Option<String> {
+    if let Value::String(address) = value {
+        return Some(address.clone());
+    }
+
+    let bytes = value
+        .as_array()?
+        .iter()
+        .map(|p| u8::try_from(p.as_u64()?).ok())
+        .collect::<Option<Vec<_>>>()?;
+    let bytes = match bytes.len() {
+        32 if bytes[..12].iter().all(|p| *p == 0) => &bytes[12..],
+        _ => &bytes[..],
+    };
+
+    Some(format!("0x{}", hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bridge_parser::{convert_bridge_request, Bridge, BridgeConfig},
+        ink_event_decoder::InkContractAbi,
+        substrate_rpc_decoder::ContractEvent,
+    };
+    use bson::DateTime;
+    use parity_scale_codec::Encode;
+    use serde_json::json;
+
+    #[test]
+    fn convert_bridge_request_works() {
+        let metadata = json!({
+            "version": "4",
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "u128" } } },
+                { "id": 1, "type": { "def": { "array": { "len": 32, "type": 2 } } } },
+                { "id": 2, "type": { "def": { "primitive": "u8" } } },
+            ],
+            "spec": {
+                "events": [
+                    {
+                        "label": "CrosschainTransferRequest",
+                        "args": [
+                            { "label": "dest_token_address", "type": { "type": 1 } },
+                            { "label": "amount", "type": { "type": 0 } },
+                            { "label": "dest_receiver_address", "type": { "type": 1 } },
+                            { "label": "request_nonce", "type": { "type": 0 } },
+                        ],
+                    },
+                ],
+            },
+        });
+        let bridge = Bridge {
+            config: BridgeConfig {
+                address: "5Most".to_string(),
+                name: "Most".to_string(),
+                chain: "Ethereum".to_string(),
+                metadata_path: Some("most.json".to_string()),
+                request_event: Some("CrosschainTransferRequest".to_string()),
+            },
+            abi: InkContractAbi::from_metadata(&metadata, 42),
+        };
+
+        let mut receiver = [0u8; 32];
+        receiver[12..].copy_from_slice(&[0xab; 20]);
+        let mut data = vec![0u8];
+        data.extend_from_slice(&[1u8; 32]);
+        data.extend(5_000_000_000_000_000u128.encode());
+        data.extend_from_slice(&receiver);
+        data.extend(7u128.encode());
+        let contract_event = ContractEvent {
+            block_number: 100,
+            event_index: "100-5".to_string(),
+            extrinsic_index: Some("100-2".to_string()),
+            event_timestamp: DateTime::from_millis(0),
+            contract: "5Most".to_string(),
+            data,
+        };
+
+        let (extrinsic_index, counterparty) =
+            convert_bridge_request(&bridge, &contract_event).unwrap();
+        assert_eq!(extrinsic_index, "100-2");
+        assert_eq!(counterparty, format!("0x{}", "ab".repeat(20)));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    ink_event_decoder::InkContractAbi,
+    subscan_cursor::get_cursor_or_default,
+    subscan_parser::{Network, EMPTY_ADDRESS},
+    subscan_price::set_usd_prices,
+    substrate_rpc_decoder::ContractEvent,
+    substrate_rpc_parser::SubstrateRpcParser,
+    SubscanCursor, TokenOperation, TokenOperationType,
+};
+use bson::DateTime;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{cmp, collections::HashMap, fs, path::Path};
+
+pub static CONTRACT_EVENTS_CURSOR_STREAM: &str = "psp22_events";
+static CONTRACT_EVENTS_MAX_BLOCKS_PER_ITERATION: u64 = 100;
+static CONTRACT_EVENTS_INITIAL_LOOKBACK_BLOCKS: u64 = 1_000;
+
+/// Price source of a token: wrapped AZERO follows the AZERO price, stablecoins are
+/// valued one to one.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenPeg {
+    Azero,
+    Usd,
+}
+
+/// One entry of the `PSP22_CONTRACTS_PATH` json file. `metadata_path` is relative to the
+/// file itself.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Psp22ContractConfig {
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u32,
+    pub metadata_path: String,
+    #[serde(default)]
+    pub peg: Option<TokenPeg>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Psp22Contract {
+    pub config: Psp22ContractConfig,
+    pub abi: InkContractAbi,
+}
+
+/// Configured contracts by address. A contract with a missing or unsupported metadata
+/// fails the whole file.
+pub fn load_psp22_contracts(path: &str) -> Option<HashMap<String, Psp22Contract>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| error!(target: "contract_events_parser", "Unable to read {path}: {e}"))
+        .ok()?;
+    let configs = serde_json::from_str::<Vec<Psp22ContractConfig>>(&content)
+        .map_err(|e| error!(target: "contract_events_parser", "Invalid contracts json: {e}"))
+        .ok()?;
+
+    configs
+        .into_iter()
+        .map(|config| {
+            let abi = load_contract_abi(path, &config.metadata_path)?;
+            Some((config.address.clone(), Psp22Contract { config, abi }))
+        })
+        .collect()
+}
+
+/// Reads the ink! metadata at `metadata_path`, relative to the config file at `path`.
+pub fn load_contract_abi(path: &str, metadata_path: &str) -> Option<InkContractAbi> {
+    let metadata_path = Path::new(path)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(metadata_path);
+    let metadata = fs::read_to_string(&metadata_path)
+        .ok()
+        .and_then(|p| serde_json::from_str::<Value>(&p).ok());
+    let abi = metadata
+        .and_then(|p| InkContractAbi::from_metadata(&p, Network::from_env().get_ss58_prefix()));
+    if abi.is_none() {
+        error!(
+            target: "contract_events_parser", "Invalid metadata {}",
+            metadata_path.display(),
+        );
+    }
+
+    abi
+}
+
+/// Converts PSP22 events of the configured contracts found after the contract events
+/// cursor. Events of other contracts are skipped.
+pub async fn parse_contract_events(
+    substrate_rpc_parser: &mut SubstrateRpcParser,
+    contracts: &HashMap<String, Psp22Contract>,
+    head_block_number: u64,
+) -> Option<(Vec<TokenOperation>, SubscanCursor)> {
+    let network = Network::from_env();
+    let (mut token_operations, cursor) = parse_contract_events_since_cursor(
+        substrate_rpc_parser,
+        CONTRACT_EVENTS_CURSOR_STREAM,
+        head_block_number,
+        |e| convert_contract_event(contracts.get(&e.contract)?, e, &network),
+    )
+    .await;
+
+    set_token_usd_prices(&mut token_operations, contracts).await?;
+
+    Some((token_operations, cursor))
+}
+
+/// Reads finalized blocks after the cursor of the stream, at most
+/// `CONTRACT_EVENTS_MAX_BLOCKS_PER_ITERATION` per call, and converts their contract events.
+/// Events `convert` returns nothing for are skipped.
+pub async fn parse_contract_events_since_cursor<T, F>(
+    substrate_rpc_parser: &mut SubstrateRpcParser,
+    stream_name: &str,
+    head_block_number: u64,
+    mut convert: F,
+) -> (Vec<T>, SubscanCursor)
+where
+    F: FnMut(&ContractEvent) -> Option<T>,
+{
+    let mut cursor = get_cursor_or_default(
+        stream_name,
+        head_block_number.saturating_sub(CONTRACT_EVENTS_INITIAL_LOOKBACK_BLOCKS),
+    )
+    .await;
+    cursor.head_block_number = head_block_number;
+    cursor.updated_timestamp = DateTime::now();
+
+    let to_block_number = cmp::min(
+        head_block_number,
+        cursor.block_number + CONTRACT_EVENTS_MAX_BLOCKS_PER_ITERATION,
+    );
+
+    let mut items = Vec::new();
+    while cursor.block_number < to_block_number {
+        let block_number = cursor.block_number + 1;
+
+        // keeping the cursor where it is, next iteration will retry the same block
+        let Some(contract_events) = substrate_rpc_parser
+            .parse_block_contract_events(block_number)
+            .await
+        else {
+            error!(target: "contract_events_parser", "Failed to parse block {block_number}.");
+            break;
+        };
+
+        for e in contract_events.iter() {
+            if let Some(item) = convert(e) {
+                items.push(item);
+                cursor.extrinsic_index = e.event_index.clone();
+            }
+        }
+        cursor.block_number = block_number;
+    }
+
+    info!(
+        target: "contract_events_parser", "Stream {} is at block {} of {} (lag {} blocks), found {} items",
+        stream_name,
+        cursor.block_number,
+        cursor.head_block_number,
+        cursor.get_lag(),
+        items.len(),
+    );
+
+    (items, cursor)
+}
+
+/// PSP22 `Transfer` and `Approval` events, minted and burned tokens have an empty
+/// side. Other events of the contract are not converted.
+pub fn convert_contract_event(
+    contract: &Psp22Contract,
+    contract_event: &ContractEvent,
+    network: &Network,
+) -> Option<TokenOperation> {
+    let (label, args) = contract.abi.decode_event(&contract_event.data)?;
+
+    let (operation_type, from_wallet, to_wallet, amount) = match label.as_str() {
+        "Transfer" => (
+            TokenOperationType::Transfer,
+            get_address(&args, "from"),
+            get_address(&args, "to"),
+            args.get("value")?,
+        ),
+        "Approval" => (
+            TokenOperationType::Approval,
+            get_address(&args, "owner"),
+            get_address(&args, "spender"),
+            args.get("amount").or(args.get("value"))?,
+        ),
+        _ => return None,
+    };
+    let amount = amount.as_str()?.parse::<u128>().ok()?;
+
+    Some(TokenOperation {
+        block_number: contract_event.block_number,
+        event_index: contract_event.event_index.clone(),
+        extrinsic_index: contract_event.extrinsic_index.clone(),
+        operation_timestamp: contract_event.event_timestamp,
+        contract: contract_event.contract.clone(),
+        token_symbol: contract.config.symbol.clone(),
+        decimals: contract.config.decimals,
+        operation_type,
+        from_wallet,
+        to_wallet,
+        operation_quantity: amount as f64 / 10f64.powi(contract.config.decimals as i32),
+        operation_usd: None,
+        network: network.clone(),
+    })
+}
+
+fn get_address(args: &Map<String, Value>, name: &str) -> String {
+    args.get(name)
+        .and_then(|p| p.as_str())
+        .unwrap_or(EMPTY_ADDRESS)
+        .to_string()
+}
+
+/// Tokens without a peg keep no USD value.
+async fn set_token_usd_prices(
+    token_operations: &mut [TokenOperation],
+    contracts: &HashMap<String, Psp22Contract>,
+) -> Option<()> {
+    let get_peg = |p: &TokenOperation| contracts.get(&p.contract)?.config.peg.clone();
+
+    let mut azero_operations = Vec::new();
+    for t in token_operations.iter_mut() {
+        match get_peg(t) {
+            Some(TokenPeg::Usd) => t.operation_usd = Some(t.operation_quantity),
+            Some(TokenPeg::Azero) => azero_operations.push(t.clone()),
+            None => (),
+        }
+    }
+    if azero_operations.is_empty() {
+        return Some(());
+    }
+
+    set_usd_prices(&mut azero_operations).await?;
+    let prices = azero_operations
+        .into_iter()
+        .map(|p| (p.event_index, p.operation_usd))
+        .collect::<HashMap<_, _>>();
+    for t in token_operations.iter_mut() {
+        if let Some(usd) = prices.get(&t.event_index) {
+            t.operation_usd = *usd;
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        contract_events_parser::{convert_contract_event, Psp22Contract, Psp22ContractConfig},
+        ink_event_decoder::InkContractAbi,
+        subscan_parser::{Network, EMPTY_ADDRESS},
+        substrate_rpc_decoder::ContractEvent,
+        TokenOperationType,
+    };
+    use bson::DateTime;
+    use parity_scale_codec::Encode;
+    use serde_json::json;
+    use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+
+    #[test]
+    fn convert_contract_event_works() {
+        let metadata = json!({
+            "version": "4",
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "u128" } } },
+                { "id": 1, "type": { "def": { "array": { "len": 32, "type": 2 } } } },
+                { "id": 2, "type": { "def": { "primitive": "u8" } } },
+                {
+                    "id": 3,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "type": 1, "typeName": "[u8; 32]" }] } },
+                        "path": ["ink_primitives", "types", "AccountId"],
+                    },
+                },
+                {
+                    "id": 4,
+                    "type": {
+                        "def": {
+                            "variant": {
+                                "variants": [
+                                    { "index": 0, "name": "None" },
+                                    { "index": 1, "name": "Some", "fields": [{ "type": 3 }] },
+                                ],
+                            },
+                        },
+                        "path": ["Option"],
+                    },
+                },
+            ],
+            "spec": {
+                "events": [
+                    {
+                        "label": "Transfer",
+                        "args": [
+                            { "label": "from", "type": { "type": 4 } },
+                            { "label": "to", "type": { "type": 4 } },
+                            { "label": "value", "type": { "type": 0 } },
+                        ],
+                    },
+                    {
+                        "label": "Approval",
+                        "args": [
+                            { "label": "owner", "type": { "type": 3 } },
+                            { "label": "spender", "type": { "type": 3 } },
+                            { "label": "value", "type": { "type": 0 } },
+                        ],
+                    },
+                ],
+            },
+        });
+        let contract = Psp22Contract {
+            config: Psp22ContractConfig {
+                address: "5Token".to_string(),
+                symbol: "USDT".to_string(),
+                decimals: 6,
+                metadata_path: "psp22.json".to_string(),
+                peg: None,
+            },
+            abi: InkContractAbi::from_metadata(&metadata, 42).unwrap(),
+        };
+
+        // a mint: no sender, 1.5 USDT to the receiver
+        let receiver = [7u8; 32];
+        let mut data = vec![0u8, 0u8, 1u8];
+        data.extend_from_slice(&receiver);
+        data.extend(1_500_000u128.encode());
+        let contract_event = ContractEvent {
+            block_number: 100,
+            event_index: "100-3".to_string(),
+            extrinsic_index: Some("100-2".to_string()),
+            event_timestamp: DateTime::from_millis(0),
+            contract: "5Token".to_string(),
+            data,
+        };
+
+        let token_operation =
+            convert_contract_event(&contract, &contract_event, &Network::Alephzero).unwrap();
+        assert_eq!(token_operation.operation_type, TokenOperationType::Transfer);
+        assert_eq!(token_operation.from_wallet, EMPTY_ADDRESS);
+        assert_eq!(
+            token_operation.to_wallet,
+            AccountId32::from(receiver).to_ss58check_with_version(Ss58AddressFormat::custom(42))
+        );
+        assert_eq!(token_operation.operation_quantity, 1.5);
+        assert_eq!(token_operation.token_symbol, "USDT");
+
+        // truncated event data is not converted
+        let mut contract_event = contract_event;
+        contract_event.data.truncate(10);
+        assert!(convert_contract_event(&contract, &contract_event, &Network::Alephzero).is_none());
+    }
+}
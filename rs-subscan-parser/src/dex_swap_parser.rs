@@ -0,0 +1,213 @@
+use crate::{
+    contract_events_parser::{load_contract_abi, parse_contract_events_since_cursor},
+    ink_event_decoder::InkContractAbi,
+    substrate_rpc_decoder::ContractEvent,
+    substrate_rpc_parser::SubstrateRpcParser,
+    SubscanCursor,
+};
+use log::error;
+use rs_exchanges_parser::{ExchangeTrade, Exchanges, PrimaryToken, SecondaryToken, TradeType};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fs};
+
+pub static DEX_SWAPS_CURSOR_STREAM: &str = "dex_swaps";
+
+/// One entry of the `DEX_PAIRS_PATH` json file, an AMM pair of wrapped AZERO and a
+/// stablecoin. `metadata_path` is relative to the file itself.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DexPairConfig {
+    pub address: String,
+    pub exchange: Exchanges,
+    pub metadata_path: String,
+    pub secondary_token: SecondaryToken,
+    /// Wrapped AZERO is token 0 of the pair, otherwise token 1.
+    pub azero_is_token_0: bool,
+    pub azero_decimals: u32,
+    pub secondary_decimals: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct DexPair {
+    pub config: DexPairConfig,
+    pub abi: InkContractAbi,
+}
+
+/// Configured pairs by address. Pairs of exchanges that are not a DEX or with a missing
+/// metadata fail the whole file.
+pub fn load_dex_pairs(path: &str) -> Option<HashMap<String, DexPair>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| error!(target: "dex_swap_parser", "Unable to read {path}: {e}"))
+        .ok()?;
+    let configs = serde_json::from_str::<Vec<DexPairConfig>>(&content)
+        .map_err(|e| error!(target: "dex_swap_parser", "Invalid pairs json: {e}"))
+        .ok()?;
+
+    configs
+        .into_iter()
+        .map(|config| {
+            if !config.exchange.is_dex() {
+                error!(target: "dex_swap_parser", "{} is not a DEX", config.exchange);
+                return None;
+            }
+
+            let abi = load_contract_abi(path, &config.metadata_path)?;
+            Some((config.address.clone(), DexPair { config, abi }))
+        })
+        .collect()
+}
+
+/// Converts swaps of the configured pairs found after the DEX swaps cursor.
+pub async fn parse_dex_swaps(
+    substrate_rpc_parser: &mut SubstrateRpcParser,
+    pairs: &HashMap<String, DexPair>,
+    head_block_number: u64,
+) -> (Vec<ExchangeTrade>, SubscanCursor) {
+    parse_contract_events_since_cursor(
+        substrate_rpc_parser,
+        DEX_SWAPS_CURSOR_STREAM,
+        head_block_number,
+        |e| convert_swap_event(pairs.get(&e.contract)?, e),
+    )
+    .await
+}
+
+/// Uniswap V2 style `Swap` event: AZERO leaving the pool is a buy, AZERO entering it is a
+/// sell. The price is the stablecoin amount paid per AZERO.
+pub fn convert_swap_event(pair: &DexPair, contract_event: &ContractEvent) -> Option<ExchangeTrade> {
+    let (label, args) = pair.abi.decode_event(&contract_event.data)?;
+    if label != "Swap" {
+        return None;
+    }
+
+    let (azero_token, secondary_token) = if pair.config.azero_is_token_0 {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    let azero_in = get_amount(&args, azero_token, "in", pair.config.azero_decimals)?;
+    let azero_out = get_amount(&args, azero_token, "out", pair.config.azero_decimals)?;
+    let secondary_in = get_amount(&args, secondary_token, "in", pair.config.secondary_decimals)?;
+    let secondary_out = get_amount(
+        &args,
+        secondary_token,
+        "out",
+        pair.config.secondary_decimals,
+    )?;
+
+    let (trade_type, trade_quantity, secondary_quantity) = if azero_out > azero_in {
+        (
+            TradeType::IsBuy,
+            azero_out - azero_in,
+            secondary_in - secondary_out,
+        )
+    } else {
+        (
+            TradeType::IsSell,
+            azero_in - azero_out,
+            secondary_out - secondary_in,
+        )
+    };
+    if trade_quantity <= 0.0 || secondary_quantity <= 0.0 {
+        return None;
+    }
+
+    let mut exchange_trade = ExchangeTrade {
+        hash: String::new(),
+        trade_timestamp: contract_event.event_timestamp,
+        trade_quantity,
+        trade_price: secondary_quantity / trade_quantity,
+        trade_type,
+        primary_token: PrimaryToken::Azero,
+        secondary_token: pair.config.secondary_token.clone(),
+        exchange: pair.config.exchange.clone(),
+    };
+    exchange_trade.set_event_hash(&contract_event.event_index);
+
+    Some(exchange_trade)
+}
+
+/// Pair contracts name the amounts either `amount_0_in` or `amount0_in`.
+fn get_amount(args: &Map<String, Value>, token: u8, direction: &str, decimals: u32) -> Option<f64> {
+    let amount = args
+        .get(&format!("amount_{token}_{direction}"))
+        .or(args.get(&format!("amount{token}_{direction}")))?
+        .as_str()?
+        .parse::<u128>()
+        .ok()?;
+
+    Some(amount as f64 / 10f64.powi(decimals as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dex_swap_parser::{convert_swap_event, DexPair, DexPairConfig},
+        ink_event_decoder::InkContractAbi,
+        substrate_rpc_decoder::ContractEvent,
+    };
+    use bson::DateTime;
+    use rs_exchanges_parser::{Exchanges, SecondaryToken, TradeType};
+    use serde_json::Value;
+
+    #[test]
+    fn convert_swap_event_works() {
+        let fixture =
+            serde_json::from_str::<Value>(include_str!("../tests/fixtures/dex_swap_events.json"))
+                .unwrap();
+        let contract_events = fixture["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| ContractEvent {
+                block_number: p["block_number"].as_u64().unwrap(),
+                event_index: p["event_index"].as_str().unwrap().to_string(),
+                extrinsic_index: p["extrinsic_index"].as_str().map(|p| p.to_string()),
+                event_timestamp: DateTime::from_millis(p["event_timestamp"].as_i64().unwrap()),
+                contract: p["contract"].as_str().unwrap().to_string(),
+                data: hex::decode(&p["data"].as_str().unwrap()[2..]).unwrap(),
+            })
+            .collect::<Vec<_>>();
+        let pair = DexPair {
+            config: DexPairConfig {
+                address: contract_events[0].contract.clone(),
+                exchange: Exchanges::Common,
+                metadata_path: "pair.json".to_string(),
+                secondary_token: SecondaryToken::Usdt,
+                azero_is_token_0: true,
+                azero_decimals: 12,
+                secondary_decimals: 6,
+            },
+            abi: InkContractAbi::from_metadata(&fixture["metadata"], 42).unwrap(),
+        };
+
+        // the `Sync` in between is not a trade
+        let exchange_trades = contract_events
+            .iter()
+            .filter_map(|p| convert_swap_event(&pair, p))
+            .collect::<Vec<_>>();
+        assert_eq!(exchange_trades.len(), 2);
+
+        assert_eq!(exchange_trades[0].trade_type, TradeType::IsSell);
+        assert_eq!(exchange_trades[0].trade_quantity, 10_000.0);
+        assert_eq!(exchange_trades[0].trade_price, 0.45);
+        assert_eq!(exchange_trades[0].exchange, Exchanges::Common);
+
+        assert_eq!(exchange_trades[1].trade_type, TradeType::IsBuy);
+        assert_eq!(exchange_trades[1].trade_quantity, 2_000.0);
+        assert_eq!(exchange_trades[1].trade_price, 0.45);
+        assert_ne!(exchange_trades[0].hash, exchange_trades[1].hash);
+
+        // an equal swap later in the same block is a trade of its own
+        let repeated_swap = ContractEvent {
+            event_index: "61000000-16".to_string(),
+            ..contract_events[0].clone()
+        };
+        let repeated_trade = convert_swap_event(&pair, &repeated_swap).unwrap();
+        assert_eq!(
+            repeated_trade.trade_timestamp,
+            exchange_trades[0].trade_timestamp
+        );
+        assert_ne!(repeated_trade.hash, exchange_trades[0].hash);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::substrate_rpc_client::parse_hex_number;
+use log::error;
+use reqwest::header::{HeaderMap, HeaderValue};
+use rs_utils::clients::http_client::HttpClient;
+use serde_json::{json, Value};
+use std::env;
+
+/// keccak256 of `Transfer(address,address,uint256)`
+pub static ERC20_TRANSFER_TOPIC: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// Ethereum JSON-RPC client of the Aleph Zero EVM layer.
+#[derive(Clone, Debug)]
+pub struct EvmRpcClient {
+    http_client: HttpClient,
+    rpc_url: String,
+}
+
+impl EvmRpcClient {
+    pub async fn new() -> Self {
+        let rpc_url = env::var("EVM_RPC_URL").unwrap();
+        let http_client = HttpClient::new("evm_rpc_client").await;
+        EvmRpcClient {
+            http_client,
+            rpc_url,
+        }
+    }
+
+    pub async fn get_block_number(&mut self) -> Option<u64> {
+        let block_number = self.rpc_request("eth_blockNumber", json!([])).await?;
+
+        parse_hex_number(block_number.as_str()?)
+    }
+
+    /// Block with its full transactions.
+    pub async fn get_block(&mut self, block_number: u64) -> Option<Value> {
+        self.rpc_request(
+            "eth_getBlockByNumber",
+            json!([format!("{block_number:#x}"), true]),
+        )
+        .await
+    }
+
+    /// Whether the transaction succeeded, `None` until it is mined.
+    pub async fn get_transaction_status(&mut self, tx_hash: &str) -> Option<bool> {
+        let receipt = self
+            .rpc_request("eth_getTransactionReceipt", json!([tx_hash]))
+            .await?;
+
+        Some(parse_hex_number(receipt.get("status")?.as_str()?)? == 1)
+    }
+
+    /// ERC-20 `Transfer` logs of the given contracts within the block range (inclusive).
+    pub async fn get_transfer_logs(
+        &mut self,
+        contracts: Vec<String>,
+        from_block_number: u64,
+        to_block_number: u64,
+    ) -> Option<Vec<Value>> {
+        let filter = json!({
+            "address": contracts,
+            "fromBlock": format!("{from_block_number:#x}"),
+            "toBlock": format!("{to_block_number:#x}"),
+            "topics": [ERC20_TRANSFER_TOPIC],
+        });
+        let logs = self.rpc_request("eth_getLogs", json!([filter])).await?;
+
+        Some(logs.as_array()?.clone())
+    }
+
+    async fn rpc_request(&mut self, method: &str, params: Value) -> Option<Value> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+
+        let payload = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let resp: Value = self
+            .http_client
+            .post_request(&self.rpc_url, headers, payload)
+            .await;
+
+        if let Some(e) = resp.get("error") {
+            error!(target: "evm_rpc_client", "{method} failed: {e}");
+            return None;
+        }
+
+        let result = resp.get("result")?;
+        if result.is_null() {
+            return None;
+        }
+
+        Some(result.clone())
+    }
+}
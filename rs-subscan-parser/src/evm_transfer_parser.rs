@@ -0,0 +1,388 @@
+use crate::{
+    contract_events_parser::TokenPeg,
+    evm_rpc_client::{EvmRpcClient, ERC20_TRANSFER_TOPIC},
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_cursor::get_cursor_or_default,
+    subscan_parser::Network,
+    subscan_price::set_usd_prices,
+    substrate_rpc_client::parse_hex_number,
+    EvmTransfer, SubscanCursor,
+};
+use bson::DateTime;
+use itertools::Itertools;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{cmp, collections::HashMap, env, fs};
+
+pub static EVM_TRANSFERS_CURSOR_STREAM: &str = "evm_transfers";
+static EVM_MAX_BLOCKS_PER_ITERATION: u64 = 100;
+static EVM_INITIAL_LOOKBACK_BLOCKS: u64 = 1_000;
+static EVM_NATIVE_DECIMALS: i32 = 18;
+
+/// One entry of the `EVM_TOKENS_PATH` json file, an ERC-20 token such as wrapped AZERO
+/// or a stablecoin.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct EvmTokenConfig {
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u32,
+    #[serde(default)]
+    pub peg: Option<TokenPeg>,
+}
+
+/// Configured tokens by lowercase address, none when `EVM_TOKENS_PATH` is not set.
+pub fn load_evm_tokens_from_env() -> Option<HashMap<String, EvmTokenConfig>> {
+    match env::var("EVM_TOKENS_PATH") {
+        Ok(path) => load_evm_tokens(&path),
+        Err(_) => Some(HashMap::new()),
+    }
+}
+
+pub fn load_evm_tokens(path: &str) -> Option<HashMap<String, EvmTokenConfig>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| error!(target: "evm_transfer_parser", "Unable to read {path}: {e}"))
+        .ok()?;
+    let configs = serde_json::from_str::<Vec<EvmTokenConfig>>(&content)
+        .map_err(|e| error!(target: "evm_transfer_parser", "Invalid tokens json: {e}"))
+        .ok()?;
+
+    Some(
+        configs
+            .into_iter()
+            .map(|p| (p.address.to_lowercase(), p))
+            .collect(),
+    )
+}
+
+/// Converts native and token transfers of the blocks after the EVM transfers cursor, at
+/// most `EVM_MAX_BLOCKS_PER_ITERATION` per call.
+pub async fn parse_evm_transfers(
+    evm_rpc_client: &mut EvmRpcClient,
+    tokens: &HashMap<String, EvmTokenConfig>,
+    head_block_number: u64,
+) -> Option<(Vec<EvmTransfer>, SubscanCursor)> {
+    let mut cursor = get_cursor_or_default(
+        EVM_TRANSFERS_CURSOR_STREAM,
+        head_block_number.saturating_sub(EVM_INITIAL_LOOKBACK_BLOCKS),
+    )
+    .await;
+    cursor.head_block_number = head_block_number;
+    cursor.updated_timestamp = DateTime::now();
+
+    let from_block_number = cursor.block_number + 1;
+    let to_block_number = cmp::min(
+        head_block_number,
+        cursor.block_number + EVM_MAX_BLOCKS_PER_ITERATION,
+    );
+
+    let network = Network::from_env();
+    let mut evm_transfers = Vec::new();
+    let mut timestamps = HashMap::new();
+    while cursor.block_number < to_block_number {
+        let block_number = cursor.block_number + 1;
+
+        // keeping the cursor where it is, next iteration will retry the same block
+        let Some(block) = evm_rpc_client.get_block(block_number).await else {
+            error!(target: "evm_transfer_parser", "Failed to read block {block_number}.");
+            break;
+        };
+        let Some((timestamp, native_transfers)) = convert_native_transfers(&block, &network) else {
+            error!(target: "evm_transfer_parser", "Failed to parse block {block_number}.");
+            break;
+        };
+        let Some(mut native_transfers) =
+            filter_succeeded_transfers(evm_rpc_client, native_transfers).await
+        else {
+            error!(target: "evm_transfer_parser", "Failed to read receipts of block {block_number}.");
+            break;
+        };
+
+        evm_transfers.append(&mut native_transfers);
+        timestamps.insert(block_number, timestamp);
+        cursor.block_number = block_number;
+    }
+
+    // the cursor is not saved without the logs, the same blocks are read again
+    if !tokens.is_empty() && cursor.block_number >= from_block_number {
+        let logs = evm_rpc_client
+            .get_transfer_logs(
+                tokens.keys().cloned().collect(),
+                from_block_number,
+                cursor.block_number,
+            )
+            .await?;
+        let mut token_transfers = logs
+            .iter()
+            .filter_map(|p| convert_token_transfer(p, tokens, &timestamps, &network))
+            .collect();
+        evm_transfers.append(&mut token_transfers);
+    }
+
+    set_evm_usd_prices(&mut evm_transfers, tokens).await?;
+    set_substrate_accounts(&mut evm_transfers).await;
+
+    info!(
+        target: "evm_transfer_parser", "Stream {} is at block {} of {} (lag {} blocks), found {} transfers",
+        EVM_TRANSFERS_CURSOR_STREAM,
+        cursor.block_number,
+        cursor.head_block_number,
+        cursor.get_lag(),
+        evm_transfers.len(),
+    );
+
+    Some((evm_transfers, cursor))
+}
+
+/// Timestamp of the block and its transactions moving native AZERO. Contract creations
+/// are skipped, their value goes to the new contract.
+pub fn convert_native_transfers(
+    block: &Value,
+    network: &Network,
+) -> Option<(DateTime, Vec<EvmTransfer>)> {
+    let block_number = parse_hex_number(block.get("number")?.as_str()?)?;
+    let timestamp = parse_hex_number(block.get("timestamp")?.as_str()?)?;
+    let timestamp = DateTime::from_millis(timestamp as i64 * 1_000);
+
+    let evm_transfers = block
+        .get("transactions")?
+        .as_array()?
+        .iter()
+        .filter_map(|p| {
+            let value = parse_hex_amount(p.get("value")?.as_str()?)?;
+            if value <= 0.0 {
+                return None;
+            }
+
+            let tx_hash = p.get("hash")?.as_str()?.to_string();
+            Some(EvmTransfer {
+                transfer_id: tx_hash.clone(),
+                tx_hash,
+                block_number,
+                transfer_timestamp: timestamp,
+                contract: None,
+                token_symbol: network.get_token_symbol().to_string(),
+                from_wallet: p.get("from")?.as_str()?.to_lowercase(),
+                to_wallet: p.get("to")?.as_str()?.to_lowercase(),
+                from_account: None,
+                to_account: None,
+                transfer_quantity: value / 10f64.powi(EVM_NATIVE_DECIMALS),
+                transfer_usd: None,
+                network: network.clone(),
+            })
+        })
+        .collect();
+
+    Some((timestamp, evm_transfers))
+}
+
+/// ERC-20 `Transfer` log of a configured token. ERC-721 transfers share the topic but
+/// index the token id as a fourth topic, they are skipped.
+pub fn convert_token_transfer(
+    log: &Value,
+    tokens: &HashMap<String, EvmTokenConfig>,
+    timestamps: &HashMap<u64, DateTime>,
+    network: &Network,
+) -> Option<EvmTransfer> {
+    if log.get("removed").and_then(|p| p.as_bool()) == Some(true) {
+        return None;
+    }
+
+    let topics = log.get("topics")?.as_array()?;
+    if topics.len() != 3 || topics[0].as_str()? != ERC20_TRANSFER_TOPIC {
+        return None;
+    }
+
+    let contract = log.get("address")?.as_str()?.to_lowercase();
+    let token = tokens.get(&contract)?;
+    let block_number = parse_hex_number(log.get("blockNumber")?.as_str()?)?;
+    let tx_hash = log.get("transactionHash")?.as_str()?.to_string();
+    let log_index = parse_hex_number(log.get("logIndex")?.as_str()?)?;
+    let amount = parse_hex_amount(log.get("data")?.as_str()?)?;
+
+    Some(EvmTransfer {
+        transfer_id: format!("{tx_hash}-{log_index}"),
+        tx_hash,
+        block_number,
+        transfer_timestamp: *timestamps.get(&block_number)?,
+        contract: Some(contract),
+        token_symbol: token.symbol.clone(),
+        from_wallet: get_topic_address(&topics[1])?,
+        to_wallet: get_topic_address(&topics[2])?,
+        from_account: None,
+        to_account: None,
+        transfer_quantity: amount / 10f64.powi(token.decimals as i32),
+        transfer_usd: None,
+        network: network.clone(),
+    })
+}
+
+/// Failed transactions keep their value, `None` when a receipt can not be read.
+async fn filter_succeeded_transfers(
+    evm_rpc_client: &mut EvmRpcClient,
+    evm_transfers: Vec<EvmTransfer>,
+) -> Option<Vec<EvmTransfer>> {
+    let mut succeeded_transfers = Vec::new();
+    for t in evm_transfers {
+        if evm_rpc_client.get_transaction_status(&t.tx_hash).await? {
+            succeeded_transfers.push(t);
+        }
+    }
+
+    Some(succeeded_transfers)
+}
+
+/// Native AZERO follows the AZERO price like tokens pegged to it, stablecoins are valued
+/// one to one. Tokens without a peg keep no USD value.
+async fn set_evm_usd_prices(
+    evm_transfers: &mut [EvmTransfer],
+    tokens: &HashMap<String, EvmTokenConfig>,
+) -> Option<()> {
+    let get_peg = |p: &EvmTransfer| match &p.contract {
+        Some(contract) => tokens.get(contract)?.peg.clone(),
+        None => Some(TokenPeg::Azero),
+    };
+
+    let mut azero_transfers = Vec::new();
+    for t in evm_transfers.iter_mut() {
+        match get_peg(t) {
+            Some(TokenPeg::Usd) => t.transfer_usd = Some(t.transfer_quantity),
+            Some(TokenPeg::Azero) => azero_transfers.push(t.clone()),
+            None => (),
+        }
+    }
+    if azero_transfers.is_empty() {
+        return Some(());
+    }
+
+    set_usd_prices(&mut azero_transfers).await?;
+    let prices = azero_transfers
+        .into_iter()
+        .map(|p| (p.transfer_id, p.transfer_usd))
+        .collect::<HashMap<_, _>>();
+    for t in evm_transfers.iter_mut() {
+        if let Some(usd) = prices.get(&t.transfer_id) {
+            t.transfer_usd = *usd;
+        }
+    }
+
+    Some(())
+}
+
+/// Links wallets to the Substrate accounts that bridged to them.
+async fn set_substrate_accounts(evm_transfers: &mut [EvmTransfer]) {
+    let wallets = evm_transfers
+        .iter()
+        .flat_map(|p| [p.from_wallet.clone(), p.to_wallet.clone()])
+        .unique()
+        .collect::<Vec<_>>();
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let accounts = mongodb_client_subscan.get_bridge_senders(wallets).await;
+    apply_substrate_accounts(evm_transfers, &accounts);
+}
+
+/// `accounts` are Substrate senders by the lowercase H160 they bridged to.
+fn apply_substrate_accounts(evm_transfers: &mut [EvmTransfer], accounts: &HashMap<String, String>) {
+    for t in evm_transfers.iter_mut() {
+        t.from_account = accounts.get(&t.from_wallet).cloned();
+        t.to_account = accounts.get(&t.to_wallet).cloned();
+    }
+}
+
+/// Amounts are hex encoded 256 bit numbers, ones beyond 128 bits are not real transfers.
+fn parse_hex_amount(value: &str) -> Option<f64> {
+    let value = value.strip_prefix("0x")?.trim_start_matches('0');
+    if value.is_empty() {
+        return Some(0.0);
+    }
+
+    Some(u128::from_str_radix(value, 16).ok()? as f64)
+}
+
+/// Indexed addresses are left padded to 32 bytes.
+fn get_topic_address(topic: &Value) -> Option<String> {
+    let topic = topic.as_str()?.strip_prefix("0x")?;
+    if topic.len() != 64 {
+        return None;
+    }
+
+    Some(format!("0x{}", topic[24..].to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        evm_transfer_parser::{
+            apply_substrate_accounts, convert_native_transfers, convert_token_transfer,
+            EvmTokenConfig,
+        },
+        subscan_parser::Network,
+    };
+    use serde_json::Value;
+    use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+    use std::collections::HashMap;
+
+    #[test]
+    fn convert_evm_transfers_works() {
+        let fixture =
+            serde_json::from_str::<Value>(include_str!("../tests/fixtures/evm_transfers.json"))
+                .unwrap();
+        let tokens = serde_json::from_value::<Vec<EvmTokenConfig>>(fixture["tokens"].clone())
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.address.to_lowercase(), p))
+            .collect::<HashMap<_, _>>();
+
+        // the zero value call and the contract creation are not native transfers
+        let (timestamp, native_transfers) =
+            convert_native_transfers(&fixture["block"], &Network::Alephzero).unwrap();
+        assert_eq!(timestamp.timestamp_millis(), 1_700_000_000_000);
+        assert_eq!(native_transfers.len(), 1);
+        assert_eq!(native_transfers[0].transfer_quantity, 1_500.0);
+        assert_eq!(native_transfers[0].token_symbol, "AZERO");
+        assert_eq!(
+            native_transfers[0].to_wallet,
+            format!("0x{}", "2".repeat(40))
+        );
+        assert!(native_transfers[0].contract.is_none());
+
+        // the ERC-721 style log with a fourth topic is skipped
+        let timestamps = HashMap::from([(420, timestamp)]);
+        let token_transfers = fixture["logs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|p| convert_token_transfer(p, &tokens, &timestamps, &Network::Alephzero))
+            .collect::<Vec<_>>();
+        assert_eq!(token_transfers.len(), 1);
+        assert_eq!(token_transfers[0].transfer_quantity, 2_500.0);
+        assert_eq!(token_transfers[0].token_symbol, "USDT");
+        assert_eq!(
+            token_transfers[0].from_wallet,
+            format!("0x{}", "1".repeat(40))
+        );
+        assert_eq!(
+            token_transfers[0].to_wallet,
+            format!("0x{}", "4".repeat(40))
+        );
+        assert_eq!(
+            token_transfers[0].transfer_id,
+            format!("0x{}-2", "d".repeat(64))
+        );
+
+        // the receiver of the token transfer bridged from a Substrate account before
+        let account =
+            AccountId32::from([4; 32]).to_ss58check_with_version(Ss58AddressFormat::custom(42));
+        let accounts = HashMap::from([(format!("0x{}", "4".repeat(40)), account.clone())]);
+        let mut evm_transfers = native_transfers
+            .into_iter()
+            .chain(token_transfers)
+            .collect::<Vec<_>>();
+        apply_substrate_accounts(&mut evm_transfers, &accounts);
+        assert_eq!(evm_transfers[0].from_account, None);
+        assert_eq!(evm_transfers[0].to_account, None);
+        assert_eq!(evm_transfers[1].from_account, None);
+        assert_eq!(evm_transfers[1].to_account, Some(account));
+    }
+}
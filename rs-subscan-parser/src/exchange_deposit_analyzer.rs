@@ -0,0 +1,166 @@
+use crate::{mongodb_client_subscan::MongoDbClientSubscan, SubscanOperation};
+use chrono::Utc;
+use itertools::Itertools;
+use rs_exchanges_parser::{
+    mongodb_client_exchange_wallets::MongoDbClientExchangeWallets, ExchangeWallet,
+    ExchangeWalletRole, ExchangeWalletStatus, Exchanges,
+};
+use std::collections::HashMap;
+
+pub static DEPOSIT_ANALYZER_INTERVAL_SECS: u64 = 6 * 60 * 60;
+static ANALYZED_SECONDS_AGO: i64 = 30 * 24 * 60 * 60;
+static SWEEP_WINDOW_SECONDS: i64 = 6 * 60 * 60;
+static SWEEP_MIN_FORWARDED_SHARE: f64 = 0.9;
+static MIN_SWEEPS: usize = 2;
+static MIN_CONFIDENCE: f64 = 0.5;
+
+/// Proposes addresses that keep forwarding what they receive to a known exchange wallet
+/// as deposit addresses of that exchange. Returns the number of proposals.
+pub async fn analyze_exchange_deposits() -> Option<usize> {
+    let mut mongodb_client_exchange_wallets = MongoDbClientExchangeWallets::new().await;
+    let exchange_by_wallet = mongodb_client_exchange_wallets
+        .get_confirmed_exchange_wallets()
+        .await
+        .into_iter()
+        .map(|p| (p.address, p.exchange))
+        .collect::<HashMap<_, _>>();
+    if exchange_by_wallet.is_empty() {
+        return None;
+    }
+
+    let from_timestamp = Utc::now().timestamp() - ANALYZED_SECONDS_AGO;
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let exchange_inflows = mongodb_client_subscan
+        .get_transfers_to_wallets(exchange_by_wallet.keys().cloned().collect(), from_timestamp)
+        .await;
+
+    let candidates = exchange_inflows
+        .iter()
+        .map(|p| p.from_wallet.clone())
+        .filter(|p| !exchange_by_wallet.contains_key(p))
+        .unique()
+        .collect::<Vec<_>>();
+    let candidate_inflows = mongodb_client_subscan
+        .get_transfers_to_wallets(candidates, from_timestamp - SWEEP_WINDOW_SECONDS)
+        .await;
+
+    let exchange_wallets =
+        find_deposit_addresses(&exchange_inflows, &candidate_inflows, &exchange_by_wallet);
+    let exchange_wallets_len = exchange_wallets.len();
+    mongodb_client_exchange_wallets
+        .propose_exchange_wallets(exchange_wallets)
+        .await;
+
+    Some(exchange_wallets_len)
+}
+
+/// A transfer into an exchange wallet is a sweep when it forwards most of what its sender
+/// received shortly before. Confidence grows with the number of sweeps and drops with
+/// transfers to the exchange that were not sweeps.
+fn find_deposit_addresses(
+    exchange_inflows: &[SubscanOperation],
+    candidate_inflows: &[SubscanOperation],
+    exchange_by_wallet: &HashMap<String, Exchanges>,
+) -> Vec<ExchangeWallet> {
+    exchange_inflows
+        .iter()
+        .filter(|p| !exchange_by_wallet.contains_key(&p.from_wallet))
+        .into_group_map_by(|p| p.from_wallet.clone())
+        .into_iter()
+        .filter_map(|(address, outflows)| {
+            let sweeps = outflows
+                .iter()
+                .filter(|o| {
+                    let sweep_timestamp = o.operation_timestamp.timestamp_millis() / 1_000;
+                    let received = candidate_inflows
+                        .iter()
+                        .filter(|p| p.to_wallet == address)
+                        .filter(|p| {
+                            let timestamp = p.operation_timestamp.timestamp_millis() / 1_000;
+                            timestamp <= sweep_timestamp
+                                && timestamp >= sweep_timestamp - SWEEP_WINDOW_SECONDS
+                        })
+                        .map(|p| p.operation_quantity)
+                        .sum::<f64>();
+                    received > 0.0 && o.operation_quantity >= received * SWEEP_MIN_FORWARDED_SHARE
+                })
+                .collect::<Vec<_>>();
+            if sweeps.len() < MIN_SWEEPS {
+                return None;
+            }
+
+            let sweep_share = sweeps.len() as f64 / outflows.len() as f64;
+            let confidence = sweep_share * (1.0 - 0.5f64.powi(sweeps.len() as i32));
+            if confidence < MIN_CONFIDENCE {
+                return None;
+            }
+
+            // the exchange swept to the most
+            let exchange = sweeps
+                .iter()
+                .filter_map(|p| exchange_by_wallet.get(&p.to_wallet))
+                .counts()
+                .into_iter()
+                .max_by_key(|(_, count)| *count)?
+                .0
+                .clone();
+
+            Some(ExchangeWallet {
+                address,
+                exchange,
+                role: ExchangeWalletRole::Deposit,
+                status: ExchangeWalletStatus::Proposed,
+                confidence: Some(confidence),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        exchange_deposit_analyzer::find_deposit_addresses, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+    use rs_exchanges_parser::Exchanges;
+    use std::collections::HashMap;
+
+    fn get_transfer(from: &str, to: &str, quantity: f64, timestamp: i64) -> SubscanOperation {
+        SubscanOperation {
+            operation_timestamp: DateTime::from_millis(timestamp * 1_000),
+            operation_quantity: quantity,
+            operation_type: OperationType::Transfer,
+            from_wallet: from.to_string(),
+            to_wallet: to.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_deposit_addresses_works() {
+        let exchange_by_wallet = HashMap::from([("5Hot".to_string(), Exchanges::Kucoin)]);
+
+        // the deposit address forwards what users sent it within an hour, the trader
+        // sends from its own balance
+        let exchange_inflows = [
+            get_transfer("5Deposit", "5Hot", 1_000.0, 10_000),
+            get_transfer("5Deposit", "5Hot", 2_990.0, 50_000),
+            get_transfer("5Deposit", "5Hot", 600.0, 90_000),
+            get_transfer("5Trader", "5Hot", 5_000.0, 10_000),
+            get_transfer("5Trader", "5Hot", 5_000.0, 90_000),
+        ];
+        let candidate_inflows = [
+            get_transfer("5User1", "5Deposit", 1_000.0, 7_000),
+            get_transfer("5User2", "5Deposit", 3_000.0, 48_000),
+            get_transfer("5User3", "5Deposit", 600.0, 88_000),
+            get_transfer("5Exchange", "5Trader", 20_000.0, 1_000),
+        ];
+
+        let exchange_wallets =
+            find_deposit_addresses(&exchange_inflows, &candidate_inflows, &exchange_by_wallet);
+        assert_eq!(exchange_wallets.len(), 1);
+        assert_eq!(exchange_wallets[0].address, "5Deposit");
+        assert_eq!(exchange_wallets[0].exchange, Exchanges::Kucoin);
+        assert_eq!(exchange_wallets[0].confidence, Some(0.875));
+    }
+}
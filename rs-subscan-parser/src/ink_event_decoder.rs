@@ -0,0 +1,223 @@
+use log::error;
+use parity_scale_codec::{Compact, Decode};
+use serde_json::{json, Map, Value};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use std::collections::HashMap;
+
+static SUPPORTED_METADATA_VERSION: &str = "4";
+static MAX_TYPE_DEPTH: usize = 16;
+
+#[derive(Clone, Debug)]
+struct InkEventSpec {
+    label: String,
+    args: Vec<(String, u32)>,
+}
+
+/// Events and types of an ink! 4 contract metadata file, enough to decode the data of
+/// `Contracts.ContractEmitted`. Event data is the index of the event followed by all of
+/// its args.
+#[derive(Clone, Debug)]
+pub struct InkContractAbi {
+    events: Vec<InkEventSpec>,
+    types: HashMap<u32, Value>,
+    ss58_prefix: u16,
+}
+
+impl InkContractAbi {
+    pub fn from_metadata(metadata: &Value, ss58_prefix: u16) -> Option<InkContractAbi> {
+        let version = metadata.get("version").and_then(|p| match p {
+            Value::String(s) => Some(s.clone()),
+            v => v.as_u64().map(|p| p.to_string()),
+        });
+        if version.as_deref() != Some(SUPPORTED_METADATA_VERSION) {
+            error!(target: "ink_event_decoder", "Unsupported ink! metadata version {version:?}");
+            return None;
+        }
+
+        let types = metadata
+            .get("types")?
+            .as_array()?
+            .iter()
+            .map(|p| Some((p.get("id")?.as_u64()? as u32, p.get("type")?.clone())))
+            .collect::<Option<HashMap<_, _>>>()?;
+
+        let events = metadata
+            .get("spec")?
+            .get("events")?
+            .as_array()?
+            .iter()
+            .map(|e| {
+                let args = e
+                    .get("args")?
+                    .as_array()?
+                    .iter()
+                    .map(|a| {
+                        Some((
+                            a.get("label")?.as_str()?.to_string(),
+                            a.get("type")?.get("type")?.as_u64()? as u32,
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(InkEventSpec {
+                    label: e.get("label")?.as_str()?.to_string(),
+                    args,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(InkContractAbi {
+            events,
+            types,
+            ss58_prefix,
+        })
+    }
+
+    /// Label of the event and its args by name. Accounts are decoded into ss58 addresses,
+    /// `u128` and `i128` into strings, `None` into null.
+    pub fn decode_event(&self, data: &[u8]) -> Option<(String, Map<String, Value>)> {
+        let mut data = data;
+        let index = u8::decode(&mut data).ok()?;
+        let event = self.events.get(index as usize)?;
+
+        let mut args = Map::new();
+        for (label, ty) in event.args.iter() {
+            args.insert(label.clone(), self.decode_type(*ty, &mut data, 0)?);
+        }
+
+        Some((event.label.clone(), args))
+    }
+
+    fn decode_type(&self, ty: u32, data: &mut &[u8], depth: usize) -> Option<Value> {
+        if depth > MAX_TYPE_DEPTH {
+            return None;
+        }
+
+        let ty = self.types.get(&ty)?;
+        let path = ty
+            .get("path")
+            .and_then(|p| p.as_array())
+            .and_then(|p| p.last())
+            .and_then(|p| p.as_str())
+            .unwrap_or_default();
+        let def = ty.get("def")?;
+
+        if path == "AccountId" {
+            let bytes = <[u8; 32]>::decode(data).ok()?;
+            let address = AccountId32::from(bytes)
+                .to_ss58check_with_version(Ss58AddressFormat::custom(self.ss58_prefix));
+            return Some(Value::String(address));
+        }
+
+        if let Some(primitive) = def.get("primitive") {
+            return decode_primitive(primitive.as_str()?, data);
+        }
+
+        if let Some(composite) = def.get("composite") {
+            let fields = composite
+                .get("fields")
+                .and_then(|p| p.as_array())
+                .cloned()
+                .unwrap_or_default();
+            return self.decode_fields(&fields, data, depth);
+        }
+
+        if let Some(variant) = def.get("variant") {
+            let index = u8::decode(data).ok()?;
+            let variant = variant
+                .get("variants")?
+                .as_array()?
+                .iter()
+                .find(|p| p.get("index").and_then(|p| p.as_u64()) == Some(index as u64))?;
+            let name = variant.get("name")?.as_str()?;
+            let fields = variant
+                .get("fields")
+                .and_then(|p| p.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let value = self.decode_fields(&fields, data, depth)?;
+
+            return match (path, name) {
+                ("Option", "None") => Some(Value::Null),
+                ("Option", "Some") => Some(value),
+                _ => Some(json!({ name: value })),
+            };
+        }
+
+        if let Some(sequence) = def.get("sequence") {
+            let len = Compact::<u32>::decode(data).ok()?.0;
+            let ty = sequence.get("type")?.as_u64()? as u32;
+            return self.decode_items(ty, len, data, depth);
+        }
+
+        if let Some(array) = def.get("array") {
+            let len = array.get("len")?.as_u64()? as u32;
+            let ty = array.get("type")?.as_u64()? as u32;
+            return self.decode_items(ty, len, data, depth);
+        }
+
+        if let Some(tuple) = def.get("tuple") {
+            let values = tuple
+                .as_array()?
+                .iter()
+                .map(|p| self.decode_type(p.as_u64()? as u32, data, depth + 1))
+                .collect::<Option<Vec<_>>>()?;
+            return Some(Value::Array(values));
+        }
+
+        if def.get("compact").is_some() {
+            let value = Compact::<u128>::decode(data).ok()?.0;
+            return Some(Value::String(value.to_string()));
+        }
+
+        None
+    }
+
+    /// Named fields become an object, a single unnamed field is unwrapped.
+    fn decode_fields(&self, fields: &[Value], data: &mut &[u8], depth: usize) -> Option<Value> {
+        let mut named = Map::new();
+        let mut unnamed = Vec::new();
+        for field in fields {
+            let value = self.decode_type(field.get("type")?.as_u64()? as u32, data, depth + 1)?;
+            match field.get("name").and_then(|p| p.as_str()) {
+                Some(name) => {
+                    named.insert(name.to_string(), value);
+                }
+                None => unnamed.push(value),
+            }
+        }
+
+        match (named.is_empty(), unnamed.len()) {
+            (false, _) => Some(Value::Object(named)),
+            (true, 1) => unnamed.pop(),
+            _ => Some(Value::Array(unnamed)),
+        }
+    }
+
+    fn decode_items(&self, ty: u32, len: u32, data: &mut &[u8], depth: usize) -> Option<Value> {
+        let values = (0..len)
+            .map(|_| self.decode_type(ty, data, depth + 1))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Value::Array(values))
+    }
+}
+
+fn decode_primitive(primitive: &str, data: &mut &[u8]) -> Option<Value> {
+    let value = match primitive {
+        "bool" => json!(bool::decode(data).ok()?),
+        "u8" => json!(u8::decode(data).ok()?),
+        "u16" => json!(u16::decode(data).ok()?),
+        "u32" => json!(u32::decode(data).ok()?),
+        "u64" => json!(u64::decode(data).ok()?),
+        "u128" => json!(u128::decode(data).ok()?.to_string()),
+        "i8" => json!(i8::decode(data).ok()?),
+        "i16" => json!(i16::decode(data).ok()?),
+        "i32" => json!(i32::decode(data).ok()?),
+        "i64" => json!(i64::decode(data).ok()?),
+        "i128" => json!(i128::decode(data).ok()?.to_string()),
+        "str" => json!(String::decode(data).ok()?),
+        _ => return None,
+    };
+
+    Some(value)
+}
@@ -1,13 +1,52 @@
 use bson::DateTime;
 use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use subscan_parser::Network;
 
+pub mod address_labels;
+pub mod balance_tracker;
+pub mod bridge_parser;
+pub mod contract_events_parser;
+pub mod dex_swap_parser;
+pub mod evm_rpc_client;
+pub mod evm_transfer_parser;
+pub mod exchange_deposit_analyzer;
+pub mod ink_event_decoder;
+pub mod mongodb_client_api_key_stats;
+pub mod mongodb_client_balance;
+pub mod mongodb_client_cursor;
+pub mod mongodb_client_era_reward;
+pub mod mongodb_client_evm_transfer;
 pub mod mongodb_client_identities;
+pub mod mongodb_client_label;
+pub mod mongodb_client_nomination;
+pub mod mongodb_client_reward;
 pub mod mongodb_client_subscan;
+pub mod mongodb_client_token_operation;
+pub mod mongodb_client_unbonding;
 pub mod mongodb_client_validator;
+pub mod mongodb_client_validator_event;
+pub mod mongodb_client_validator_info;
+pub mod subscan_api_key_pool;
+pub mod subscan_backfill;
+pub mod subscan_call_decoder;
+pub mod subscan_cursor;
+pub mod subscan_enrichment;
+pub mod subscan_governance_parser;
+pub mod subscan_identity_parser;
 pub mod subscan_parser;
+pub mod subscan_pool_parser;
+pub mod subscan_price;
+pub mod subscan_reward_parser;
 pub mod subscan_stake_parser;
 pub mod subscan_transfer_parser;
+pub mod subscan_validator_event_parser;
+pub mod subscan_validator_parser;
+pub mod substrate_rpc_client;
+pub mod substrate_rpc_decoder;
+pub mod substrate_rpc_parser;
+pub mod unbonding_forecast;
 
 pub static MINIMUM_AZERO_TO_SAVE_TO_DB: f64 = 499.999999;
 
@@ -33,9 +72,101 @@ pub enum OperationType {
     ReStake,
     RequestUnstake,
     WithdrawUnstaked,
+    Rebond,
     Transfer,
     DepositToExchange,
     WithdrawFromExchange,
+    ExchangeToExchange,
+    BridgeOut,
+    BridgeIn,
+    PoolJoin,
+    PoolBondExtra,
+    PoolUnbond,
+    PoolWithdrawUnbonded,
+    PoolCreate,
+    PoolClaimPayout,
+    TreasuryProposal,
+    TreasurySpend,
+    GovernanceVote,
+    VestingUnlock,
+    VestedTransfer,
+}
+
+impl OperationType {
+    pub fn from_extrinsics_type(
+        module: &Module,
+        extrinsics_type: &ExtrinsicsType,
+    ) -> OperationType {
+        match (module, extrinsics_type) {
+            (Module::NominationPools, ExtrinsicsType::Join) => OperationType::PoolJoin,
+            (Module::NominationPools, ExtrinsicsType::BondExtra) => OperationType::PoolBondExtra,
+            (Module::NominationPools, ExtrinsicsType::Unbond) => OperationType::PoolUnbond,
+            (Module::NominationPools, ExtrinsicsType::WithdrawUnbonded) => {
+                OperationType::PoolWithdrawUnbonded
+            }
+            (Module::NominationPools, ExtrinsicsType::Create) => OperationType::PoolCreate,
+            (Module::NominationPools, ExtrinsicsType::ClaimPayout) => {
+                OperationType::PoolClaimPayout
+            }
+            (Module::Treasury, _) => OperationType::TreasuryProposal,
+            (Module::Democracy, _) => OperationType::GovernanceVote,
+            (Module::Vesting, ExtrinsicsType::VestedTransfer) => OperationType::VestedTransfer,
+            (Module::Vesting, _) => OperationType::VestingUnlock,
+            (_, ExtrinsicsType::Nominate) => OperationType::ReStake,
+            (_, ExtrinsicsType::Unbond) => OperationType::RequestUnstake,
+            (_, ExtrinsicsType::WithdrawUnbonded) => OperationType::WithdrawUnstaked,
+            (_, ExtrinsicsType::Rebond) => OperationType::Rebond,
+            _ => OperationType::Stake,
+        }
+    }
+
+    /// Type of a transfer by whether its sender and receiver are exchange wallets.
+    pub fn from_transfer_sides(from_exchange: bool, to_exchange: bool) -> OperationType {
+        match (from_exchange, to_exchange) {
+            (true, true) => OperationType::ExchangeToExchange,
+            (true, false) => OperationType::WithdrawFromExchange,
+            (false, true) => OperationType::DepositToExchange,
+            (false, false) => OperationType::Transfer,
+        }
+    }
+
+    pub fn is_transfer(&self) -> bool {
+        matches!(
+            self,
+            OperationType::Transfer
+                | OperationType::DepositToExchange
+                | OperationType::WithdrawFromExchange
+                | OperationType::ExchangeToExchange
+        )
+    }
+
+    pub fn is_bridge_transfer(&self) -> bool {
+        matches!(self, OperationType::BridgeOut | OperationType::BridgeIn)
+    }
+
+    /// Treasury, democracy and vesting operations, each posted from its own USD amount.
+    pub fn is_governance_operation(&self) -> bool {
+        matches!(
+            self,
+            OperationType::TreasuryProposal
+                | OperationType::TreasurySpend
+                | OperationType::GovernanceVote
+                | OperationType::VestingUnlock
+                | OperationType::VestedTransfer
+        )
+    }
+
+    pub fn is_pool_operation(&self) -> bool {
+        matches!(
+            self,
+            OperationType::PoolJoin
+                | OperationType::PoolBondExtra
+                | OperationType::PoolUnbond
+                | OperationType::PoolWithdrawUnbonded
+                | OperationType::PoolCreate
+                | OperationType::PoolClaimPayout
+        )
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -44,10 +175,347 @@ pub struct Validator {
     pub validator: String,
 }
 
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum ValidatorStatus {
+    #[default]
+    Active,
+    Waiting,
+    /// In neither list anymore, e.g. chilled or fully unbonded.
+    Inactive,
+}
+
+/// Registry entry of a validator, refreshed from the active and waiting lists.
+/// Commission is in percent, stakes are in AZERO.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ValidatorInfo {
+    pub validator: String,
+    pub identity: Option<String>,
+    pub commission: f64,
+    pub self_stake: f64,
+    pub total_stake: f64,
+    pub nominators: u32,
+    pub status: ValidatorStatus,
+    pub era: u32,
+    #[serde(default)]
+    pub active_eras: Vec<u32>,
+    pub updated_timestamp: DateTime,
+    #[serde(default)]
+    pub network: Network,
+}
+
+impl ValidatorInfo {
+    /// Share of the given total stake backing this validator.
+    pub fn get_stake_share(&self, total_stake: f64) -> f64 {
+        if total_stake <= 0.0 {
+            return 0.0;
+        }
+
+        self.total_stake / total_stake
+    }
+}
+
+/// Targets chosen by a nominator, valid from `from_block` until the block it nominated
+/// again at, `to_block` is `None` for the current nomination. A chill is stored with no
+/// targets, so it ends the nomination before it. A nominator can't unbond everything
+/// without chilling, so this covers full unbonds too.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Nomination {
+    pub nominator: String,
+    pub targets: Vec<String>,
+    pub extrinsic_index: String,
+    pub from_block: u64,
+    pub to_block: Option<u64>,
+    #[serde(default)]
+    pub network: Network,
+}
+
+/// Reward paid to a single stash, either the validator itself or one of its nominators.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct StakingReward {
+    pub era: u32,
+    pub block_number: u64,
+    pub event_index: String,
+    pub extrinsic_index: String,
+    pub reward_timestamp: DateTime,
+    pub reward_quantity: f64,
+    pub reward_usd: f64,
+    pub stash: String,
+    pub validator: String,
+    pub is_validator: bool,
+    #[serde(default)]
+    pub network: Network,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum ValidatorEventType {
+    #[default]
+    Slashed,
+    Kicked,
+    Chilled,
+    Banned,
+}
+
+impl ValidatorEventType {
+    pub fn get_module_id(&self) -> &'static str {
+        match self {
+            ValidatorEventType::Banned => "elections",
+            _ => "staking",
+        }
+    }
+
+    pub fn get_event_id(&self) -> &'static str {
+        match self {
+            ValidatorEventType::Slashed => "Slashed",
+            ValidatorEventType::Kicked => "Kicked",
+            ValidatorEventType::Chilled => "Chilled",
+            ValidatorEventType::Banned => "BanValidators",
+        }
+    }
+
+    /// Name of the cursor the events are ingested with.
+    pub fn get_stream_name(&self) -> String {
+        format!("validator_{}", self.to_string().to_lowercase())
+    }
+}
+
+/// Slash, kick, chill or ban of a validator. `affected_wallet` is the slashed staker
+/// or the kicked nominator, if any.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ValidatorEvent {
+    pub event_index: String,
+    pub extrinsic_index: String,
+    pub block_number: u64,
+    pub event_timestamp: DateTime,
+    pub event_type: ValidatorEventType,
+    pub validator: String,
+    pub affected_wallet: String,
+    pub amount: f64,
+    pub total_stake: f64,
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub network: Network,
+}
+
+/// Totals of all rewards paid out for an era so far.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct EraReward {
+    pub era: u32,
+    pub reward_quantity: f64,
+    pub reward_usd: f64,
+    pub validators: u32,
+    pub nominators: u32,
+    pub updated_timestamp: DateTime,
+    #[serde(default)]
+    pub network: Network,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum LabelCategory {
+    Exchange,
+    Bridge,
+    Treasury,
+    Foundation,
+    MarketMaker,
+    #[default]
+    Other,
+}
+
+/// Our own name for a known address. Preferred over the on-chain identity, `confidence`
+/// goes from 0 to 1 and `source` tells where the label comes from.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Label {
+    pub address: String,
+    pub label: String,
+    pub category: LabelCategory,
+    pub source: String,
+    pub confidence: f64,
+    #[serde(default)]
+    pub network: Network,
+}
+
+/// Latest known balance of a wallet seen in large operations, in AZERO. `previous_total`
+/// and `changed_timestamp` are kept from the last refresh that moved the total by more
+/// than the alert share.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Balance {
+    pub address: String,
+    pub free: f64,
+    pub reserved: f64,
+    pub staked: f64,
+    pub total: f64,
+    pub supply_share: f64,
+    pub previous_total: Option<f64>,
+    pub changed_timestamp: Option<DateTime>,
+    pub updated_timestamp: DateTime,
+    /// Total at the last alert or the start of the alert window, changes are measured
+    /// against it.
+    #[serde(default)]
+    pub baseline_total: Option<f64>,
+    #[serde(default)]
+    pub baseline_timestamp: Option<DateTime>,
+    #[serde(default)]
+    pub network: Network,
+}
+
+impl Balance {
+    /// Relative change of the total since the previous alert, if any.
+    pub fn get_change_share(&self) -> Option<f64> {
+        let previous_total = self.previous_total.filter(|p| *p > 0.0)?;
+        Some((self.total - previous_total) / previous_total)
+    }
+}
+
+/// AZERO a stash requested to unstake with one `RequestUnstake`. `unlock_timestamp` is
+/// estimated from the bonding duration, the withdrawn fields are set once a
+/// `WithdrawUnstaked` of the stash covered the chunk.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct UnbondingChunk {
+    pub stash: String,
+    pub extrinsic_index: String,
+    pub amount: f64,
+    pub unbond_timestamp: DateTime,
+    pub unlock_timestamp: DateTime,
+    pub withdrawn_extrinsic_index: Option<String>,
+    pub withdrawn_timestamp: Option<DateTime>,
+    /// Rebonds that took from the chunk, `amount` is what is left of it.
+    #[serde(default)]
+    pub rebond_extrinsic_indexes: Vec<String>,
+    #[serde(default)]
+    pub network: Network,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum TokenOperationType {
+    #[default]
+    Transfer,
+    Approval,
+}
+
+/// PSP22 event of a configured ink! contract. `operation_quantity` is already divided by
+/// the token decimals, `operation_usd` is set only for tokens with a known price.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct TokenOperation {
+    pub block_number: u64,
+    pub event_index: String,
+    pub extrinsic_index: Option<String>,
+    pub operation_timestamp: DateTime,
+    pub contract: String,
+    pub token_symbol: String,
+    pub decimals: u32,
+    pub operation_type: TokenOperationType,
+    pub from_wallet: String,
+    pub to_wallet: String,
+    pub operation_quantity: f64,
+    pub operation_usd: Option<f64>,
+    #[serde(default)]
+    pub network: Network,
+}
+
+/// Native or ERC-20 transfer on the EVM layer. Wallets stay in H160 form, `from_account`
+/// and `to_account` are the Substrate accounts that bridged to them, if known.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct EvmTransfer {
+    /// Transaction hash, followed by the log index for ERC-20 transfers.
+    pub transfer_id: String,
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub transfer_timestamp: DateTime,
+    /// Token contract, `None` for native transfers.
+    pub contract: Option<String>,
+    pub token_symbol: String,
+    pub from_wallet: String,
+    pub to_wallet: String,
+    #[serde(default)]
+    pub from_account: Option<String>,
+    #[serde(default)]
+    pub to_account: Option<String>,
+    pub transfer_quantity: f64,
+    pub transfer_usd: Option<f64>,
+    #[serde(default)]
+    pub network: Network,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+/// Display name of an address, sub-identities are shown as `Parent/child`.
 pub struct Identity {
     pub address: String,
     pub identity: String,
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub judgement: Option<String>,
+    #[serde(default)]
+    pub updated_timestamp: Option<DateTime>,
+}
+
+/// Bridge a `BridgeOut` or `BridgeIn` went through. `chain` is known for bridges of the
+/// registry, `counterparty` only when the bridge contract emitted the address on the
+/// other chain.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct BridgeTransfer {
+    pub bridge: String,
+    pub chain: Option<String>,
+    pub counterparty: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -62,6 +530,40 @@ pub struct SubscanOperation {
     pub from_wallet: String,
     pub controller_wallet: String,
     pub to_wallet: String,
+    #[serde(default)]
+    pub pool_id: Option<u32>,
+    #[serde(default)]
+    pub pool_name: Option<String>,
+    /// Full target list of a nomination, `to_wallet` is the first of them.
+    #[serde(default)]
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub bridge: Option<BridgeTransfer>,
+    #[serde(default)]
+    pub network: Network,
+}
+
+/// `bson::DateTime` has no default, operations start at the unix epoch.
+impl Default for SubscanOperation {
+    fn default() -> Self {
+        Self {
+            hash: Default::default(),
+            block_number: Default::default(),
+            extrinsic_index: Default::default(),
+            operation_timestamp: DateTime::from_millis(0),
+            operation_quantity: Default::default(),
+            operation_usd: Default::default(),
+            operation_type: Default::default(),
+            from_wallet: Default::default(),
+            controller_wallet: Default::default(),
+            to_wallet: Default::default(),
+            pool_id: Default::default(),
+            pool_name: Default::default(),
+            targets: Default::default(),
+            bridge: Default::default(),
+            network: Default::default(),
+        }
+    }
 }
 
 impl SubscanOperation {
@@ -106,6 +608,36 @@ pub enum ExtrinsicsType {
 
     #[strum(to_string = "withdraw_unbonded")]
     WithdrawUnbonded,
+    Join,
+    Create,
+
+    #[strum(to_string = "claim_payout")]
+    ClaimPayout,
+    Batch,
+
+    #[strum(to_string = "batch_all")]
+    BatchAll,
+
+    #[strum(to_string = "force_batch")]
+    ForceBatch,
+    Proxy,
+
+    #[strum(to_string = "as_multi")]
+    AsMulti,
+
+    #[strum(to_string = "as_multi_threshold_1")]
+    AsMultiThreshold1,
+
+    #[strum(to_string = "propose_spend")]
+    ProposeSpend,
+    Vote,
+    Vest,
+
+    #[strum(to_string = "vest_other")]
+    VestOther,
+
+    #[strum(to_string = "vested_transfer")]
+    VestedTransfer,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -118,10 +650,33 @@ pub struct SubscanEventParam {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub struct SubscanEvent {
     pub module_id: String,
+    #[serde(default)]
+    pub event_id: String,
     pub event_index: String,
     pub event_params: Vec<SubscanEventParam>,
 }
 
+/// Event listed by module and name, with the block and extrinsic it was emitted in.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct SubscanEventRecord {
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub event_timestamp: DateTime,
+    pub event: SubscanEvent,
+}
+
+impl SubscanEventRecord {
+    /// Finds a param by name, or by position for older runtimes that emit unnamed fields.
+    pub fn get_param(&self, name: &str, position: usize) -> Option<String> {
+        self.event
+            .event_params
+            .iter()
+            .find(|p| p.name == name)
+            .or(self.event.event_params.get(position))
+            .map(|p| p.value.clone())
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -142,4 +697,180 @@ pub struct SubscanEvent {
 pub enum Module {
     #[default]
     Staking,
+
+    #[strum(to_string = "nominationpools")]
+    NominationPools,
+    Utility,
+    Proxy,
+    Multisig,
+    Treasury,
+    Democracy,
+    Vesting,
+}
+
+impl Module {
+    pub fn get_extrinsics_types(&self) -> Vec<ExtrinsicsType> {
+        match self {
+            Module::Staking => vec![
+                ExtrinsicsType::Bond,
+                ExtrinsicsType::BondExtra,
+                ExtrinsicsType::Nominate,
+                ExtrinsicsType::Rebond,
+                ExtrinsicsType::Unbond,
+                ExtrinsicsType::WithdrawUnbonded,
+            ],
+            Module::NominationPools => vec![
+                ExtrinsicsType::Join,
+                ExtrinsicsType::BondExtra,
+                ExtrinsicsType::Unbond,
+                ExtrinsicsType::WithdrawUnbonded,
+                ExtrinsicsType::Create,
+                ExtrinsicsType::ClaimPayout,
+            ],
+            Module::Utility => vec![
+                ExtrinsicsType::Batch,
+                ExtrinsicsType::BatchAll,
+                ExtrinsicsType::ForceBatch,
+            ],
+            Module::Proxy => vec![ExtrinsicsType::Proxy],
+            Module::Multisig => vec![ExtrinsicsType::AsMulti, ExtrinsicsType::AsMultiThreshold1],
+            Module::Treasury => vec![ExtrinsicsType::ProposeSpend],
+            Module::Democracy => vec![ExtrinsicsType::Vote],
+            Module::Vesting => vec![
+                ExtrinsicsType::Vest,
+                ExtrinsicsType::VestOther,
+                ExtrinsicsType::VestedTransfer,
+            ],
+        }
+    }
+
+    /// Wrapper calls dispatch other calls, possibly on behalf of another account.
+    pub fn is_wrapper(&self) -> bool {
+        matches!(self, Module::Utility | Module::Proxy | Module::Multisig)
+    }
+
+    /// Calls whose amount and beneficiary are read from the call params.
+    pub fn is_governance(&self) -> bool {
+        matches!(self, Module::Treasury | Module::Democracy | Module::Vesting)
+    }
+}
+
+/// Where the worker reads chain data from, selected with `INGESTION_BACKEND`.
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum IngestionBackend {
+    #[default]
+    Subscan,
+    Rpc,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SubscanStream {
+    Transfers,
+    Extrinsics(Module, ExtrinsicsType),
+}
+
+impl SubscanStream {
+    pub fn get_all() -> Vec<SubscanStream> {
+        let mut streams = vec![SubscanStream::Transfers];
+        streams.append(&mut SubscanStream::get_staking_streams());
+        streams.append(&mut SubscanStream::get_pool_streams());
+        streams.append(&mut SubscanStream::get_governance_streams());
+        streams
+    }
+
+    /// Direct staking calls followed by wrapper calls that may contain them.
+    pub fn get_staking_streams() -> Vec<SubscanStream> {
+        [
+            Module::Staking,
+            Module::Utility,
+            Module::Proxy,
+            Module::Multisig,
+        ]
+        .into_iter()
+        .flat_map(|m| {
+            m.get_extrinsics_types()
+                .into_iter()
+                .map(move |e| SubscanStream::Extrinsics(m.clone(), e))
+        })
+        .collect()
+    }
+
+    pub fn is_wrapper(&self) -> bool {
+        match self {
+            SubscanStream::Transfers => false,
+            SubscanStream::Extrinsics(module, _) => module.is_wrapper(),
+        }
+    }
+
+    pub fn get_pool_streams() -> Vec<SubscanStream> {
+        Module::NominationPools
+            .get_extrinsics_types()
+            .into_iter()
+            .map(|e| SubscanStream::Extrinsics(Module::NominationPools, e))
+            .collect()
+    }
+
+    pub fn get_governance_streams() -> Vec<SubscanStream> {
+        [Module::Treasury, Module::Democracy, Module::Vesting]
+            .into_iter()
+            .flat_map(|m| {
+                m.get_extrinsics_types()
+                    .into_iter()
+                    .map(move |e| SubscanStream::Extrinsics(m.clone(), e))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for SubscanStream {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SubscanStream::get_all()
+            .into_iter()
+            .find(|p| p.to_string() == s)
+            .ok_or(format!("unknown stream {s}"))
+    }
+}
+
+impl fmt::Display for SubscanStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscanStream::Transfers => write!(f, "transfers"),
+            SubscanStream::Extrinsics(module, extrinsics_type) => {
+                write!(f, "{module}_{extrinsics_type}")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct SubscanCursor {
+    pub stream: String,
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub head_block_number: u64,
+    pub updated_timestamp: DateTime,
+}
+
+impl SubscanCursor {
+    pub fn get_lag(&self) -> u64 {
+        self.head_block_number.saturating_sub(self.block_number)
+    }
 }
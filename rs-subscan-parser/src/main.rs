@@ -1,13 +1,49 @@
+use futures::{future::BoxFuture, FutureExt};
 use itertools::Itertools;
 use log::{error, info};
+use rs_exchanges_parser::{mongodb_client_exchanges::MongoDbClientExchanges, ExchangeTrade};
 use rs_subscan_parser::{
-    mongodb_client_identities::MongoDbClientIdentity, mongodb_client_subscan::MongoDbClientSubscan,
-    mongodb_client_validator::MongoDbClientValidator, subscan_stake_parser::parse_staking,
+    address_labels::{export_labels, import_labels},
+    balance_tracker::{refresh_balances, BALANCE_REFRESH_INTERVAL_SECS},
+    bridge_parser::{load_bridges_from_env, parse_bridge_counterparties, Bridge},
+    contract_events_parser::{load_psp22_contracts, parse_contract_events, Psp22Contract},
+    dex_swap_parser::{load_dex_pairs, parse_dex_swaps, DexPair},
+    evm_rpc_client::EvmRpcClient,
+    evm_transfer_parser::{load_evm_tokens_from_env, parse_evm_transfers, EvmTokenConfig},
+    exchange_deposit_analyzer::{analyze_exchange_deposits, DEPOSIT_ANALYZER_INTERVAL_SECS},
+    mongodb_client_api_key_stats::MongoDbClientApiKeyStats,
+    mongodb_client_balance::MongoDbClientBalance,
+    mongodb_client_cursor::MongoDbClientCursor,
+    mongodb_client_era_reward::MongoDbClientEraReward,
+    mongodb_client_evm_transfer::MongoDbClientEvmTransfer,
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_label::MongoDbClientLabel,
+    mongodb_client_nomination::MongoDbClientNomination,
+    mongodb_client_reward::MongoDbClientReward,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_token_operation::MongoDbClientTokenOperation,
+    mongodb_client_unbonding::MongoDbClientUnbonding,
+    mongodb_client_validator::MongoDbClientValidator,
+    mongodb_client_validator_event::MongoDbClientValidatorEvent,
+    mongodb_client_validator_info::MongoDbClientValidatorInfo,
+    subscan_api_key_pool::{save_api_key_stats, API_KEY_STATS_INTERVAL_SECS},
+    subscan_backfill::{run_backfill, BackfillOptions},
+    subscan_cursor::{get_head_block_number, run_cursor_worker, CursorWorker},
+    subscan_governance_parser::parse_governance,
+    subscan_identity_parser::{refresh_stale_identities, IDENTITY_REFRESH_INTERVAL_SECS},
+    subscan_pool_parser::parse_pools,
+    subscan_reward_parser::{parse_rewards, update_era_rewards},
+    subscan_stake_parser::parse_staking,
     subscan_transfer_parser::parse_transfers,
+    subscan_validator_event_parser::parse_validator_events,
+    subscan_validator_parser::{refresh_validator_infos, VALIDATOR_REFRESH_INTERVAL_SECS},
+    substrate_rpc_parser::{parse_rpc_blocks, SubstrateRpcParser, RPC_CURSOR_STREAM},
+    unbonding_forecast::{analyze_unbondings, UNBONDING_ANALYZER_INTERVAL_SECS},
+    EvmTransfer, IngestionBackend, SubscanCursor, SubscanOperation, SubscanStream, TokenOperation,
 };
 use rs_utils::utils::logger::initialize_logger;
 // use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
-use std::time::Duration;
+use std::{collections::HashMap, env, str::FromStr, time::Duration};
 use tokio::time::sleep;
 
 #[tokio::main(worker_threads = 100)]
@@ -21,12 +57,88 @@ async fn main() {
 
     initialize_logger().expect("failed to initialize logging.");
 
-    info!(target: "subscan_parser", "Started subscan parser worker.");
+    let args = env::args().skip(1).collect_vec();
+    if args.first().map(|p| p.as_str()) == Some("backfill") {
+        let Some(options) = BackfillOptions::from_args(&args[1..]) else {
+            error!(
+                target: "subscan_backfill", "Usage: rs-subscan-parser backfill <stream> [--from-block N] [--to-block N] [--from-date YYYY-MM-DD] [--to-date YYYY-MM-DD]",
+            );
+            return;
+        };
+
+        info!(target: "subscan_backfill", "Started subscan backfill.");
+
+        create_indexes().await;
+        tokio::spawn(async move { start_api_key_stats_worker().await });
+        run_backfill(options).await;
+        return;
+    }
+
+    if args.first().map(|p| p.as_str()) == Some("labels") {
+        create_indexes().await;
+        run_labels_command(&args[1..]).await;
+        return;
+    }
+
+    let ingestion_backend = env::var("INGESTION_BACKEND")
+        .ok()
+        .and_then(|p| IngestionBackend::from_str(&p).ok())
+        .unwrap_or_default();
+
+    info!(target: "subscan_parser", "Started subscan parser worker, backend {ingestion_backend}.");
 
-    start_worker().await;
+    create_indexes().await;
+    tokio::spawn(async move { start_api_key_stats_worker().await });
+    tokio::spawn(async move { start_validator_registry_worker().await });
+    tokio::spawn(async move { start_identity_refresh_worker().await });
+    tokio::spawn(async move { start_deposit_analyzer_worker().await });
+    tokio::spawn(async move { start_balance_tracker_worker().await });
+    tokio::spawn(async move { start_unbonding_analyzer_worker().await });
+    if let Ok(path) = env::var("PSP22_CONTRACTS_PATH") {
+        match load_psp22_contracts(&path) {
+            Some(contracts) => {
+                tokio::spawn(async move { start_contract_events_worker(contracts).await });
+            }
+            None => error!(
+                target: "contract_events_parser", "Unable to load contracts from {path}",
+            ),
+        }
+    }
+    if let Some(bridges) = load_bridges_from_env() {
+        let ingestion_stream = match ingestion_backend {
+            IngestionBackend::Subscan => SubscanStream::Transfers.to_string(),
+            IngestionBackend::Rpc => RPC_CURSOR_STREAM.to_string(),
+        };
+        tokio::spawn(async move { start_bridge_events_worker(bridges, ingestion_stream).await });
+    }
+    if let Ok(path) = env::var("DEX_PAIRS_PATH") {
+        match load_dex_pairs(&path) {
+            Some(pairs) => {
+                tokio::spawn(async move { start_dex_swaps_worker(pairs).await });
+            }
+            None => error!(
+                target: "dex_swap_parser", "Unable to load pairs from {path}",
+            ),
+        }
+    }
+    // compose passes unset variables as empty strings
+    if env::var("EVM_RPC_URL").is_ok_and(|p| !p.is_empty()) {
+        match load_evm_tokens_from_env() {
+            Some(tokens) => {
+                tokio::spawn(async move { start_evm_transfers_worker(tokens).await });
+            }
+            None => error!(
+                target: "evm_transfer_parser", "Unable to load EVM tokens",
+            ),
+        }
+    }
+    match ingestion_backend {
+        IngestionBackend::Subscan => start_worker().await,
+        IngestionBackend::Rpc => start_rpc_worker().await,
+    }
 }
 
-async fn start_worker() {
+async fn create_indexes() {
     let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
     mongodb_client_subscan.create_index().await;
 
@@ -36,37 +148,516 @@ async fn start_worker() {
     let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
     mongodb_client_identity.create_index().await;
 
+    let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+    mongodb_client_nomination.create_index().await;
+
+    let mut mongodb_client_label = MongoDbClientLabel::new().await;
+    mongodb_client_label.create_index().await;
+
+    let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+    mongodb_client_cursor.create_index().await;
+
+    let mut mongodb_client_reward = MongoDbClientReward::new().await;
+    mongodb_client_reward.create_index().await;
+
+    let mut mongodb_client_era_reward = MongoDbClientEraReward::new().await;
+    mongodb_client_era_reward.create_index().await;
+
+    let mut mongodb_client_validator_event = MongoDbClientValidatorEvent::new().await;
+    mongodb_client_validator_event.create_index().await;
+
+    let mut mongodb_client_validator_info = MongoDbClientValidatorInfo::new().await;
+    mongodb_client_validator_info.create_index().await;
+
+    let mut mongodb_client_balance = MongoDbClientBalance::new().await;
+    mongodb_client_balance.create_index().await;
+
+    let mut mongodb_client_unbonding = MongoDbClientUnbonding::new().await;
+    mongodb_client_unbonding.create_index().await;
+
+    let mut mongodb_client_token_operation = MongoDbClientTokenOperation::new().await;
+    mongodb_client_token_operation.create_index().await;
+
+    let mut mongodb_client_evm_transfer = MongoDbClientEvmTransfer::new().await;
+    mongodb_client_evm_transfer.create_index().await;
+
+    let mut mongodb_client_api_key_stats = MongoDbClientApiKeyStats::new().await;
+    mongodb_client_api_key_stats.create_index().await;
+}
+
+async fn start_api_key_stats_worker() {
     loop {
-        let subscan_operations_task = tokio::spawn(async move { parse_staking().await });
-        let subscan_transfers_task = tokio::spawn(async move { parse_transfers().await });
+        sleep(Duration::from_secs(API_KEY_STATS_INTERVAL_SECS)).await;
+        let api_key_stats_len = save_api_key_stats().await;
+        info!(
+            target: "subscan_api_key_pool", "Saved stats of {} api keys",
+            api_key_stats_len,
+        );
+    }
+}
 
-        let subscan_operations = subscan_operations_task.await.ok();
-        let subscan_transfers = subscan_transfers_task.await.ok();
+async fn start_validator_registry_worker() {
+    loop {
+        match refresh_validator_infos().await {
+            Some(validator_infos_len) => info!(
+                target: "subscan_validator_parser", "Refreshed {} validators",
+                validator_infos_len,
+            ),
+            None => error!(
+                target: "subscan_validator_parser", "Unable to refresh validators",
+            ),
+        }
+        sleep(Duration::from_secs(VALIDATOR_REFRESH_INTERVAL_SECS)).await;
+    }
+}
 
-        let subscan_operations = vec![subscan_operations, subscan_transfers]
-            .into_iter()
-            .flatten()
-            .flatten()
-            .flatten()
-            .collect_vec();
-        if subscan_operations.is_empty() {
+async fn start_identity_refresh_worker() {
+    loop {
+        match refresh_stale_identities().await {
+            Some(identities_len) => info!(
+                target: "subscan_identity_parser", "Re-checked {} stale identities",
+                identities_len,
+            ),
+            None => error!(
+                target: "subscan_identity_parser", "Unable to re-check identities",
+            ),
+        }
+        sleep(Duration::from_secs(IDENTITY_REFRESH_INTERVAL_SECS)).await;
+    }
+}
+
+async fn start_deposit_analyzer_worker() {
+    loop {
+        match analyze_exchange_deposits().await {
+            Some(exchange_wallets_len) => info!(
+                target: "exchange_deposit_analyzer", "Proposed {} exchange deposit addresses",
+                exchange_wallets_len,
+            ),
+            None => error!(
+                target: "exchange_deposit_analyzer", "No exchange wallets to analyze deposits for",
+            ),
+        }
+        sleep(Duration::from_secs(DEPOSIT_ANALYZER_INTERVAL_SECS)).await;
+    }
+}
+
+async fn start_balance_tracker_worker() {
+    loop {
+        match refresh_balances().await {
+            Some(balances_len) => info!(
+                target: "balance_tracker", "Refreshed {} balances",
+                balances_len,
+            ),
+            None => error!(
+                target: "balance_tracker", "Unable to refresh balances",
+            ),
+        }
+        sleep(Duration::from_secs(BALANCE_REFRESH_INTERVAL_SECS)).await;
+    }
+}
+
+async fn start_unbonding_analyzer_worker() {
+    loop {
+        match analyze_unbondings().await {
+            Some(unbonding_chunks_len) => info!(
+                target: "unbonding_forecast", "Analyzed {} unbonding chunks",
+                unbonding_chunks_len,
+            ),
+            None => error!(
+                target: "unbonding_forecast", "Unable to read the bonding duration",
+            ),
+        }
+        sleep(Duration::from_secs(UNBONDING_ANALYZER_INTERVAL_SECS)).await;
+    }
+}
+
+async fn run_labels_command(args: &[String]) {
+    let labels_len = match (args.first().map(|p| p.as_str()), args.get(1)) {
+        (Some("import"), Some(path)) => import_labels(path).await,
+        (Some("export"), Some(path)) => export_labels(path).await,
+        _ => {
             error!(
-                target: "subscan_parser", "Nothing found",
+                target: "address_labels", "Usage: rs-subscan-parser labels <import|export> <file.csv|file.json>",
+            );
+            return;
+        }
+    };
+
+    match labels_len {
+        Some(labels_len) => info!(target: "address_labels", "Processed {labels_len} labels"),
+        None => error!(target: "address_labels", "Unable to process labels"),
+    }
+}
+
+async fn start_worker() {
+    loop {
+        let Some(head_block_number) = get_head_block_number().await else {
+            error!(
+                target: "subscan_parser", "Unable to get head block",
             );
             sleep(Duration::from_millis(1_000)).await;
             continue;
         };
 
+        let subscan_operations_task =
+            tokio::spawn(async move { parse_staking(head_block_number).await });
+        let subscan_transfers_task =
+            tokio::spawn(async move { parse_transfers(head_block_number).await });
+        let subscan_pools_task = tokio::spawn(async move { parse_pools(head_block_number).await });
+        let subscan_governance_task =
+            tokio::spawn(async move { parse_governance(head_block_number).await });
+        let subscan_rewards_task =
+            tokio::spawn(async move { parse_rewards(head_block_number).await });
+        let subscan_validator_events_task =
+            tokio::spawn(async move { parse_validator_events(head_block_number).await });
+
+        let subscan_operations = subscan_operations_task.await.ok();
+        let subscan_transfers = subscan_transfers_task.await.ok();
+        let subscan_pools = subscan_pools_task.await.ok();
+        let subscan_governance = subscan_governance_task.await.ok();
+        let subscan_rewards = subscan_rewards_task.await.ok().flatten();
+        let subscan_validator_events = subscan_validator_events_task.await.ok().flatten();
+
+        let (subscan_operations, cursors): (Vec<_>, Vec<_>) = vec![
+            subscan_operations,
+            subscan_transfers,
+            subscan_pools,
+            subscan_governance,
+        ]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .unzip();
+        let subscan_operations = subscan_operations.into_iter().flatten().collect_vec();
+        let mut cursors = cursors.into_iter().flatten().collect_vec();
+
         let subscan_operations_len = subscan_operations.len();
         let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
         mongodb_client_subscan
             .import_subscan_operations(subscan_operations)
             .await;
 
+        let mut staking_rewards_len = 0;
+        if let Some((staking_rewards, cursor)) = subscan_rewards {
+            staking_rewards_len = staking_rewards.len();
+            let eras = staking_rewards.iter().map(|p| p.era).collect_vec();
+            let mut mongodb_client_reward = MongoDbClientReward::new().await;
+            mongodb_client_reward.import_rewards(staking_rewards).await;
+            update_era_rewards(eras).await;
+            cursors.push(cursor);
+        }
+
+        let mut validator_events_len = 0;
+        if let Some((validator_events, mut s)) = subscan_validator_events {
+            validator_events_len = validator_events.len();
+            let mut mongodb_client_validator_event = MongoDbClientValidatorEvent::new().await;
+            mongodb_client_validator_event
+                .import_validator_events(validator_events)
+                .await;
+            cursors.append(&mut s);
+        }
+
+        // cursors are moved only after operations are safely stored
+        let max_lag = cursors
+            .iter()
+            .map(|c| c.get_lag())
+            .max()
+            .unwrap_or_default();
+        let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+        mongodb_client_cursor
+            .import_or_update_cursors(cursors)
+            .await;
+
         info!(
-            target: "subscan_parser", "Imported {} items",
+            target: "subscan_parser", "Imported {} items, {} rewards and {} validator events, max lag {} blocks",
             subscan_operations_len,
+            staking_rewards_len,
+            validator_events_len,
+            max_lag,
         );
         sleep(Duration::from_millis(1_000)).await;
     }
 }
+
+async fn start_rpc_worker() {
+    let substrate_rpc_parser = SubstrateRpcParser::new().await;
+    run_cursor_worker(RpcWorker {
+        substrate_rpc_parser,
+    })
+    .await;
+}
+
+struct RpcWorker {
+    substrate_rpc_parser: SubstrateRpcParser,
+}
+
+impl CursorWorker for RpcWorker {
+    type Item = SubscanOperation;
+
+    fn get_target(&self) -> &'static str {
+        "substrate_rpc_parser"
+    }
+
+    fn get_items_name(&self) -> &'static str {
+        "operations"
+    }
+
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>> {
+        self.substrate_rpc_parser.get_head_block_number().boxed()
+    }
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<SubscanOperation>, SubscanCursor)>> {
+        parse_rpc_blocks(&mut self.substrate_rpc_parser, head_block_number).boxed()
+    }
+
+    fn import(&mut self, subscan_operations: Vec<SubscanOperation>) -> BoxFuture<'_, ()> {
+        async move {
+            let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+            mongodb_client_subscan
+                .import_subscan_operations(subscan_operations)
+                .await;
+        }
+        .boxed()
+    }
+}
+
+async fn start_contract_events_worker(contracts: HashMap<String, Psp22Contract>) {
+    info!(
+        target: "contract_events_parser", "Started contract events worker for {} contracts.",
+        contracts.len(),
+    );
+
+    let substrate_rpc_parser = SubstrateRpcParser::new().await;
+    run_cursor_worker(ContractEventsWorker {
+        substrate_rpc_parser,
+        contracts,
+    })
+    .await;
+}
+
+struct ContractEventsWorker {
+    substrate_rpc_parser: SubstrateRpcParser,
+    contracts: HashMap<String, Psp22Contract>,
+}
+
+impl CursorWorker for ContractEventsWorker {
+    type Item = TokenOperation;
+
+    fn get_target(&self) -> &'static str {
+        "contract_events_parser"
+    }
+
+    fn get_items_name(&self) -> &'static str {
+        "token operations"
+    }
+
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>> {
+        self.substrate_rpc_parser.get_head_block_number().boxed()
+    }
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<TokenOperation>, SubscanCursor)>> {
+        parse_contract_events(
+            &mut self.substrate_rpc_parser,
+            &self.contracts,
+            head_block_number,
+        )
+        .boxed()
+    }
+
+    fn import(&mut self, token_operations: Vec<TokenOperation>) -> BoxFuture<'_, ()> {
+        async move {
+            let mut mongodb_client_token_operation = MongoDbClientTokenOperation::new().await;
+            mongodb_client_token_operation
+                .import_token_operations(token_operations)
+                .await;
+        }
+        .boxed()
+    }
+}
+
+async fn start_dex_swaps_worker(pairs: HashMap<String, DexPair>) {
+    info!(
+        target: "dex_swap_parser", "Started DEX swaps worker for {} pairs.",
+        pairs.len(),
+    );
+
+    let substrate_rpc_parser = SubstrateRpcParser::new().await;
+    run_cursor_worker(DexSwapsWorker {
+        substrate_rpc_parser,
+        pairs,
+    })
+    .await;
+}
+
+struct DexSwapsWorker {
+    substrate_rpc_parser: SubstrateRpcParser,
+    pairs: HashMap<String, DexPair>,
+}
+
+impl CursorWorker for DexSwapsWorker {
+    type Item = ExchangeTrade;
+
+    fn get_target(&self) -> &'static str {
+        "dex_swap_parser"
+    }
+
+    fn get_items_name(&self) -> &'static str {
+        "DEX trades"
+    }
+
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>> {
+        self.substrate_rpc_parser.get_head_block_number().boxed()
+    }
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<ExchangeTrade>, SubscanCursor)>> {
+        parse_dex_swaps(
+            &mut self.substrate_rpc_parser,
+            &self.pairs,
+            head_block_number,
+        )
+        .map(Some)
+        .boxed()
+    }
+
+    fn import(&mut self, exchange_trades: Vec<ExchangeTrade>) -> BoxFuture<'_, ()> {
+        async move {
+            let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
+            mongodb_client_exchanges
+                .import_exchange(exchange_trades)
+                .await;
+        }
+        .boxed()
+    }
+}
+
+async fn start_evm_transfers_worker(tokens: HashMap<String, EvmTokenConfig>) {
+    info!(
+        target: "evm_transfer_parser", "Started EVM transfers worker for {} tokens.",
+        tokens.len(),
+    );
+
+    let evm_rpc_client = EvmRpcClient::new().await;
+    run_cursor_worker(EvmTransfersWorker {
+        evm_rpc_client,
+        tokens,
+    })
+    .await;
+}
+
+struct EvmTransfersWorker {
+    evm_rpc_client: EvmRpcClient,
+    tokens: HashMap<String, EvmTokenConfig>,
+}
+
+impl CursorWorker for EvmTransfersWorker {
+    type Item = EvmTransfer;
+
+    fn get_target(&self) -> &'static str {
+        "evm_transfer_parser"
+    }
+
+    fn get_items_name(&self) -> &'static str {
+        "EVM transfers"
+    }
+
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>> {
+        self.evm_rpc_client.get_block_number().boxed()
+    }
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<EvmTransfer>, SubscanCursor)>> {
+        parse_evm_transfers(&mut self.evm_rpc_client, &self.tokens, head_block_number).boxed()
+    }
+
+    fn import(&mut self, evm_transfers: Vec<EvmTransfer>) -> BoxFuture<'_, ()> {
+        async move {
+            let mut mongodb_client_evm_transfer = MongoDbClientEvmTransfer::new().await;
+            mongodb_client_evm_transfer
+                .import_evm_transfers(evm_transfers)
+                .await;
+        }
+        .boxed()
+    }
+}
+
+/// Bridge events are read only up to the block transfers are ingested to, so the
+/// `BridgeOut` operations they complete are already stored.
+async fn start_bridge_events_worker(bridges: HashMap<String, Bridge>, ingestion_stream: String) {
+    info!(
+        target: "bridge_parser", "Started bridge events worker for {} bridges.",
+        bridges.len(),
+    );
+
+    let substrate_rpc_parser = SubstrateRpcParser::new().await;
+    run_cursor_worker(BridgeEventsWorker {
+        substrate_rpc_parser,
+        bridges,
+        ingestion_stream,
+    })
+    .await;
+}
+
+struct BridgeEventsWorker {
+    substrate_rpc_parser: SubstrateRpcParser,
+    bridges: HashMap<String, Bridge>,
+    ingestion_stream: String,
+}
+
+impl CursorWorker for BridgeEventsWorker {
+    type Item = (String, String);
+
+    fn get_target(&self) -> &'static str {
+        "bridge_parser"
+    }
+
+    fn get_items_name(&self) -> &'static str {
+        "bridge counterparties"
+    }
+
+    /// The ingestion cursor is the head here.
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>> {
+        async move {
+            let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+            let ingestion_cursor = mongodb_client_cursor
+                .get_cursor_by_stream(&self.ingestion_stream)
+                .await?;
+
+            Some(ingestion_cursor.block_number)
+        }
+        .boxed()
+    }
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<(String, String)>, SubscanCursor)>> {
+        parse_bridge_counterparties(
+            &mut self.substrate_rpc_parser,
+            &self.bridges,
+            head_block_number,
+        )
+        .map(Some)
+        .boxed()
+    }
+
+    fn import(&mut self, counterparties: Vec<(String, String)>) -> BoxFuture<'_, ()> {
+        async move {
+            let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+            mongodb_client_subscan
+                .update_bridge_counterparties(counterparties)
+                .await;
+        }
+        .boxed()
+    }
+}
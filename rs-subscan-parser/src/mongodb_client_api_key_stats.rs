@@ -0,0 +1,66 @@
+use crate::subscan_api_key_pool::SubscanApiKeyStats;
+use bson::doc;
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientApiKeyStats {
+    pub client_api_key_stats: MongoDbClient<SubscanApiKeyStats>,
+}
+
+impl MongoDbClientApiKeyStats {
+    pub async fn new() -> MongoDbClientApiKeyStats {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_API_KEY_STATS").unwrap();
+        let client_name = "mongodb_api_key_stats";
+        let client_api_key_stats = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_api_key_stats,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"api_key": 1u32})
+            .options(options)
+            .build();
+        self.client_api_key_stats.create_index(model, None).await;
+    }
+
+    pub async fn import_or_update_api_key_stats(&mut self, api_key_stats: Vec<SubscanApiKeyStats>) {
+        for doc in api_key_stats {
+            if self
+                .client_api_key_stats
+                .find_one(doc! { "api_key": doc.api_key.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_api_key_stats.insert_one(doc, None).await;
+                continue;
+            }
+
+            self.client_api_key_stats
+                .update_one(
+                    doc! { "api_key": doc.api_key },
+                    doc! { "$set": {
+                        "requests": doc.requests as i64,
+                        "successes": doc.successes as i64,
+                        "rate_limits": doc.rate_limits as i64,
+                        "failures": doc.failures as i64,
+                        "consecutive_failures": doc.consecutive_failures,
+                        "is_evicted": doc.is_evicted,
+                        "updated_timestamp": doc.updated_timestamp,
+                    }},
+                    None,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_api_key_stats(&mut self) -> Vec<SubscanApiKeyStats> {
+        self.client_api_key_stats.find(doc! {}, None).await
+    }
+}
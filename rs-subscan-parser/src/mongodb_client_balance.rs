@@ -0,0 +1,138 @@
+use crate::Balance;
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientBalance {
+    pub client_balance: MongoDbClient<Balance>,
+}
+
+impl MongoDbClientBalance {
+    pub async fn new() -> MongoDbClientBalance {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_BALANCE").unwrap();
+        let client_name = "mongodb_balance";
+        let client_balance = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_balance }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"address": 1u32})
+            .options(options)
+            .build();
+        self.client_balance.create_index(model, None).await;
+
+        let indexes = vec!["total", "updated_timestamp", "changed_timestamp"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_balance.create_index(model, None).await;
+        }
+    }
+
+    pub async fn import_or_update_balances(&mut self, balances: Vec<Balance>) {
+        for doc in balances {
+            if self
+                .client_balance
+                .find_one(doc! { "address": doc.address.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_balance.insert_one(doc, None).await;
+                continue;
+            }
+
+            self.client_balance
+                .update_one(
+                    doc! { "address": doc.address },
+                    doc! { "$set": {
+                        "free": doc.free,
+                        "reserved": doc.reserved,
+                        "staked": doc.staked,
+                        "total": doc.total,
+                        "supply_share": doc.supply_share,
+                        "previous_total": doc.previous_total,
+                        "changed_timestamp": doc.changed_timestamp,
+                        "updated_timestamp": doc.updated_timestamp,
+                        "baseline_total": doc.baseline_total,
+                        "baseline_timestamp": doc.baseline_timestamp,
+                    }},
+                    None,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_balance_by_address(&mut self, address: &str) -> Option<Balance> {
+        self.client_balance
+            .find_one(doc! { "address": address }, None)
+            .await
+    }
+
+    pub async fn get_balances_by_addresses(&mut self, addresses: Vec<String>) -> Vec<Balance> {
+        if addresses.is_empty() {
+            return Vec::new();
+        }
+
+        self.client_balance
+            .find(doc! { "address": { "$in": addresses } }, None)
+            .await
+    }
+
+    /// Largest tracked holders first.
+    pub async fn get_top_balances(&mut self, limit: i64) -> Vec<Balance> {
+        let options = FindOptions::builder()
+            .sort(doc! {"total": -1i32})
+            .limit(limit)
+            .build();
+
+        self.client_balance.find(doc! {}, Some(options)).await
+    }
+
+    /// Position among the tracked holders of a wallet holding the given total, from 1.
+    pub async fn get_holder_rank(&mut self, total: f64) -> u64 {
+        self.client_balance
+            .count_documents(doc! { "total": { "$gt": total } }, None)
+            .await
+            + 1
+    }
+
+    pub async fn get_stale_balances(
+        &mut self,
+        updated_before: DateTime,
+        limit: i64,
+    ) -> Vec<Balance> {
+        let query = doc! { "updated_timestamp": { "$lt": updated_before } };
+        let options = FindOptions::builder()
+            .sort(doc! {"updated_timestamp": 1i32})
+            .limit(limit)
+            .build();
+
+        self.client_balance.find(query, Some(options)).await
+    }
+
+    pub async fn get_changed_balances(&mut self, from_timestamp: i64) -> Vec<Balance> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"changed_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "changed_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+            },
+        };
+
+        self.client_balance.find(query, options).await
+    }
+}
@@ -0,0 +1,69 @@
+use crate::SubscanCursor;
+use bson::doc;
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientCursor {
+    pub client_cursor: MongoDbClient<SubscanCursor>,
+}
+
+impl MongoDbClientCursor {
+    pub async fn new() -> MongoDbClientCursor {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_CURSOR").unwrap();
+        let client_name = "mongodb_cursor";
+        let client_cursor = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_cursor }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"stream": 1u32})
+            .options(options)
+            .build();
+        self.client_cursor.create_index(model, None).await;
+    }
+
+    pub async fn import_or_update_cursors(&mut self, cursors: Vec<SubscanCursor>) {
+        for doc in cursors {
+            if self
+                .client_cursor
+                .find_one(doc! { "stream": doc.stream.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_cursor.insert_one(doc, None).await;
+                continue;
+            }
+
+            self.client_cursor
+                .update_one(
+                    doc! { "stream": doc.stream },
+                    doc! { "$set": {
+                        "block_number": doc.block_number as i64,
+                        "extrinsic_index": doc.extrinsic_index,
+                        "head_block_number": doc.head_block_number as i64,
+                        "updated_timestamp": doc.updated_timestamp,
+                    }},
+                    None,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_cursor_by_stream(&mut self, stream: &str) -> Option<SubscanCursor> {
+        let query = doc! {
+            "stream": stream
+        };
+
+        self.client_cursor.find_one(query, None).await
+    }
+
+    pub async fn get_cursors(&mut self) -> Vec<SubscanCursor> {
+        self.client_cursor.find(doc! {}, None).await
+    }
+}
@@ -0,0 +1,73 @@
+use crate::EraReward;
+use bson::doc;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientEraReward {
+    pub client_era_reward: MongoDbClient<EraReward>,
+}
+
+impl MongoDbClientEraReward {
+    pub async fn new() -> MongoDbClientEraReward {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_ERA_REWARD").unwrap();
+        let client_name = "mongodb_era_reward";
+        let client_era_reward = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_era_reward }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"era": 1u32})
+            .options(options)
+            .build();
+        self.client_era_reward.create_index(model, None).await;
+    }
+
+    pub async fn import_or_update_era_rewards(&mut self, era_rewards: Vec<EraReward>) {
+        for doc in era_rewards {
+            if self
+                .client_era_reward
+                .find_one(doc! { "era": doc.era }, None)
+                .await
+                .is_none()
+            {
+                self.client_era_reward.insert_one(doc, None).await;
+                continue;
+            }
+
+            self.client_era_reward
+                .update_one(
+                    doc! { "era": doc.era },
+                    doc! { "$set": {
+                        "reward_quantity": doc.reward_quantity,
+                        "reward_usd": doc.reward_usd,
+                        "validators": doc.validators,
+                        "nominators": doc.nominators,
+                        "updated_timestamp": doc.updated_timestamp,
+                    }},
+                    None,
+                )
+                .await;
+        }
+    }
+
+    /// Newest eras first.
+    pub async fn get_latest_era_rewards(&mut self, limit: i64) -> Vec<EraReward> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"era": -1i32})
+                .limit(limit)
+                .build(),
+        );
+
+        self.client_era_reward.find(doc! {}, options).await
+    }
+}
@@ -0,0 +1,80 @@
+use crate::EvmTransfer;
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientEvmTransfer {
+    pub client_evm_transfer: MongoDbClient<EvmTransfer>,
+}
+
+impl MongoDbClientEvmTransfer {
+    pub async fn new() -> MongoDbClientEvmTransfer {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_EVM_TRANSFER").unwrap();
+        let client_name = "mongodb_evm_transfer";
+        let client_evm_transfer = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_evm_transfer,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"transfer_id": 1u32})
+            .options(options)
+            .build();
+        self.client_evm_transfer.create_index(model, None).await;
+
+        let indexes = vec!["contract", "from_wallet", "to_wallet", "transfer_timestamp"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_evm_transfer.create_index(model, None).await;
+        }
+    }
+
+    /// Blocks retried after a failed iteration do not duplicate transfers.
+    pub async fn import_evm_transfers(&mut self, evm_transfers: Vec<EvmTransfer>) {
+        for doc in evm_transfers {
+            if self
+                .client_evm_transfer
+                .find_one(doc! { "transfer_id": doc.transfer_id.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_evm_transfer.insert_one(doc, None).await;
+            }
+        }
+    }
+
+    pub async fn get_filtered_evm_transfers(
+        &mut self,
+        from_timestamp: i64,
+        min_usd: f64,
+    ) -> Vec<EvmTransfer> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"transfer_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "transfer_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+            },
+            "transfer_usd": {
+                "$gte": min_usd,
+            },
+        };
+
+        self.client_evm_transfer.find(query, options).await
+    }
+}
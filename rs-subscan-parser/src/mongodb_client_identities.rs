@@ -1,6 +1,9 @@
 use crate::Identity;
-use bson::doc;
-use mongodb::{options::IndexOptions, IndexModel};
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
 use rs_utils::clients::mongodb_client::MongoDbClient;
 use std::env;
 
@@ -52,7 +55,12 @@ impl MongoDbClientIdentity {
             self.client_identity
                 .update_one(
                     doc! { "address": doc.address },
-                    doc! { "$set": { "identity": doc.identity }},
+                    doc! { "$set": {
+                        "identity": doc.identity,
+                        "parent": doc.parent,
+                        "judgement": doc.judgement,
+                        "updated_timestamp": doc.updated_timestamp,
+                    }},
                     None,
                 )
                 .await;
@@ -91,4 +99,45 @@ impl MongoDbClientIdentity {
             .filter(|m| !found.contains(m))
             .collect()
     }
+
+    /// Identities updated before the given time, oldest first. Entries stored before
+    /// refreshes were tracked come first of all.
+    pub async fn get_stale_identities(
+        &mut self,
+        updated_before: DateTime,
+        limit: i64,
+    ) -> Vec<Identity> {
+        let query = doc! {
+            "$or": [
+                { "updated_timestamp": null },
+                { "updated_timestamp": { "$lt": updated_before } },
+            ]
+        };
+        let options = FindOptions::builder()
+            .sort(doc! {"updated_timestamp": 1i32})
+            .limit(limit)
+            .build();
+
+        self.client_identity.find(query, Some(options)).await
+    }
+
+    pub async fn set_identity_updated_timestamp(
+        &mut self,
+        address: &str,
+        updated_timestamp: DateTime,
+    ) {
+        self.client_identity
+            .update_one(
+                doc! { "address": address },
+                doc! { "$set": { "updated_timestamp": updated_timestamp }},
+                None,
+            )
+            .await;
+    }
+
+    pub async fn delete_identity(&mut self, address: &str) {
+        self.client_identity
+            .delete_one(doc! { "address": address }, None)
+            .await;
+    }
 }
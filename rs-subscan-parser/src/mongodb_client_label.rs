@@ -0,0 +1,101 @@
+use crate::{Label, LabelCategory};
+use bson::doc;
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientLabel {
+    pub client_label: MongoDbClient<Label>,
+}
+
+impl MongoDbClientLabel {
+    pub async fn new() -> MongoDbClientLabel {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_LABEL").unwrap();
+        let client_name = "mongodb_label";
+        let client_label = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_label }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"address": 1u32})
+            .options(options)
+            .build();
+        self.client_label.create_index(model, None).await;
+
+        let indexes = vec!["category"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_label.create_index(model, None).await;
+        }
+    }
+
+    pub async fn import_or_update_labels(&mut self, labels: Vec<Label>) {
+        for doc in labels {
+            if self
+                .client_label
+                .find_one(doc! { "address": doc.address.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_label.insert_one(doc, None).await;
+                continue;
+            }
+
+            self.client_label
+                .update_one(
+                    doc! { "address": doc.address },
+                    doc! { "$set": {
+                        "label": doc.label,
+                        "category": doc.category.to_string(),
+                        "source": doc.source,
+                        "confidence": doc.confidence,
+                    }},
+                    None,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_label_by_address(&mut self, address: &str) -> Option<Label> {
+        self.client_label
+            .find_one(doc! { "address": address }, None)
+            .await
+    }
+
+    pub async fn get_labels_by_category(&mut self, category: LabelCategory) -> Vec<Label> {
+        self.client_label
+            .find(doc! { "category": category.to_string() }, None)
+            .await
+    }
+
+    pub async fn get_labels(&mut self) -> Vec<Label> {
+        self.client_label.find(doc! {}, None).await
+    }
+
+    pub async fn get_not_existing_addresses(&mut self, addresses: Vec<String>) -> Vec<String> {
+        if addresses.is_empty() {
+            return Vec::new();
+        }
+
+        let found = self
+            .client_label
+            .find(doc! { "address": { "$in": addresses.clone() } }, None)
+            .await
+            .into_iter()
+            .map(|m| m.address)
+            .collect::<Vec<String>>();
+
+        addresses
+            .into_iter()
+            .filter(|m| !found.contains(m))
+            .collect()
+    }
+}
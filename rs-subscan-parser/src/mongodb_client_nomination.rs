@@ -0,0 +1,173 @@
+use crate::Nomination;
+use bson::doc;
+use itertools::Itertools;
+use mongodb::{
+    options::{FindOneOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::{collections::HashMap, env};
+
+pub struct MongoDbClientNomination {
+    pub client_nomination: MongoDbClient<Nomination>,
+}
+
+impl MongoDbClientNomination {
+    pub async fn new() -> MongoDbClientNomination {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_NOMINATION").unwrap();
+        let client_name = "mongodb_nomination";
+        let client_nomination = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_nomination }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"nominator": 1u32, "from_block": 1u32})
+            .options(options)
+            .build();
+        self.client_nomination.create_index(model, None).await;
+
+        let indexes = vec!["targets"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_nomination.create_index(model, None).await;
+        }
+    }
+
+    /// Inserts nominations and keeps block ranges consistent, also when history is filled
+    /// out of order by backfill: a nomination ends where the next one of its nominator starts.
+    pub async fn import_nominations(&mut self, nominations: Vec<Nomination>) {
+        let nominations = nominations
+            .into_iter()
+            .into_group_map_by(|p| p.nominator.clone());
+        for (nominator, nominations) in nominations {
+            let stored_nominations = self
+                .client_nomination
+                .find(doc! { "nominator": nominator.clone() }, None)
+                .await;
+            let stored_to_blocks = stored_nominations
+                .iter()
+                .map(|p| (p.from_block, p.to_block))
+                .collect::<HashMap<_, _>>();
+
+            let nominations =
+                set_nomination_ranges(stored_nominations.into_iter().chain(nominations).collect());
+            for doc in nominations {
+                match stored_to_blocks.get(&doc.from_block) {
+                    None => {
+                        self.client_nomination.insert_one(doc, None).await;
+                    }
+                    Some(to_block) if *to_block != doc.to_block => {
+                        self.client_nomination
+                            .update_one(
+                                doc! {
+                                    "nominator": doc.nominator,
+                                    "from_block": doc.from_block as i64,
+                                },
+                                doc! { "$set": { "to_block": doc.to_block.map(|p| p as i64) }},
+                                None,
+                            )
+                            .await;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Nomination in effect at the given block.
+    pub async fn get_nomination_at_block(
+        &mut self,
+        nominator: &str,
+        block_number: u64,
+    ) -> Option<Nomination> {
+        let options = FindOneOptions::builder()
+            .sort(doc! {"from_block": -1i32})
+            .build();
+
+        self.client_nomination
+            .find_one(
+                doc! {
+                    "nominator": nominator,
+                    "from_block": { "$lte": block_number as i64 },
+                },
+                Some(options),
+            )
+            .await
+    }
+}
+
+/// Orders the nominations of one nominator and ends each where the next one starts. Of
+/// two nominations at the same block the first one is kept.
+pub fn set_nomination_ranges(nominations: Vec<Nomination>) -> Vec<Nomination> {
+    let nominations = nominations
+        .into_iter()
+        .unique_by(|p| p.from_block)
+        .sorted_by_key(|p| p.from_block)
+        .collect::<Vec<_>>();
+    let to_blocks = nominations
+        .iter()
+        .skip(1)
+        .map(|p| Some(p.from_block))
+        .chain([None])
+        .collect::<Vec<_>>();
+
+    nominations
+        .into_iter()
+        .zip(to_blocks)
+        .map(|(p, to_block)| Nomination { to_block, ..p })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mongodb_client_nomination::set_nomination_ranges, Nomination};
+
+    fn get_nomination(from_block: u64, targets: &[&str]) -> Nomination {
+        Nomination {
+            nominator: "5Nominator".to_string(),
+            targets: targets.iter().map(|p| p.to_string()).collect(),
+            extrinsic_index: format!("{from_block}-1"),
+            from_block,
+            to_block: None,
+            network: Default::default(),
+        }
+    }
+
+    #[test]
+    fn set_nomination_ranges_closes_out_of_order_history() {
+        // the stored nomination is current until backfill finds the ones around it
+        let nominations = set_nomination_ranges(vec![
+            get_nomination(200, &["5B"]),
+            get_nomination(300, &[]),
+            get_nomination(100, &["5A"]),
+            get_nomination(200, &["5Duplicate"]),
+            get_nomination(400, &["5C"]),
+        ]);
+
+        let ranges = nominations
+            .iter()
+            .map(|p| (p.from_block, p.to_block))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            vec![
+                (100, Some(200)),
+                (200, Some(300)),
+                (300, Some(400)),
+                (400, None)
+            ]
+        );
+        assert_eq!(nominations[1].targets, vec!["5B"]);
+
+        // the chill ends the nomination before it and nominates nobody itself
+        assert!(nominations[2].targets.is_empty());
+    }
+}
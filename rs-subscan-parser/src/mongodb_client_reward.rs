@@ -0,0 +1,110 @@
+use crate::StakingReward;
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientReward {
+    pub client_reward: MongoDbClient<StakingReward>,
+}
+
+impl MongoDbClientReward {
+    pub async fn new() -> MongoDbClientReward {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_REWARD").unwrap();
+        let client_name = "mongodb_reward";
+        let client_reward = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_reward }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"event_index": 1u32})
+            .options(options)
+            .build();
+        self.client_reward.create_index(model, None).await;
+
+        let indexes = vec!["era", "stash", "validator", "reward_timestamp"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_reward.create_index(model, None).await;
+        }
+    }
+
+    pub async fn import_rewards(&mut self, rewards: Vec<StakingReward>) {
+        for doc in rewards {
+            self.client_reward.insert_one(doc, None).await;
+        }
+    }
+
+    pub async fn get_rewards_by_era(&mut self, era: u32) -> Vec<StakingReward> {
+        let query = doc! {
+            "era": era
+        };
+
+        self.client_reward.find(query, None).await
+    }
+
+    pub async fn get_filtered_rewards(
+        &mut self,
+        from_timestamp: i64,
+        min_usd: f64,
+    ) -> Vec<StakingReward> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"reward_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "reward_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+            },
+            "reward_usd": {
+                "$gte": min_usd,
+            },
+        };
+
+        self.client_reward.find(query, options).await
+    }
+
+    pub async fn get_not_existing_rewards(
+        &mut self,
+        rewards: Vec<StakingReward>,
+    ) -> Vec<StakingReward> {
+        if rewards.is_empty() {
+            return Vec::new();
+        }
+
+        let indexes = rewards
+            .iter()
+            .map(|p| p.event_index.to_string())
+            .collect::<Vec<String>>();
+        let query = doc! {
+            "event_index": {
+                "$in": indexes
+            }
+        };
+
+        let found = self
+            .client_reward
+            .find(query, None)
+            .await
+            .into_iter()
+            .map(|m| m.event_index)
+            .collect::<Vec<String>>();
+
+        rewards
+            .into_iter()
+            .filter(|m| !found.contains(&m.event_index))
+            .collect()
+    }
+}
@@ -1,12 +1,13 @@
-use crate::SubscanOperation;
-use bson::{doc, DateTime};
+use crate::{subscan_parser::Network, OperationType, SubscanOperation};
+use bson::{doc, Bson, DateTime};
 use chrono::Utc;
 use mongodb::{
     options::{FindOptions, IndexOptions},
     IndexModel,
 };
 use rs_utils::clients::mongodb_client::MongoDbClient;
-use std::{env, time::Duration};
+use std::{collections::HashMap, env, time::Duration};
+use strum::IntoEnumIterator;
 
 static RECORDS_TTL_SECONDS: u64 = 90 * 24 * 60 * 60;
 
@@ -66,6 +67,7 @@ impl MongoDbClientSubscan {
 
     pub async fn get_filtered_operations(
         &mut self,
+        network: &Network,
         from_timestamp: i64,
         to_timestamp: Option<i64>,
     ) -> Vec<SubscanOperation> {
@@ -75,17 +77,94 @@ impl MongoDbClientSubscan {
                 .build(),
         );
         let to_timestamp = to_timestamp.unwrap_or(Utc::now().timestamp());
+        // records saved before networks were introduced have no network field
+        let mut networks = vec![bson::to_bson(network).unwrap()];
+        if *network == Network::default() {
+            networks.push(Bson::Null);
+        }
         let query = doc! {
             "operation_timestamp": {
                 "$gte": DateTime::from_millis(from_timestamp * 1000),
                 "$lt": DateTime::from_millis(to_timestamp * 1000),
+            },
+            "network": {
+                "$in": networks
             }
+        };
+
+        self.client_subscan.find(query, options).await
+    }
 
+    /// Transfers of any type received by the wallets since the given time, oldest first.
+    pub async fn get_transfers_to_wallets(
+        &mut self,
+        wallets: Vec<String>,
+        from_timestamp: i64,
+    ) -> Vec<SubscanOperation> {
+        if wallets.is_empty() {
+            return Vec::new();
+        }
+
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+        let operation_types = OperationType::iter()
+            .filter(|p| p.is_transfer())
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        let query = doc! {
+            "operation_type": { "$in": operation_types },
+            "to_wallet": { "$in": wallets },
+            "operation_timestamp": { "$gte": DateTime::from_millis(from_timestamp * 1000) },
         };
 
         self.client_subscan.find(query, options).await
     }
 
+    /// Sets the receiver on the other chain of stored `BridgeOut` operations, by extrinsic.
+    pub async fn update_bridge_counterparties(&mut self, counterparties: Vec<(String, String)>) {
+        for (extrinsic_index, counterparty) in counterparties {
+            self.client_subscan
+                .update_one(
+                    doc! {
+                        "extrinsic_index": extrinsic_index,
+                        "operation_type": OperationType::BridgeOut.to_string(),
+                        "bridge": { "$ne": null },
+                    },
+                    doc! { "$set": { "bridge.counterparty": counterparty } },
+                    None,
+                )
+                .await;
+        }
+    }
+
+    /// Substrate senders of stored `BridgeOut` operations by their receiver on the other
+    /// chain. Bridge transfers older than the records TTL are not known.
+    pub async fn get_bridge_senders(
+        &mut self,
+        counterparties: Vec<String>,
+    ) -> HashMap<String, String> {
+        if counterparties.is_empty() {
+            return HashMap::new();
+        }
+
+        let query = doc! {
+            "operation_type": OperationType::BridgeOut.to_string(),
+            "bridge.counterparty": {
+                "$in": counterparties
+            }
+        };
+
+        self.client_subscan
+            .find(query, None)
+            .await
+            .into_iter()
+            .filter_map(|p| Some((p.bridge?.counterparty?, p.from_wallet)))
+            .collect()
+    }
+
     pub async fn get_not_existing_operations(
         &mut self,
         subscan_operations: Vec<SubscanOperation>,
@@ -0,0 +1,85 @@
+use crate::TokenOperation;
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientTokenOperation {
+    pub client_token_operation: MongoDbClient<TokenOperation>,
+}
+
+impl MongoDbClientTokenOperation {
+    pub async fn new() -> MongoDbClientTokenOperation {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_TOKEN_OPERATION").unwrap();
+        let client_name = "mongodb_token_operation";
+        let client_token_operation = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_token_operation,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"event_index": 1u32})
+            .options(options)
+            .build();
+        self.client_token_operation.create_index(model, None).await;
+
+        let indexes = vec![
+            "contract",
+            "from_wallet",
+            "to_wallet",
+            "operation_timestamp",
+        ];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_token_operation.create_index(model, None).await;
+        }
+    }
+
+    /// Blocks retried after a failed iteration do not duplicate events.
+    pub async fn import_token_operations(&mut self, token_operations: Vec<TokenOperation>) {
+        for doc in token_operations {
+            if self
+                .client_token_operation
+                .find_one(doc! { "event_index": doc.event_index.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_token_operation.insert_one(doc, None).await;
+            }
+        }
+    }
+
+    pub async fn get_filtered_token_operations(
+        &mut self,
+        from_timestamp: i64,
+        min_usd: f64,
+    ) -> Vec<TokenOperation> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "operation_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+            },
+            "operation_usd": {
+                "$gte": min_usd,
+            },
+        };
+
+        self.client_token_operation.find(query, options).await
+    }
+}
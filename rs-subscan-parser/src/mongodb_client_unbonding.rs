@@ -0,0 +1,108 @@
+use crate::UnbondingChunk;
+use bson::doc;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientUnbonding {
+    pub client_unbonding: MongoDbClient<UnbondingChunk>,
+}
+
+impl MongoDbClientUnbonding {
+    pub async fn new() -> MongoDbClientUnbonding {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_UNBONDING").unwrap();
+        let client_name = "mongodb_unbonding";
+        let client_unbonding = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_unbonding }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"extrinsic_index": 1u32})
+            .options(options)
+            .build();
+        self.client_unbonding.create_index(model, None).await;
+
+        let indexes = vec!["stash", "unlock_timestamp", "withdrawn_timestamp"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_unbonding.create_index(model, None).await;
+        }
+    }
+
+    /// Chunks stored already keep their withdrawn state.
+    pub async fn import_unbonding_chunks(&mut self, unbonding_chunks: Vec<UnbondingChunk>) {
+        for doc in unbonding_chunks {
+            if self
+                .client_unbonding
+                .find_one(
+                    doc! { "extrinsic_index": doc.extrinsic_index.clone() },
+                    None,
+                )
+                .await
+                .is_none()
+            {
+                self.client_unbonding.insert_one(doc, None).await;
+            }
+        }
+    }
+
+    pub async fn update_unbonding_chunks(&mut self, unbonding_chunks: Vec<UnbondingChunk>) {
+        for doc in unbonding_chunks {
+            self.client_unbonding
+                .update_one(
+                    doc! { "extrinsic_index": doc.extrinsic_index },
+                    doc! { "$set": {
+                        "amount": doc.amount,
+                        "withdrawn_extrinsic_index": doc.withdrawn_extrinsic_index,
+                        "withdrawn_timestamp": doc.withdrawn_timestamp,
+                        "rebond_extrinsic_indexes": doc.rebond_extrinsic_indexes,
+                    }},
+                    None,
+                )
+                .await;
+        }
+    }
+
+    /// Chunks not withdrawn yet, soonest unlock first.
+    pub async fn get_outstanding_unbonding_chunks(&mut self) -> Vec<UnbondingChunk> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"unlock_timestamp": 1i32})
+                .build(),
+        );
+
+        self.client_unbonding
+            .find(doc! { "withdrawn_timestamp": null }, options)
+            .await
+    }
+
+    pub async fn get_unbonding_chunks_by_stashes(
+        &mut self,
+        stashes: Vec<String>,
+    ) -> Vec<UnbondingChunk> {
+        if stashes.is_empty() {
+            return Vec::new();
+        }
+
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"unbond_timestamp": 1i32})
+                .build(),
+        );
+
+        self.client_unbonding
+            .find(doc! { "stash": { "$in": stashes } }, options)
+            .await
+    }
+}
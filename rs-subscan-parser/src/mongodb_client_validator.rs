@@ -67,6 +67,22 @@ impl MongoDbClientValidator {
         self.client_validator.find_one(query, None).await
     }
 
+    pub async fn get_nominators_by_validator(&mut self, validator: &str) -> Vec<Validator> {
+        let query = doc! {
+            "validator": validator
+        };
+
+        self.client_validator.find(query, None).await
+    }
+
+    pub async fn count_nominators_by_validator(&mut self, validator: &str) -> u64 {
+        let query = doc! {
+            "validator": validator
+        };
+
+        self.client_validator.count_documents(query, None).await
+    }
+
     pub async fn get_not_existing_nominators(&mut self, nominators: Vec<String>) -> Vec<String> {
         if nominators.is_empty() {
             return Vec::new();
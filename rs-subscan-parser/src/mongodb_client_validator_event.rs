@@ -0,0 +1,101 @@
+use crate::ValidatorEvent;
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientValidatorEvent {
+    pub client_validator_event: MongoDbClient<ValidatorEvent>,
+}
+
+impl MongoDbClientValidatorEvent {
+    pub async fn new() -> MongoDbClientValidatorEvent {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_VALIDATOR_EVENT").unwrap();
+        let client_name = "mongodb_validator_event";
+        let client_validator_event = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_validator_event,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        // a single ban event can ban several validators
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"event_index": 1u32, "validator": 1u32})
+            .options(options)
+            .build();
+        self.client_validator_event.create_index(model, None).await;
+
+        let indexes = vec!["validator", "event_type", "event_timestamp"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_validator_event.create_index(model, None).await;
+        }
+    }
+
+    pub async fn import_validator_events(&mut self, validator_events: Vec<ValidatorEvent>) {
+        for doc in validator_events {
+            self.client_validator_event.insert_one(doc, None).await;
+        }
+    }
+
+    pub async fn get_filtered_validator_events(
+        &mut self,
+        from_timestamp: i64,
+    ) -> Vec<ValidatorEvent> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"event_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "event_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+            },
+        };
+
+        self.client_validator_event.find(query, options).await
+    }
+
+    pub async fn get_not_existing_validator_events(
+        &mut self,
+        validator_events: Vec<ValidatorEvent>,
+    ) -> Vec<ValidatorEvent> {
+        if validator_events.is_empty() {
+            return Vec::new();
+        }
+
+        let indexes = validator_events
+            .iter()
+            .map(|p| p.event_index.to_string())
+            .collect::<Vec<String>>();
+        let query = doc! {
+            "event_index": {
+                "$in": indexes
+            }
+        };
+
+        let found = self
+            .client_validator_event
+            .find(query, None)
+            .await
+            .into_iter()
+            .map(|m| (m.event_index, m.validator))
+            .collect::<Vec<_>>();
+
+        validator_events
+            .into_iter()
+            .filter(|m| !found.contains(&(m.event_index.clone(), m.validator.clone())))
+            .collect()
+    }
+}
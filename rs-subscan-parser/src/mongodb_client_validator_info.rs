@@ -0,0 +1,106 @@
+use crate::{ValidatorInfo, ValidatorStatus};
+use bson::{doc, DateTime};
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientValidatorInfo {
+    pub client_validator_info: MongoDbClient<ValidatorInfo>,
+}
+
+impl MongoDbClientValidatorInfo {
+    pub async fn new() -> MongoDbClientValidatorInfo {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_VALIDATOR_INFO").unwrap();
+        let client_name = "mongodb_validator_info";
+        let client_validator_info = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_validator_info,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"validator": 1u32})
+            .options(options)
+            .build();
+        self.client_validator_info.create_index(model, None).await;
+
+        let indexes = vec!["status"];
+        for index in indexes {
+            let model = IndexModel::builder()
+                .keys(doc! {index: 1u32})
+                .options(None)
+                .build();
+            self.client_validator_info.create_index(model, None).await;
+        }
+    }
+
+    /// Eras a validator was seen active in are accumulated, everything else is replaced.
+    pub async fn import_or_update_validator_infos(&mut self, validator_infos: Vec<ValidatorInfo>) {
+        for doc in validator_infos {
+            if self
+                .client_validator_info
+                .find_one(doc! { "validator": doc.validator.clone() }, None)
+                .await
+                .is_none()
+            {
+                self.client_validator_info.insert_one(doc, None).await;
+                continue;
+            }
+
+            let mut update = doc! { "$set": {
+                "identity": doc.identity,
+                "commission": doc.commission,
+                "self_stake": doc.self_stake,
+                "total_stake": doc.total_stake,
+                "nominators": doc.nominators,
+                "status": doc.status.to_string(),
+                "era": doc.era,
+                "updated_timestamp": doc.updated_timestamp,
+            }};
+            if doc.status == ValidatorStatus::Active {
+                update.insert("$addToSet", doc! { "active_eras": doc.era });
+            }
+
+            self.client_validator_info
+                .update_one(doc! { "validator": doc.validator }, update, None)
+                .await;
+        }
+    }
+
+    /// Validators missing from a complete refresh have left both lists.
+    pub async fn mark_missing_validator_infos_inactive(&mut self, validators: Vec<String>) {
+        self.client_validator_info
+            .update_many(
+                doc! {
+                    "validator": { "$nin": validators },
+                    "status": { "$ne": ValidatorStatus::Inactive.to_string() },
+                },
+                doc! { "$set": {
+                    "status": ValidatorStatus::Inactive.to_string(),
+                    "updated_timestamp": DateTime::now(),
+                }},
+                None,
+            )
+            .await;
+    }
+
+    pub async fn get_validator_info(&mut self, validator: &str) -> Option<ValidatorInfo> {
+        self.client_validator_info
+            .find_one(doc! { "validator": validator }, None)
+            .await
+    }
+
+    pub async fn get_validator_infos_by_status(
+        &mut self,
+        status: ValidatorStatus,
+    ) -> Vec<ValidatorInfo> {
+        self.client_validator_info
+            .find(doc! { "status": status.to_string() }, None)
+            .await
+    }
+}
@@ -0,0 +1,310 @@
+use crate::mongodb_client_api_key_stats::MongoDbClientApiKeyStats;
+use bson::DateTime;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp, env,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+static API_KEY_POOL: OnceLock<Mutex<SubscanApiKeyPool>> = OnceLock::new();
+
+static RATE_LIMIT_COOLDOWN_MS: u64 = 2_000;
+static MAX_RATE_LIMIT_COOLDOWN_MS: u64 = 60_000;
+static EMPTY_POOL_WAIT_MS: u64 = 1_000;
+static EVICTION_DURATION_MS: u64 = 60 * 60 * 1_000;
+static MAX_CONSECUTIVE_FAILURES: u32 = 10;
+static STATS_LOG_INTERVAL_REQUESTS: u64 = 1_000;
+pub static API_KEY_STATS_INTERVAL_SECS: u64 = 60;
+
+// subscan response codes, https://support.subscan.io/#errors
+static RATE_LIMIT_CODES: [u64; 2] = [429, 20008];
+static INVALID_KEY_CODES: [u64; 4] = [401, 403, 10004, 10005];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubscanApiKeyOutcome {
+    Success,
+    RateLimited,
+    InvalidKey,
+    Failure,
+}
+
+impl SubscanApiKeyOutcome {
+    pub fn from_code(code: u64) -> SubscanApiKeyOutcome {
+        if code == 0 {
+            SubscanApiKeyOutcome::Success
+        } else if RATE_LIMIT_CODES.contains(&code) {
+            SubscanApiKeyOutcome::RateLimited
+        } else if INVALID_KEY_CODES.contains(&code) {
+            SubscanApiKeyOutcome::InvalidKey
+        } else {
+            SubscanApiKeyOutcome::Failure
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct SubscanApiKeyStats {
+    pub api_key: String,
+    pub requests: u64,
+    pub successes: u64,
+    pub rate_limits: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub is_evicted: bool,
+    pub updated_timestamp: Option<DateTime>,
+}
+
+#[derive(Clone, Debug)]
+struct SubscanApiKey {
+    api_key: String,
+    stats: SubscanApiKeyStats,
+    cooldown_until: Option<Instant>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubscanApiKeyPool {
+    api_keys: Vec<SubscanApiKey>,
+    total_requests: u64,
+}
+
+impl SubscanApiKeyPool {
+    pub fn new(api_keys: Vec<String>) -> SubscanApiKeyPool {
+        let mut unique_keys: Vec<String> = Vec::new();
+        for api_key in api_keys {
+            let api_key = api_key.trim().to_string();
+            if api_key.is_empty() || unique_keys.contains(&api_key) {
+                continue;
+            }
+            unique_keys.push(api_key);
+        }
+
+        let api_keys = unique_keys
+            .into_iter()
+            .map(|api_key| SubscanApiKey {
+                stats: SubscanApiKeyStats {
+                    api_key: mask_api_key(&api_key),
+                    ..Default::default()
+                },
+                api_key,
+                cooldown_until: None,
+            })
+            .collect();
+
+        SubscanApiKeyPool {
+            api_keys,
+            total_requests: 0,
+        }
+    }
+
+    pub fn from_env() -> SubscanApiKeyPool {
+        let api_keys = env::var("SUBSCAN_API_KEY")
+            .unwrap()
+            .split(',')
+            .map(|p| p.to_string())
+            .collect();
+
+        SubscanApiKeyPool::new(api_keys)
+    }
+
+    /// Picks the least used key that is not cooling down. If every key is cooling down,
+    /// returns how long to wait until the first one becomes available.
+    pub fn acquire(&mut self, now: Instant) -> Result<String, Duration> {
+        for api_key in self.api_keys.iter_mut() {
+            if api_key.cooldown_until.is_some_and(|p| p <= now) {
+                api_key.cooldown_until = None;
+                api_key.stats.is_evicted = false;
+            }
+        }
+
+        let api_key = self
+            .api_keys
+            .iter_mut()
+            .filter(|p| p.cooldown_until.is_none())
+            .min_by_key(|p| p.stats.requests);
+        if let Some(api_key) = api_key {
+            api_key.stats.requests += 1;
+            self.total_requests += 1;
+            return Ok(api_key.api_key.clone());
+        }
+
+        let wait = self
+            .api_keys
+            .iter()
+            .filter_map(|p| p.cooldown_until)
+            .min()
+            .map(|p| p.saturating_duration_since(now))
+            .unwrap_or(Duration::from_millis(EMPTY_POOL_WAIT_MS));
+        Err(wait)
+    }
+
+    pub fn report(&mut self, api_key: &str, outcome: SubscanApiKeyOutcome, now: Instant) {
+        let Some(key) = self.api_keys.iter_mut().find(|p| p.api_key == api_key) else {
+            return;
+        };
+
+        match outcome {
+            SubscanApiKeyOutcome::Success => {
+                key.stats.successes += 1;
+                key.stats.consecutive_failures = 0;
+            }
+            SubscanApiKeyOutcome::RateLimited => {
+                key.stats.rate_limits += 1;
+                key.stats.consecutive_failures += 1;
+
+                // backing off exponentially while the key keeps hitting the limit
+                let cooldown_ms = RATE_LIMIT_COOLDOWN_MS
+                    .saturating_mul(2u64.saturating_pow(key.stats.consecutive_failures - 1));
+                let cooldown_ms = cmp::min(cooldown_ms, MAX_RATE_LIMIT_COOLDOWN_MS);
+                key.cooldown_until = Some(now + Duration::from_millis(cooldown_ms));
+            }
+            SubscanApiKeyOutcome::InvalidKey => {
+                key.stats.failures += 1;
+                key.stats.consecutive_failures += 1;
+                key.stats.is_evicted = true;
+                key.cooldown_until = Some(now + Duration::from_millis(EVICTION_DURATION_MS));
+                error!(target: "subscan_api_key_pool", "Evicted api key {} for {} ms.", key.stats.api_key, EVICTION_DURATION_MS);
+            }
+            SubscanApiKeyOutcome::Failure => {
+                key.stats.failures += 1;
+                key.stats.consecutive_failures += 1;
+
+                // a single failure can be caused by the request itself, not by the key
+                if key.stats.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                    key.stats.is_evicted = true;
+                    key.cooldown_until = Some(now + Duration::from_millis(EVICTION_DURATION_MS));
+                    error!(target: "subscan_api_key_pool", "Evicted api key {} after {} failures in a row.", key.stats.api_key, key.stats.consecutive_failures);
+                }
+            }
+        }
+
+        if self
+            .total_requests
+            .is_multiple_of(STATS_LOG_INTERVAL_REQUESTS)
+        {
+            self.log_stats();
+        }
+    }
+
+    pub fn get_stats(&self) -> Vec<SubscanApiKeyStats> {
+        self.api_keys.iter().map(|p| p.stats.clone()).collect()
+    }
+
+    pub fn log_stats(&self) {
+        for s in self.get_stats() {
+            info!(
+                target: "subscan_api_key_pool", "Api key {}: requests {}, successes {}, rate limits {}, failures {}, evicted {}",
+                s.api_key,
+                s.requests,
+                s.successes,
+                s.rate_limits,
+                s.failures,
+                s.is_evicted,
+            );
+        }
+    }
+}
+
+pub fn get_api_key_pool() -> &'static Mutex<SubscanApiKeyPool> {
+    API_KEY_POOL.get_or_init(|| Mutex::new(SubscanApiKeyPool::from_env()))
+}
+
+/// Saves a snapshot of the per-key stats of this process so they can be charted and
+/// alerted on outside of the logs.
+pub async fn save_api_key_stats() -> usize {
+    let updated_timestamp = Some(DateTime::now());
+    let api_key_stats = get_api_key_pool()
+        .lock()
+        .unwrap()
+        .get_stats()
+        .into_iter()
+        .map(|p| SubscanApiKeyStats {
+            updated_timestamp,
+            ..p
+        })
+        .collect::<Vec<_>>();
+    let api_key_stats_len = api_key_stats.len();
+
+    let mut mongodb_client_api_key_stats = MongoDbClientApiKeyStats::new().await;
+    mongodb_client_api_key_stats
+        .import_or_update_api_key_stats(api_key_stats)
+        .await;
+
+    api_key_stats_len
+}
+
+fn mask_api_key(api_key: &str) -> String {
+    if api_key.len() <= 8 {
+        return "*".repeat(api_key.len());
+    }
+
+    format!("{}...{}", &api_key[..4], &api_key[api_key.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subscan_api_key_pool::{SubscanApiKeyOutcome, SubscanApiKeyPool};
+    use std::time::{Duration, Instant};
+
+    fn get_pool() -> SubscanApiKeyPool {
+        SubscanApiKeyPool::new(vec![
+            "key_number_one".to_string(),
+            " key_number_two".to_string(),
+            "key_number_one".to_string(),
+            "".to_string(),
+        ])
+    }
+
+    #[test]
+    fn api_key_pool_spreads_load_evenly() {
+        let mut pool = get_pool();
+        let now = Instant::now();
+
+        let first = pool.acquire(now).unwrap();
+        let second = pool.acquire(now).unwrap();
+        assert_ne!(first, second);
+
+        let stats = pool.get_stats();
+        assert_eq!(stats.len(), 2);
+        assert!(stats.iter().all(|p| p.requests == 1));
+        assert_eq!(stats[0].api_key, "key_..._one");
+    }
+
+    #[test]
+    fn api_key_pool_cools_down_rate_limited_keys() {
+        let mut pool = get_pool();
+        let now = Instant::now();
+
+        let first = pool.acquire(now).unwrap();
+        pool.report(&first, SubscanApiKeyOutcome::from_code(20008), now);
+
+        // only the healthy key is handed out while the first one cools down
+        for _ in 0..3 {
+            assert_ne!(pool.acquire(now).unwrap(), first);
+        }
+
+        let second = pool.acquire(now).unwrap();
+        pool.report(&second, SubscanApiKeyOutcome::RateLimited, now);
+        let wait = pool.acquire(now).unwrap_err();
+        assert!(wait <= Duration::from_millis(2_000));
+
+        let later = now + Duration::from_millis(2_001);
+        assert!(pool.acquire(later).is_ok());
+    }
+
+    #[test]
+    fn api_key_pool_evicts_invalid_keys() {
+        let mut pool = get_pool();
+        let now = Instant::now();
+
+        let first = pool.acquire(now).unwrap();
+        pool.report(&first, SubscanApiKeyOutcome::from_code(10004), now);
+        assert!(pool.get_stats().iter().any(|p| p.is_evicted));
+
+        let later = now + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_ne!(pool.acquire(later).unwrap(), first);
+        }
+    }
+}
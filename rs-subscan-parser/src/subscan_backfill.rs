@@ -0,0 +1,251 @@
+use crate::{
+    mongodb_client_cursor::MongoDbClientCursor,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_cursor::{get_cursor_or_default, parse_stream_until_block},
+    subscan_governance_parser::enrich_governance_operations,
+    subscan_parser::{Network, SubscanParser},
+    subscan_pool_parser::enrich_pool_operations,
+    subscan_stake_parser::enrich_staking_operations,
+    subscan_transfer_parser::enrich_transfers,
+    Module, SubscanStream,
+};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use log::{error, info};
+use std::{env, str::FromStr, time::Duration};
+use tokio::time::sleep;
+
+static BACKFILL_CURSOR_PREFIX: &str = "backfill";
+static DEFAULT_BACKFILL_DELAY_MS: u64 = 500;
+static BACKFILL_MAX_STALLED_ATTEMPTS: u32 = 10;
+static BACKFILL_MAX_BACKOFF_MS: u64 = 60_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackfillOptions {
+    pub stream: SubscanStream,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub from_timestamp: Option<i64>,
+    pub to_timestamp: Option<i64>,
+}
+
+impl BackfillOptions {
+    /// Parses `<stream> [--from-block N] [--to-block N] [--from-date YYYY-MM-DD] [--to-date YYYY-MM-DD]`.
+    /// Either a starting block or a starting date is required, the end defaults to the head block.
+    pub fn from_args(args: &[String]) -> Option<BackfillOptions> {
+        let stream = SubscanStream::from_str(args.first()?).ok()?;
+        if args.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let mut options = BackfillOptions {
+            stream,
+            from_block: None,
+            to_block: None,
+            from_timestamp: None,
+            to_timestamp: None,
+        };
+        for (key, value) in args[1..].iter().tuples() {
+            match key.as_str() {
+                "--from-block" => options.from_block = Some(value.parse().ok()?),
+                "--to-block" => options.to_block = Some(value.parse().ok()?),
+                "--from-date" => options.from_timestamp = Some(parse_date(value)?),
+                "--to-date" => options.to_timestamp = Some(parse_date(value)?),
+                _ => return None,
+            }
+        }
+
+        if options.from_block.is_none() && options.from_timestamp.is_none() {
+            return None;
+        }
+
+        Some(options)
+    }
+}
+
+/// Walks the stream from the starting block to the end block through the same enrichment
+/// as live ingestion. Progress is checkpointed after every imported batch, so running the
+/// same command again resumes where it stopped. Gives up after
+/// `BACKFILL_MAX_STALLED_ATTEMPTS` attempts in a row that don't move the checkpoint.
+pub async fn run_backfill(options: BackfillOptions) -> Option<()> {
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    let from_block = match options.from_block {
+        Some(from_block) => from_block,
+        None => {
+            subscan_parser
+                .get_block_number_by_timestamp(options.from_timestamp?)
+                .await?
+        }
+    };
+    let to_block = match (options.to_block, options.to_timestamp) {
+        (Some(to_block), _) => to_block,
+        (None, Some(to_timestamp)) => {
+            subscan_parser
+                .get_block_number_by_timestamp(to_timestamp)
+                .await?
+        }
+        (None, None) => subscan_parser.get_head_block_number().await?,
+    };
+
+    let request_delay = Duration::from_millis(
+        env::var("SUBSCAN_BACKFILL_DELAY_MS")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_BACKFILL_DELAY_MS),
+    );
+
+    let checkpoint_name = format!(
+        "{BACKFILL_CURSOR_PREFIX}_{}_{from_block}_{to_block}",
+        options.stream
+    );
+    let mut cursor = get_cursor_or_default(&checkpoint_name, from_block.saturating_sub(1)).await;
+    cursor.head_block_number = to_block;
+
+    info!(
+        target: "subscan_backfill", "Backfilling {} from block {} to block {}, resuming at block {}",
+        options.stream,
+        from_block,
+        to_block,
+        cursor.block_number + 1,
+    );
+
+    let mut stalled_attempts = 0;
+    while cursor.block_number < to_block {
+        if stalled_attempts >= BACKFILL_MAX_STALLED_ATTEMPTS {
+            error!(
+                target: "subscan_backfill", "Backfill of {} is stuck at block {}, giving up after {} attempts",
+                options.stream,
+                cursor.block_number,
+                stalled_attempts,
+            );
+            return None;
+        }
+        if stalled_attempts > 0 {
+            sleep(get_backoff(stalled_attempts)).await;
+        }
+
+        // a window failing to fetch leaves the returned cursor where it was
+        let Some((subscan_operations, identities, new_cursor)) = parse_stream_until_block(
+            options.stream.clone(),
+            cursor.clone(),
+            to_block,
+            request_delay,
+        )
+        .await
+        .filter(|p| p.2.block_number > cursor.block_number) else {
+            stalled_attempts += 1;
+            continue;
+        };
+
+        let subscan_operations = match options.stream {
+            SubscanStream::Transfers => enrich_transfers(subscan_operations, identities).await,
+            SubscanStream::Extrinsics(Module::NominationPools, _) => {
+                enrich_pool_operations(subscan_operations, Vec::new()).await
+            }
+            SubscanStream::Extrinsics(ref module, _) if module.is_governance() => {
+                enrich_governance_operations(subscan_operations).await
+            }
+            SubscanStream::Extrinsics(ref module, _) if module.is_wrapper() => {
+                enrich_staking_operations(Vec::new(), subscan_operations).await
+            }
+            SubscanStream::Extrinsics(_, _) => {
+                enrich_staking_operations(subscan_operations, Vec::new()).await
+            }
+        };
+
+        // keeping the checkpoint, same blocks will be retried
+        let Some(subscan_operations) = subscan_operations else {
+            error!(
+                target: "subscan_backfill", "Failed to enrich blocks {}-{}.",
+                cursor.block_number + 1,
+                new_cursor.block_number,
+            );
+            stalled_attempts += 1;
+            continue;
+        };
+        stalled_attempts = 0;
+
+        let subscan_operations_len = subscan_operations.len();
+        let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+        mongodb_client_subscan
+            .import_subscan_operations(subscan_operations)
+            .await;
+
+        let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+        mongodb_client_cursor
+            .import_or_update_cursors(vec![new_cursor.clone()])
+            .await;
+        cursor = new_cursor;
+
+        info!(
+            target: "subscan_backfill", "Imported {} items, at block {} of {}",
+            subscan_operations_len,
+            cursor.block_number,
+            to_block,
+        );
+    }
+
+    info!(target: "subscan_backfill", "Backfill of {} is finished", options.stream);
+
+    Some(())
+}
+
+/// Doubles the delay with every attempt in a row that didn't move the checkpoint.
+fn get_backoff(stalled_attempts: u32) -> Duration {
+    let backoff_ms = 1_000u64 << stalled_attempts.min(6);
+    Duration::from_millis(backoff_ms.min(BACKFILL_MAX_BACKOFF_MS))
+}
+
+fn parse_date(date: &str) -> Option<i64> {
+    let date_time = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?;
+    Some(date_time.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_backfill::{get_backoff, BackfillOptions, BACKFILL_MAX_STALLED_ATTEMPTS},
+        ExtrinsicsType, Module, SubscanStream,
+    };
+    use std::time::Duration;
+
+    fn to_args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn backfill_options_from_args_works() {
+        let options = BackfillOptions::from_args(&to_args(
+            "staking_bond_extra --from-block 10 --to-block 20",
+        ))
+        .unwrap();
+        assert_eq!(
+            options.stream,
+            SubscanStream::Extrinsics(Module::Staking, ExtrinsicsType::BondExtra)
+        );
+        assert_eq!(options.from_block, Some(10));
+        assert_eq!(options.to_block, Some(20));
+
+        let options =
+            BackfillOptions::from_args(&to_args("transfers --from-date 2023-11-01")).unwrap();
+        assert_eq!(options.stream, SubscanStream::Transfers);
+        assert_eq!(options.from_timestamp, Some(1_698_796_800));
+        assert_eq!(options.to_timestamp, None);
+
+        assert!(BackfillOptions::from_args(&to_args("transfers")).is_none());
+        assert!(BackfillOptions::from_args(&to_args("transfers --from-block")).is_none());
+        assert!(BackfillOptions::from_args(&to_args("unknown --from-block 1")).is_none());
+    }
+
+    #[test]
+    fn get_backoff_works() {
+        assert_eq!(get_backoff(1), Duration::from_secs(2));
+        assert_eq!(get_backoff(3), Duration::from_secs(8));
+        assert_eq!(
+            get_backoff(BACKFILL_MAX_STALLED_ATTEMPTS),
+            Duration::from_secs(60)
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use crate::SubscanEvent;
+use parity_scale_codec::Encode;
+use serde_json::Value;
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    hashing::blake2_256,
+};
+
+static MAX_CALL_DEPTH: usize = 8;
+static MULTISIG_PREFIX: &[u8; 16] = b"modlpy/utilisuba";
+
+/// A call found inside an extrinsic, with the account it is dispatched from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubscanCall {
+    pub origin: AccountId32,
+    pub call_module: String,
+    pub call_name: String,
+    pub params: Vec<Value>,
+}
+
+impl SubscanCall {
+    pub fn get_param(&self, name: &str) -> Option<&Value> {
+        self.params
+            .iter()
+            .find(|p| p.get("name").and_then(|n| n.as_str()) == Some(name))?
+            .get("value")
+    }
+}
+
+/// Walks utility batches, proxies and multisigs down to the calls they dispatch.
+/// Proxied calls are attributed to the real account and multisig calls to the multisig
+/// account, so a staking call always carries the stash it acts on. An `as_multi` only
+/// dispatches its call once the last approval executes it, so its call is kept only when
+/// the multisig is among `executed_multisigs`.
+pub fn decode_nested_calls(
+    call: &Value,
+    origin: AccountId32,
+    executed_multisigs: &[AccountId32],
+) -> Vec<SubscanCall> {
+    let mut nested_calls = NestedCalls::default();
+    decode_call(call, origin, 0, Some(executed_multisigs), &mut nested_calls);
+    nested_calls.calls
+}
+
+/// Accounts of the multisigs whose `as_multi` calls are wrapped in the call, to look up
+/// whether the extrinsic executed them.
+pub fn get_multisig_accounts(call: &Value, origin: AccountId32) -> Vec<AccountId32> {
+    let mut nested_calls = NestedCalls::default();
+    decode_call(call, origin, 0, None, &mut nested_calls);
+    nested_calls.multisigs
+}
+
+#[derive(Default)]
+struct NestedCalls {
+    calls: Vec<SubscanCall>,
+    multisigs: Vec<AccountId32>,
+}
+
+fn decode_call(
+    call: &Value,
+    origin: AccountId32,
+    depth: usize,
+    executed_multisigs: Option<&[AccountId32]>,
+    nested_calls: &mut NestedCalls,
+) -> Option<()> {
+    if depth > MAX_CALL_DEPTH {
+        return None;
+    }
+
+    let call = SubscanCall {
+        origin,
+        call_module: call.get("call_module")?.as_str()?.to_lowercase(),
+        call_name: call.get("call_name")?.as_str()?.to_lowercase(),
+        params: call.get("params")?.as_array()?.clone(),
+    };
+
+    match (call.call_module.as_str(), call.call_name.as_str()) {
+        ("utility", "batch" | "batch_all" | "force_batch") => {
+            for c in call.get_param("calls")?.as_array()? {
+                decode_call(
+                    c,
+                    call.origin.clone(),
+                    depth + 1,
+                    executed_multisigs,
+                    nested_calls,
+                );
+            }
+        }
+        ("proxy", "proxy" | "proxy_announced") => {
+            let real = get_account_id(call.get_param("real")?)?;
+            decode_call(
+                call.get_param("call")?,
+                real,
+                depth + 1,
+                executed_multisigs,
+                nested_calls,
+            )?;
+        }
+        ("multisig", "as_multi_threshold_1") => {
+            // dispatched right away, the extrinsic fails together with the call
+            let mut signatories = get_signatories(&call)?;
+            signatories.push(call.origin.clone());
+
+            let multisig = get_multisig_account(signatories, 1);
+            decode_call(
+                call.get_param("call")?,
+                multisig,
+                depth + 1,
+                executed_multisigs,
+                nested_calls,
+            )?;
+        }
+        ("multisig", "as_multi") => {
+            let threshold = call
+                .get_param("threshold")
+                .and_then(get_number)
+                .unwrap_or(1);
+            let mut signatories = get_signatories(&call)?;
+            signatories.push(call.origin.clone());
+
+            let multisig = get_multisig_account(signatories, threshold as u16);
+            nested_calls.multisigs.push(multisig.clone());
+            if executed_multisigs.is_some_and(|p| !p.contains(&multisig)) {
+                return Some(());
+            }
+
+            decode_call(
+                call.get_param("call")?,
+                multisig,
+                depth + 1,
+                executed_multisigs,
+                nested_calls,
+            )?;
+        }
+        _ => nested_calls.calls.push(call),
+    }
+
+    Some(())
+}
+
+/// Multisigs the extrinsic executed, read from `MultisigExecuted` events whose dispatch
+/// result is `Ok`.
+pub fn get_executed_multisigs(events: &[SubscanEvent]) -> Vec<AccountId32> {
+    events
+        .iter()
+        .filter(|p| p.module_id == "multisig" && p.event_id == "MultisigExecuted")
+        .filter_map(|p| {
+            let get_param = |name: &str| {
+                p.event_params
+                    .iter()
+                    .find(|e| e.name == name)
+                    .map(|e| e.value.as_str())
+            };
+
+            let result = get_param("result")?;
+            let is_ok = result == "Ok"
+                || serde_json::from_str::<Value>(result)
+                    .is_ok_and(|r| r.get("Ok").is_some() || r.get("ok").is_some());
+            if !is_ok {
+                return None;
+            }
+
+            get_account_id(&Value::String(get_param("multisig")?.to_string()))
+        })
+        .collect()
+}
+
+fn get_signatories(call: &SubscanCall) -> Option<Vec<AccountId32>> {
+    call.get_param("other_signatories")?
+        .as_array()?
+        .iter()
+        .map(get_account_id)
+        .collect()
+}
+
+/// Derives the account of a multisig the same way the multisig pallet does.
+pub fn get_multisig_account(mut signatories: Vec<AccountId32>, threshold: u16) -> AccountId32 {
+    signatories.sort();
+    let entropy = (MULTISIG_PREFIX, signatories, threshold).using_encoded(blake2_256);
+    AccountId32::from(entropy)
+}
+
+/// Accepts an account as hex, as ss58 or wrapped in a `MultiAddress::Id`.
+pub fn get_account_id(value: &Value) -> Option<AccountId32> {
+    let value = value.get("Id").unwrap_or(value).as_str()?;
+    if let Some(addr) = value.strip_prefix("0x") {
+        let byte_arr: [u8; 32] = hex::decode(addr).ok()?.try_into().ok()?;
+        return Some(AccountId32::from(byte_arr));
+    }
+
+    AccountId32::from_ss58check(value).ok()
+}
+
+fn get_number(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str()?.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subscan_call_decoder::{
+        decode_nested_calls, get_multisig_account, get_multisig_accounts,
+    };
+    use serde_json::json;
+    use sp_core::crypto::AccountId32;
+
+    fn get_account(byte: u8) -> AccountId32 {
+        AccountId32::from([byte; 32])
+    }
+
+    fn get_hex(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 32]))
+    }
+
+    #[test]
+    fn decode_nested_calls_works() {
+        let batch = json!({
+            "call_module": "Utility",
+            "call_name": "batch_all",
+            "params": [{"name": "calls", "value": [
+                {"call_module": "Staking", "call_name": "bond", "params": [
+                    {"name": "value", "value": "5000000000000000"},
+                ]},
+                {"call_module": "Staking", "call_name": "nominate", "params": [
+                    {"name": "targets", "value": [{"Id": get_hex(3)}]},
+                ]},
+            ]}],
+        });
+        let proxy = json!({
+            "call_module": "Proxy",
+            "call_name": "proxy",
+            "params": [
+                {"name": "real", "value": {"Id": get_hex(2)}},
+                {"name": "force_proxy_type", "value": null},
+                {"name": "call", "value": batch},
+            ],
+        });
+        let multisig = json!({
+            "call_module": "Multisig",
+            "call_name": "as_multi",
+            "params": [
+                {"name": "threshold", "value": 2},
+                {"name": "other_signatories", "value": [get_hex(4)]},
+                {"name": "call", "value": proxy},
+            ],
+        });
+
+        // the proxy attributes the batch to the real account, whoever signed it
+        let multisig_account = get_multisig_account(vec![get_account(4), get_account(1)], 2);
+        let executed_multisigs = vec![multisig_account.clone()];
+        let calls = decode_nested_calls(&multisig, get_account(1), &executed_multisigs);
+        assert_eq!(calls.len(), 2);
+        assert!(calls.iter().all(|p| p.origin == get_account(2)));
+        assert_eq!(calls[0].call_name, "bond");
+        assert_eq!(calls[1].call_module, "staking");
+        assert_eq!(calls[1].get_param("targets").unwrap()[0]["Id"], get_hex(3));
+
+        // without the proxy the calls are made by the multisig itself
+        let multisig = json!({
+            "call_module": "Multisig",
+            "call_name": "as_multi",
+            "params": [
+                {"name": "threshold", "value": 2},
+                {"name": "other_signatories", "value": [get_hex(4)]},
+                {"name": "call", "value": batch},
+            ],
+        });
+        assert_eq!(
+            get_multisig_accounts(&multisig, get_account(1)),
+            executed_multisigs
+        );
+        let calls = decode_nested_calls(&multisig, get_account(1), &executed_multisigs);
+        assert_eq!(calls.len(), 2);
+        assert!(calls.iter().all(|p| p.origin == multisig_account));
+
+        // an approval that didn't execute the multisig dispatches nothing
+        assert!(decode_nested_calls(&multisig, get_account(1), &[]).is_empty());
+    }
+
+    #[test]
+    fn multisig_account_works() {
+        let account = get_multisig_account(vec![get_account(1), get_account(2)], 2);
+        assert_eq!(
+            account,
+            get_multisig_account(vec![get_account(2), get_account(1)], 2)
+        );
+        assert_ne!(
+            account,
+            get_multisig_account(vec![get_account(1), get_account(2)], 1)
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{
+    mongodb_client_cursor::MongoDbClientCursor,
+    subscan_parser::{Network, SubscanParser},
+    Identity, StakingReward, SubscanCursor, SubscanEventRecord, SubscanOperation, SubscanStream,
+};
+use bson::DateTime;
+use futures::future::BoxFuture;
+use log::{error, info};
+use std::{cmp, future::Future, time::Duration};
+use tokio::time::sleep;
+
+pub static CURSOR_PAGE_SIZE: u32 = 100;
+static CURSOR_BLOCK_WINDOW: u64 = 1_000;
+static CURSOR_MAX_WINDOWS_PER_ITERATION: u32 = 10;
+static CURSOR_MAX_PAGES_PER_WINDOW: u32 = 50;
+static CURSOR_INITIAL_LOOKBACK_BLOCKS: u64 = 1_000;
+// subscan indexes the newest blocks with a small delay
+static CURSOR_CONFIRMATION_BLOCKS: u64 = 3;
+
+/// Items paged by a cursor, ordered by their subscan "{block_number}-{position}" index.
+pub trait CursorItem {
+    fn get_cursor_index(&self) -> &str;
+}
+
+impl CursorItem for SubscanOperation {
+    fn get_cursor_index(&self) -> &str {
+        &self.extrinsic_index
+    }
+}
+
+impl CursorItem for StakingReward {
+    fn get_cursor_index(&self) -> &str {
+        &self.event_index
+    }
+}
+
+impl CursorItem for SubscanEventRecord {
+    fn get_cursor_index(&self) -> &str {
+        &self.event.event_index
+    }
+}
+
+/// A stream followed block by block: what was parsed since the stored cursor up to the
+/// head is imported, only then the cursor is moved.
+pub trait CursorWorker {
+    type Item;
+
+    fn get_target(&self) -> &'static str;
+
+    /// Plural name of the items for the logs, e.g. `token operations`.
+    fn get_items_name(&self) -> &'static str;
+
+    fn get_head_block_number(&mut self) -> BoxFuture<'_, Option<u64>>;
+
+    fn parse(
+        &mut self,
+        head_block_number: u64,
+    ) -> BoxFuture<'_, Option<(Vec<Self::Item>, SubscanCursor)>>;
+
+    fn import(&mut self, items: Vec<Self::Item>) -> BoxFuture<'_, ()>;
+}
+
+/// Runs the worker forever, sleeping only when it caught up with the head.
+pub async fn run_cursor_worker<W: CursorWorker>(mut worker: W) {
+    loop {
+        let Some(head_block_number) = worker.get_head_block_number().await else {
+            error!(target: worker.get_target(), "Unable to get head block");
+            sleep(Duration::from_millis(1_000)).await;
+            continue;
+        };
+
+        let Some((items, cursor)) = worker.parse(head_block_number).await else {
+            sleep(Duration::from_millis(1_000)).await;
+            continue;
+        };
+
+        let items_len = items.len();
+        worker.import(items).await;
+
+        // cursor is moved only after the items are safely stored
+        let lag = cursor.get_lag();
+        let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+        mongodb_client_cursor
+            .import_or_update_cursors(vec![cursor])
+            .await;
+
+        info!(
+            target: worker.get_target(), "Imported {} {}, lag {} blocks",
+            items_len,
+            worker.get_items_name(),
+            lag,
+        );
+
+        if lag == 0 {
+            sleep(Duration::from_millis(1_000)).await;
+        }
+    }
+}
+
+pub async fn get_head_block_number() -> Option<u64> {
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    subscan_parser.get_head_block_number().await
+}
+
+pub async fn get_cursor_or_default(stream_name: &str, default_block_number: u64) -> SubscanCursor {
+    let mut mongodb_client_cursor = MongoDbClientCursor::new().await;
+    mongodb_client_cursor
+        .get_cursor_by_stream(stream_name)
+        .await
+        .unwrap_or(SubscanCursor {
+            stream: stream_name.to_string(),
+            block_number: default_block_number,
+            extrinsic_index: String::new(),
+            head_block_number: default_block_number,
+            updated_timestamp: DateTime::now(),
+        })
+}
+
+/// Pages forward from the persisted cursor of the given stream until it catches up
+/// with the head block. Returns new operations together with the moved cursor, which
+/// must be saved only after the operations are imported.
+pub async fn parse_stream_since_cursor(
+    stream: SubscanStream,
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, Vec<Identity>, SubscanCursor)> {
+    parse_pages_since_cursor(
+        &stream.to_string(),
+        head_block_number,
+        |page, block_range| parse_stream_page(stream.clone(), page, block_range),
+    )
+    .await
+}
+
+/// Same as `parse_stream_since_cursor` for streams that are not a `SubscanStream`.
+pub async fn parse_pages_since_cursor<T, F, Fut>(
+    stream_name: &str,
+    head_block_number: u64,
+    parse_page: F,
+) -> Option<(Vec<T>, Vec<Identity>, SubscanCursor)>
+where
+    T: CursorItem,
+    F: Fn(u32, (u64, u64)) -> Fut,
+    Fut: Future<Output = Option<(Vec<T>, Vec<Identity>, usize)>>,
+{
+    let safe_head_block_number = head_block_number.saturating_sub(CURSOR_CONFIRMATION_BLOCKS);
+    let mut cursor = get_cursor_or_default(
+        stream_name,
+        safe_head_block_number.saturating_sub(CURSOR_INITIAL_LOOKBACK_BLOCKS),
+    )
+    .await;
+    cursor.head_block_number = head_block_number;
+
+    parse_pages_until_block(cursor, safe_head_block_number, Duration::ZERO, parse_page).await
+}
+
+/// Walks block windows after the cursor up to `to_block_number` (inclusive), at most
+/// `CURSOR_MAX_WINDOWS_PER_ITERATION` windows per call. `request_delay` is slept before
+/// every page request to stay within api key quotas.
+pub async fn parse_stream_until_block(
+    stream: SubscanStream,
+    cursor: SubscanCursor,
+    to_block_number: u64,
+    request_delay: Duration,
+) -> Option<(Vec<SubscanOperation>, Vec<Identity>, SubscanCursor)> {
+    parse_pages_until_block(
+        cursor,
+        to_block_number,
+        request_delay,
+        |page, block_range| parse_stream_page(stream.clone(), page, block_range),
+    )
+    .await
+}
+
+/// Window and page walk behind the stream parsers, for streams of any item.
+/// `parse_page` fetches one page of `CURSOR_PAGE_SIZE` rows within a block range and
+/// returns the items parsed from it along with the number of rows the page had, since
+/// rows dropped while parsing still count towards a full page.
+pub async fn parse_pages_until_block<T, F, Fut>(
+    mut cursor: SubscanCursor,
+    to_block_number: u64,
+    request_delay: Duration,
+    parse_page: F,
+) -> Option<(Vec<T>, Vec<Identity>, SubscanCursor)>
+where
+    T: CursorItem,
+    F: Fn(u32, (u64, u64)) -> Fut,
+    Fut: Future<Output = Option<(Vec<T>, Vec<Identity>, usize)>>,
+{
+    let stream_name = cursor.stream.clone();
+    cursor.updated_timestamp = DateTime::now();
+
+    let mut subscan_operations = Vec::new();
+    let mut identities = Vec::new();
+    let mut block_window = CURSOR_BLOCK_WINDOW;
+    let mut windows_counter = 0;
+    while windows_counter < CURSOR_MAX_WINDOWS_PER_ITERATION
+        && cursor.block_number < to_block_number
+    {
+        let from_block = cursor.block_number + 1;
+        let to_block = cmp::min(cursor.block_number + block_window, to_block_number);
+
+        let mut window_operations = Vec::new();
+        let mut window_identities = Vec::new();
+        let mut is_window_complete = false;
+        let mut is_fetch_failed = false;
+        for page in 0..CURSOR_MAX_PAGES_PER_WINDOW {
+            sleep(request_delay).await;
+
+            let Some((mut s, mut d, rows_len)) = parse_page(page, (from_block, to_block)).await
+            else {
+                is_fetch_failed = true;
+                break;
+            };
+
+            window_operations.append(&mut s);
+            window_identities.append(&mut d);
+            if rows_len < CURSOR_PAGE_SIZE as usize {
+                is_window_complete = true;
+                break;
+            }
+        }
+
+        // keeping the cursor where it is, next iteration will retry the same window
+        if is_fetch_failed {
+            error!(target: "subscan_parser", "Stream {stream_name}: failed to fetch blocks {from_block}-{to_block}.");
+            break;
+        }
+
+        // too many rows in the window, retrying with a smaller one
+        if !is_window_complete && block_window > 1 {
+            block_window = cmp::max(1, block_window / 2);
+            continue;
+        }
+
+        let mut window_operations = window_operations
+            .into_iter()
+            .filter(|p| is_after_cursor(p.get_cursor_index(), &cursor))
+            .collect::<Vec<_>>();
+
+        if let Some(newest) = window_operations
+            .iter()
+            .max_by_key(|p| parse_extrinsic_index(p.get_cursor_index()))
+        {
+            cursor.extrinsic_index = newest.get_cursor_index().to_string();
+        }
+        cursor.block_number = to_block;
+
+        subscan_operations.append(&mut window_operations);
+        identities.append(&mut window_identities);
+        block_window = CURSOR_BLOCK_WINDOW;
+        windows_counter += 1;
+    }
+
+    info!(
+        target: "subscan_parser", "Stream {stream_name} is at block {} of {} (lag {} blocks), found {} items",
+        cursor.block_number,
+        cursor.head_block_number,
+        cursor.get_lag(),
+        subscan_operations.len(),
+    );
+
+    Some((subscan_operations, identities, cursor))
+}
+
+pub async fn parse_stream_page(
+    stream: SubscanStream,
+    page: u32,
+    block_range: (u64, u64),
+) -> Option<(Vec<SubscanOperation>, Vec<Identity>, usize)> {
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    match stream {
+        SubscanStream::Transfers => {
+            subscan_parser
+                .parse_subscan_transfers(page, CURSOR_PAGE_SIZE, Some(block_range))
+                .await
+        }
+        SubscanStream::Extrinsics(module, extrinsics_type) if module.is_wrapper() => {
+            let (s, rows_len) = subscan_parser
+                .parse_subscan_wrapped_calls(
+                    "",
+                    module,
+                    extrinsics_type,
+                    page,
+                    CURSOR_PAGE_SIZE,
+                    Some(block_range),
+                )
+                .await?;
+            Some((s, Vec::new(), rows_len))
+        }
+        SubscanStream::Extrinsics(module, extrinsics_type) => {
+            let (s, rows_len) = subscan_parser
+                .parse_subscan_operations(
+                    "",
+                    module,
+                    extrinsics_type,
+                    page,
+                    CURSOR_PAGE_SIZE,
+                    Some(block_range),
+                )
+                .await?;
+            Some((s, Vec::new(), rows_len))
+        }
+    }
+}
+
+/// Splits subscan's "{block_number}-{extrinsic_position}" index into comparable parts.
+pub fn parse_extrinsic_index(extrinsic_index: &str) -> Option<(u64, u64)> {
+    let (block_number, position) = extrinsic_index.split_once('-')?;
+    Some((block_number.parse().ok()?, position.parse().ok()?))
+}
+
+fn is_after_cursor(extrinsic_index: &str, cursor: &SubscanCursor) -> bool {
+    let Some(position) = parse_extrinsic_index(extrinsic_index) else {
+        return true;
+    };
+
+    // cursor block is always fully ingested
+    position.0 > cursor.block_number
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_cursor::{
+            is_after_cursor, parse_extrinsic_index, parse_pages_until_block, CursorItem,
+            CURSOR_PAGE_SIZE,
+        },
+        SubscanCursor,
+    };
+    use bson::DateTime;
+    use std::time::Duration;
+
+    struct TestItem(String);
+
+    impl CursorItem for TestItem {
+        fn get_cursor_index(&self) -> &str {
+            &self.0
+        }
+    }
+
+    #[test]
+    fn parse_extrinsic_index_works() {
+        assert_eq!(parse_extrinsic_index("58713052-2"), Some((58713052, 2)));
+        assert_eq!(parse_extrinsic_index("58713052"), None);
+        assert_eq!(parse_extrinsic_index(""), None);
+    }
+
+    #[test]
+    fn is_after_cursor_works() {
+        let cursor = SubscanCursor {
+            stream: "transfers".to_string(),
+            block_number: 100,
+            extrinsic_index: "100-3".to_string(),
+            head_block_number: 150,
+            updated_timestamp: DateTime::now(),
+        };
+
+        assert!(!is_after_cursor("99-7", &cursor));
+        assert!(!is_after_cursor("100-4", &cursor));
+        assert!(is_after_cursor("101-0", &cursor));
+        assert_eq!(cursor.get_lag(), 50);
+    }
+
+    #[tokio::test]
+    async fn parse_pages_until_block_counts_filtered_rows() {
+        let cursor = SubscanCursor {
+            stream: "utility_batch_all".to_string(),
+            block_number: 100,
+            extrinsic_index: String::new(),
+            head_block_number: 150,
+            updated_timestamp: DateTime::now(),
+        };
+
+        // the first page is full but one of its rows is dropped while parsing
+        let (items, _, cursor) =
+            parse_pages_until_block(cursor, 150, Duration::ZERO, |page, _| async move {
+                let (items_len, rows_len) = match page {
+                    0 => (CURSOR_PAGE_SIZE - 1, CURSOR_PAGE_SIZE),
+                    _ => (5, 5),
+                };
+                let items = (0..items_len)
+                    .map(|p| TestItem(format!("{}-{p}", 101 + page)))
+                    .collect::<Vec<_>>();
+                Some((items, Vec::new(), rows_len as usize))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(items.len(), CURSOR_PAGE_SIZE as usize + 4);
+        assert_eq!(cursor.block_number, 150);
+        assert_eq!(cursor.extrinsic_index, "102-4");
+    }
+}
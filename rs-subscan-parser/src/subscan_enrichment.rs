@@ -0,0 +1,397 @@
+use crate::{
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_governance_parser::DecodeVestingEventStage,
+    subscan_parser::{Network, SubscanParser},
+    subscan_price::set_usd_prices,
+    subscan_stake_parser::{
+        DecodeStakeEventStage, ResolveControllersStage, ResolveNominatorsStage,
+        ResolveValidatorStage, SaveNominationsStage,
+    },
+    subscan_transfer_parser::ClassifyTransfersStage,
+    SubscanOperation, MINIMUM_AZERO_TO_SAVE_TO_DB,
+};
+use futures::{
+    future::{self, BoxFuture},
+    stream::FuturesUnordered,
+    FutureExt, StreamExt,
+};
+use itertools::Itertools;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{env, str::FromStr, time::Instant};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+
+/// One step of turning freshly parsed operations into stored ones. Returning `None`
+/// aborts the pipeline, the operations are then parsed again on the next run.
+pub trait EnrichmentStage: Send + Sync {
+    fn get_name(&self) -> &'static str;
+
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>>;
+}
+
+/// Stages that can be listed in a pipeline config such as `STAKING_ENRICHMENT_STAGES`.
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum EnrichmentStageKind {
+    SkipExisting,
+    DecodeStakeEvent,
+    DecodeVestingEvent,
+    SaveNominations,
+    FilterDust,
+    ValueInUsd,
+    ResolveNominators,
+    ResolveValidator,
+    ResolveControllers,
+    SetHash,
+    ClassifyTransfers,
+    ResolveIdentities,
+}
+
+impl EnrichmentStageKind {
+    pub fn get_stage(&self) -> Box<dyn EnrichmentStage> {
+        match self {
+            EnrichmentStageKind::SkipExisting => Box::new(SkipExistingStage),
+            EnrichmentStageKind::DecodeStakeEvent => Box::new(DecodeStakeEventStage),
+            EnrichmentStageKind::DecodeVestingEvent => Box::new(DecodeVestingEventStage),
+            EnrichmentStageKind::SaveNominations => Box::new(SaveNominationsStage),
+            EnrichmentStageKind::FilterDust => Box::new(FilterDustStage),
+            EnrichmentStageKind::ValueInUsd => Box::new(ValueInUsdStage),
+            EnrichmentStageKind::ResolveNominators => Box::new(ResolveNominatorsStage),
+            EnrichmentStageKind::ResolveValidator => Box::new(ResolveValidatorStage),
+            EnrichmentStageKind::ResolveControllers => Box::new(ResolveControllersStage),
+            EnrichmentStageKind::SetHash => Box::new(SetHashStage),
+            EnrichmentStageKind::ClassifyTransfers => Box::new(ClassifyTransfersStage),
+            EnrichmentStageKind::ResolveIdentities => Box::new(ResolveIdentitiesStage),
+        }
+    }
+
+    /// Comma separated stage names, `None` when the list is empty, any of the names is
+    /// unknown or `set_hash` is missing, operations without a hash can not be stored.
+    pub fn parse_list(value: &str) -> Option<Vec<EnrichmentStageKind>> {
+        let kinds = value
+            .split(',')
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                EnrichmentStageKind::from_str(p)
+                    .map_err(|_| error!(target: "enrichment", "Unknown enrichment stage {p}"))
+                    .ok()
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if kinds.is_empty() {
+            return None;
+        }
+        if !kinds.contains(&EnrichmentStageKind::SetHash) {
+            error!(target: "enrichment", "Enrichment stages {value} do not set the hash");
+            return None;
+        }
+
+        Some(kinds)
+    }
+}
+
+pub struct EnrichmentPipeline {
+    stages: Vec<Box<dyn EnrichmentStage>>,
+}
+
+impl EnrichmentPipeline {
+    pub fn new(stages: Vec<Box<dyn EnrichmentStage>>) -> EnrichmentPipeline {
+        Self { stages }
+    }
+
+    pub fn from_kinds(kinds: &[EnrichmentStageKind]) -> EnrichmentPipeline {
+        Self::new(kinds.iter().map(|p| p.get_stage()).collect())
+    }
+
+    /// Stages listed in the env variable, the default ones when it is not set, empty or
+    /// invalid.
+    pub fn from_env(name: &str, default: &[EnrichmentStageKind]) -> EnrichmentPipeline {
+        let kinds = env::var(name)
+            .ok()
+            .and_then(|p| EnrichmentStageKind::parse_list(&p))
+            .unwrap_or(default.to_vec());
+
+        Self::from_kinds(&kinds)
+    }
+
+    /// Runs the stages in order, logging how many operations each one kept and how
+    /// long it took.
+    pub async fn run(
+        &self,
+        mut subscan_operations: Vec<SubscanOperation>,
+    ) -> Option<Vec<SubscanOperation>> {
+        for stage in self.stages.iter() {
+            if subscan_operations.is_empty() {
+                break;
+            }
+
+            let operations_len = subscan_operations.len();
+            let started = Instant::now();
+            subscan_operations = stage.enrich(subscan_operations).await?;
+            info!(
+                target: "enrichment",
+                "{}: {} -> {} operations in {} ms",
+                stage.get_name(),
+                operations_len,
+                subscan_operations.len(),
+                started.elapsed().as_millis(),
+            );
+        }
+
+        Some(subscan_operations)
+    }
+}
+
+/// Drops operations whose extrinsic is stored already.
+pub struct SkipExistingStage;
+
+impl EnrichmentStage for SkipExistingStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::SkipExisting.into()
+    }
+
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+            Some(
+                mongodb_client_subscan
+                    .get_not_existing_operations(subscan_operations)
+                    .await,
+            )
+        }
+        .boxed()
+    }
+}
+
+/// Drops operations with less than MINIMUM_AZERO_TO_SAVE_TO_DB AZERO.
+pub struct FilterDustStage;
+
+impl EnrichmentStage for FilterDustStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::FilterDust.into()
+    }
+
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        let subscan_operations = subscan_operations
+            .into_iter()
+            .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
+            .collect();
+
+        future::ready(Some(subscan_operations)).boxed()
+    }
+}
+
+/// Values operations in USD at the time they happened.
+pub struct ValueInUsdStage;
+
+impl EnrichmentStage for ValueInUsdStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::ValueInUsd.into()
+    }
+
+    fn enrich(
+        &self,
+        mut subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            set_usd_prices(&mut subscan_operations).await?;
+            Some(subscan_operations)
+        }
+        .boxed()
+    }
+}
+
+/// Hashes the operations as they are at this point, later stages do not change the hash.
+pub struct SetHashStage;
+
+impl EnrichmentStage for SetHashStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::SetHash.into()
+    }
+
+    fn enrich(
+        &self,
+        mut subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        for s in subscan_operations.iter_mut() {
+            s.set_hash();
+        }
+
+        future::ready(Some(subscan_operations)).boxed()
+    }
+}
+
+/// Parses and saves identities of the addresses not seen before.
+pub struct ResolveIdentitiesStage;
+
+impl EnrichmentStage for ResolveIdentitiesStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::ResolveIdentities.into()
+    }
+
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let new_addresses = subscan_operations
+                .iter()
+                .flat_map(|m| [m.from_wallet.clone(), m.to_wallet.clone()])
+                .unique()
+                .collect::<Vec<_>>();
+
+            // skipping already existing records
+            let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
+            let new_addresses = mongodb_client_identity
+                .get_not_existing_addresses(new_addresses)
+                .await;
+
+            // parsing non existing identities
+            let mut tasks = FuturesUnordered::new();
+            for a in new_addresses {
+                tasks.push(tokio::spawn(async move {
+                    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+                    subscan_parser.parse_subscan_identity(&a).await
+                }));
+            }
+
+            let mut identities = Vec::new();
+            while let Some(res) = tasks.next().await {
+                let Ok(Some(mut s)) = res else {
+                    continue;
+                };
+                identities.append(&mut s);
+            }
+
+            // saving newly parsed identities
+            mongodb_client_identity
+                .import_or_update_identities(identities)
+                .await;
+
+            Some(subscan_operations)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_enrichment::{
+            EnrichmentPipeline, EnrichmentStage, EnrichmentStageKind, FilterDustStage, SetHashStage,
+        },
+        OperationType, SubscanOperation,
+    };
+    use futures::future::{self, BoxFuture, FutureExt};
+
+    struct DoubleQuantityStage;
+
+    impl EnrichmentStage for DoubleQuantityStage {
+        fn get_name(&self) -> &'static str {
+            "double_quantity"
+        }
+
+        fn enrich(
+            &self,
+            mut subscan_operations: Vec<SubscanOperation>,
+        ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+            for s in subscan_operations.iter_mut() {
+                s.operation_quantity *= 2.0;
+            }
+            future::ready(Some(subscan_operations)).boxed()
+        }
+    }
+
+    fn get_operation(operation_quantity: f64) -> SubscanOperation {
+        SubscanOperation {
+            operation_quantity,
+            operation_type: OperationType::Stake,
+            from_wallet: "5Stash".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn enrichment_pipeline_works() {
+        // a custom stage runs between the built-in ones in the given order
+        let pipeline = EnrichmentPipeline::new(vec![
+            Box::new(DoubleQuantityStage),
+            Box::new(FilterDustStage),
+            Box::new(SetHashStage),
+        ]);
+        let subscan_operations = pipeline
+            .run(vec![get_operation(200.0), get_operation(600.0)])
+            .await
+            .unwrap();
+        assert_eq!(subscan_operations.len(), 1);
+        assert_eq!(subscan_operations[0].operation_quantity, 1_200.0);
+        assert!(!subscan_operations[0].hash.is_empty());
+    }
+
+    #[tokio::test]
+    async fn filter_dust_stage_works() {
+        let subscan_operations = FilterDustStage
+            .enrich(vec![get_operation(499.0), get_operation(500.0)])
+            .await
+            .unwrap();
+        assert_eq!(subscan_operations.len(), 1);
+        assert_eq!(subscan_operations[0].operation_quantity, 500.0);
+    }
+
+    #[tokio::test]
+    async fn set_hash_stage_works() {
+        let subscan_operations = SetHashStage
+            .enrich(vec![get_operation(500.0), get_operation(600.0)])
+            .await
+            .unwrap();
+        assert!(!subscan_operations[0].hash.is_empty());
+        assert_ne!(subscan_operations[0].hash, subscan_operations[1].hash);
+
+        // hashing again gives the same hash, so reparsed operations are not duplicated
+        let mut subscan_operation = subscan_operations[0].clone();
+        subscan_operation.set_hash();
+        assert_eq!(subscan_operation.hash, subscan_operations[0].hash);
+    }
+
+    #[test]
+    fn parse_list_works() {
+        assert_eq!(
+            EnrichmentStageKind::parse_list("skip_existing, set_hash,"),
+            Some(vec![
+                EnrichmentStageKind::SkipExisting,
+                EnrichmentStageKind::SetHash
+            ])
+        );
+        assert_eq!(
+            EnrichmentStageKind::parse_list("skip_existing,unknown"),
+            None
+        );
+        assert_eq!(EnrichmentStageKind::parse_list("skip_existing"), None);
+        assert_eq!(EnrichmentStageKind::parse_list(""), None);
+        assert_eq!(EnrichmentStageKind::parse_list(" , "), None);
+    }
+}
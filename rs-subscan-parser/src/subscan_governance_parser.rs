@@ -0,0 +1,398 @@
+use crate::{
+    subscan_call_decoder::get_account_id,
+    subscan_cursor::{parse_pages_since_cursor, parse_stream_since_cursor, CURSOR_PAGE_SIZE},
+    subscan_enrichment::{EnrichmentPipeline, EnrichmentStage, EnrichmentStageKind},
+    subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR, EMPTY_ADDRESS},
+    ExtrinsicsType, OperationType, SubscanCursor, SubscanEvent, SubscanEventRecord,
+    SubscanOperation, SubscanStream,
+};
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use serde_json::{json, Value};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+
+pub static TREASURY_AWARDED_CURSOR_STREAM: &str = "treasury_awarded";
+static TREASURY_MODULE_ID: &str = "treasury";
+static AWARDED_EVENT_ID: &str = "Awarded";
+static BALANCES_MODULE_ID: &str = "balances";
+static UNLOCKED_EVENT_ID: &str = "Unlocked";
+// the treasury account is "modl" followed by its pallet id, padded with zeros
+static TREASURY_PALLET_ID: &[u8; 8] = b"py/trsry";
+static VOTE_BALANCE_PARAMS: [&str; 4] = ["balance", "aye", "nay", "abstain"];
+
+static GOVERNANCE_ENRICHMENT_STAGES: [EnrichmentStageKind; 6] = [
+    EnrichmentStageKind::SkipExisting,
+    EnrichmentStageKind::DecodeVestingEvent,
+    EnrichmentStageKind::FilterDust,
+    EnrichmentStageKind::ValueInUsd,
+    EnrichmentStageKind::SetHash,
+    EnrichmentStageKind::ResolveIdentities,
+];
+
+pub async fn parse_governance(
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, Vec<SubscanCursor>)> {
+    let treasury_spends_task =
+        tokio::spawn(async move { parse_treasury_spends(head_block_number).await });
+
+    let mut tasks = FuturesUnordered::new();
+    for stream in SubscanStream::get_governance_streams() {
+        tasks.push(tokio::spawn(async move {
+            parse_stream_since_cursor(stream, head_block_number).await
+        }));
+    }
+
+    let mut governance_operations = Vec::new();
+    let mut cursors = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some((mut s, _, cursor))) = res else {
+            continue;
+        };
+
+        governance_operations.append(&mut s);
+        cursors.push(cursor);
+    }
+
+    if let Ok(Some((mut s, cursor))) = treasury_spends_task.await {
+        governance_operations.append(&mut s);
+        cursors.push(cursor);
+    }
+
+    let subscan_operations = enrich_governance_operations(governance_operations).await?;
+
+    Some((subscan_operations, cursors))
+}
+
+/// Resolves unlocked amounts, USD values and identities of freshly parsed governance
+/// operations. The stages can be changed with `GOVERNANCE_ENRICHMENT_STAGES`.
+pub async fn enrich_governance_operations(
+    governance_operations: Vec<SubscanOperation>,
+) -> Option<Vec<SubscanOperation>> {
+    EnrichmentPipeline::from_env(
+        "GOVERNANCE_ENRICHMENT_STAGES",
+        &GOVERNANCE_ENRICHMENT_STAGES,
+    )
+    .run(governance_operations)
+    .await
+}
+
+async fn parse_treasury_spends(
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, SubscanCursor)> {
+    let (s, _, cursor) = parse_pages_since_cursor(
+        TREASURY_AWARDED_CURSOR_STREAM,
+        head_block_number,
+        |page, block_range| async move {
+            let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+            let (s, rows_len) = subscan_parser
+                .parse_subscan_event_records(
+                    TREASURY_MODULE_ID,
+                    AWARDED_EVENT_ID,
+                    page,
+                    CURSOR_PAGE_SIZE,
+                    Some(block_range),
+                )
+                .await?;
+            Some((s, Vec::new(), rows_len))
+        },
+    )
+    .await?;
+
+    let ss58_prefix = Network::from_env().get_ss58_prefix();
+    let treasury_spends = s
+        .into_iter()
+        .filter_map(|p| convert_treasury_award(p, ss58_prefix))
+        .collect();
+
+    Some((treasury_spends, cursor))
+}
+
+/// Amount and receiver of a treasury, democracy or vesting call. Amounts unlocked by
+/// `vest` and `vest_other` are only known from their events and are left at zero.
+pub fn decode_governance_call(
+    extrinsics_type: &ExtrinsicsType,
+    params: &Value,
+    ss58_prefix: u16,
+) -> Option<(f64, String)> {
+    let get_param = |name: &str| {
+        params
+            .as_array()?
+            .iter()
+            .find(|p| p.get("name").and_then(|p| p.as_str()) == Some(name))?
+            .get("value")
+    };
+    let get_address = |name: &str| {
+        Some(
+            get_account_id(get_param(name)?)?
+                .to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)),
+        )
+    };
+
+    match extrinsics_type {
+        ExtrinsicsType::ProposeSpend => Some((
+            get_amount(get_param("value")?)?,
+            get_address("beneficiary")?,
+        )),
+        // a standard vote has a balance, split votes divide it into aye, nay and abstain
+        ExtrinsicsType::Vote => {
+            let vote = get_param("vote")?.as_object()?.values().next()?;
+            let balance = VOTE_BALANCE_PARAMS
+                .iter()
+                .filter_map(|p| get_amount(vote.get(*p)?))
+                .sum();
+            Some((balance, EMPTY_ADDRESS.to_string()))
+        }
+        ExtrinsicsType::Vest => Some((0.0, EMPTY_ADDRESS.to_string())),
+        ExtrinsicsType::VestOther => Some((0.0, get_address("target")?)),
+        ExtrinsicsType::VestedTransfer => Some((
+            get_amount(get_param("schedule")?.get("locked")?)?,
+            get_address("target")?,
+        )),
+        _ => None,
+    }
+}
+
+/// Awards are paid at the start of a spend period outside of any extrinsic, the event
+/// index stands in for the extrinsic index.
+fn convert_treasury_award(
+    subscan_event_record: SubscanEventRecord,
+    ss58_prefix: u16,
+) -> Option<SubscanOperation> {
+    let ss58_format = Ss58AddressFormat::custom(ss58_prefix);
+    let award = subscan_event_record.get_param("award", 1)?;
+    let account = subscan_event_record.get_param("account", 2)?;
+
+    Some(SubscanOperation {
+        hash: String::new(),
+        block_number: subscan_event_record.block_number,
+        extrinsic_index: subscan_event_record.event.event_index.clone(),
+        operation_timestamp: subscan_event_record.event_timestamp,
+        operation_quantity: award.parse::<f64>().ok()? / AZERO_DENOMINATOR,
+        operation_usd: 0.0,
+        operation_type: OperationType::TreasurySpend,
+        from_wallet: get_treasury_account().to_ss58check_with_version(ss58_format),
+        controller_wallet: EMPTY_ADDRESS.to_string(),
+        to_wallet: get_account_id(&json!(account))?.to_ss58check_with_version(ss58_format),
+        pool_id: None,
+        pool_name: None,
+        targets: Vec::new(),
+        bridge: None,
+        network: Network::from_env(),
+    })
+}
+
+fn get_treasury_account() -> AccountId32 {
+    let mut bytes = [0u8; 32];
+    bytes[..4].copy_from_slice(b"modl");
+    bytes[4..12].copy_from_slice(TREASURY_PALLET_ID);
+    AccountId32::from(bytes)
+}
+
+/// Balances come as strings, small ones sometimes as numbers.
+fn get_amount(value: &Value) -> Option<f64> {
+    let amount = match value {
+        Value::String(s) => s.parse::<f64>().ok()?,
+        v => v.as_f64()?,
+    };
+
+    Some(amount / AZERO_DENOMINATOR)
+}
+
+/// Sets the amount of vesting unlocks from the `Balances.Unlocked` event of the vested
+/// account. Unlocks that freed nothing, such as ones still covered by a larger staking
+/// lock, have no such event and are dropped. Other operations pass through.
+pub struct DecodeVestingEventStage;
+
+impl EnrichmentStage for DecodeVestingEventStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::DecodeVestingEvent.into()
+    }
+
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let mut tasks = FuturesUnordered::new();
+            for s in subscan_operations {
+                tasks.push(tokio::spawn(async move {
+                    if s.operation_type != OperationType::VestingUnlock {
+                        return Some(Some(s));
+                    }
+                    decode_vesting_event(s).await
+                }));
+            }
+
+            // an extrinsic that could not be fetched fails the batch, it is parsed again
+            let mut subscan_operations = Vec::new();
+            while let Some(res) = tasks.next().await {
+                let Ok(Some(s)) = res else {
+                    return None;
+                };
+                subscan_operations.extend(s);
+            }
+
+            Some(subscan_operations)
+        }
+        .boxed()
+    }
+}
+
+/// `None` when the extrinsic could not be fetched, `Some(None)` when it unlocked nothing.
+async fn decode_vesting_event(s: SubscanOperation) -> Option<Option<SubscanOperation>> {
+    let mut subscan_parser = SubscanParser::new(s.network.clone()).await;
+    let events = subscan_parser
+        .parse_subscan_extrinsic_details(s.extrinsic_index.clone())
+        .await?;
+
+    Some(convert_vesting_unlock(s, &events))
+}
+
+/// Takes the amount from the `Balances.Unlocked` event of the vested account.
+pub fn convert_vesting_unlock(
+    s: SubscanOperation,
+    events: &[SubscanEvent],
+) -> Option<SubscanOperation> {
+    // `vest_other` unlocks the target, `vest` the signer
+    let vested_wallet = if s.to_wallet == EMPTY_ADDRESS {
+        &s.from_wallet
+    } else {
+        &s.to_wallet
+    };
+    let vested_account = get_account_id(&json!(vested_wallet))?;
+
+    let unlocked_event = events.iter().find(|p| {
+        p.module_id == BALANCES_MODULE_ID
+            && p.event_id == UNLOCKED_EVENT_ID
+            && p.event_params
+                .iter()
+                .find(|e| e.name == "who")
+                .and_then(|e| get_account_id(&json!(e.value)))
+                == Some(vested_account.clone())
+    })?;
+    let amount = unlocked_event
+        .event_params
+        .iter()
+        .find(|p| p.name == "amount")?;
+
+    Some(SubscanOperation {
+        operation_quantity: amount.value.parse::<f64>().ok()? / AZERO_DENOMINATOR,
+        ..s
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_governance_parser::{
+            convert_treasury_award, convert_vesting_unlock, decode_governance_call,
+        },
+        subscan_parser::EMPTY_ADDRESS,
+        ExtrinsicsType, OperationType, SubscanEvent, SubscanEventParam, SubscanEventRecord,
+        SubscanOperation,
+    };
+    use bson::DateTime;
+    use serde_json::json;
+    use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+
+    fn get_address(byte: u8) -> String {
+        AccountId32::from([byte; 32]).to_ss58check_with_version(Ss58AddressFormat::custom(42))
+    }
+
+    fn get_hex(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 32]))
+    }
+
+    #[test]
+    fn decode_governance_call_works() {
+        let propose_spend = json!([
+            {"name": "value", "type": "compact<U128>", "value": "2000000000000000"},
+            {"name": "beneficiary", "type": "sp_runtime:multiaddress:MultiAddress", "value": {"Id": get_hex(1)}},
+        ]);
+        assert_eq!(
+            decode_governance_call(&ExtrinsicsType::ProposeSpend, &propose_spend, 42),
+            Some((2_000.0, get_address(1)))
+        );
+
+        let split_vote = json!([
+            {"name": "ref_index", "type": "compact<U32>", "value": 12},
+            {"name": "vote", "type": "AccountVote", "value": {"Split": {"aye": "1500000000000000", "nay": "500000000000000"}}},
+        ]);
+        assert_eq!(
+            decode_governance_call(&ExtrinsicsType::Vote, &split_vote, 42),
+            Some((2_000.0, EMPTY_ADDRESS.to_string()))
+        );
+
+        let vested_transfer = json!([
+            {"name": "target", "type": "MultiAddress", "value": {"Id": get_hex(2)}},
+            {"name": "schedule", "type": "VestingInfo", "value": {"locked": "750000000000000", "per_block": "1000000", "starting_block": 100}},
+        ]);
+        assert_eq!(
+            decode_governance_call(&ExtrinsicsType::VestedTransfer, &vested_transfer, 42),
+            Some((750.0, get_address(2)))
+        );
+
+        let award = SubscanEventRecord {
+            block_number: 100,
+            extrinsic_index: "100-0".to_string(),
+            event_timestamp: DateTime::from_millis(0),
+            event: SubscanEvent {
+                module_id: "treasury".to_string(),
+                event_id: "Awarded".to_string(),
+                event_index: "100-4".to_string(),
+                event_params: [
+                    ("proposal_index", "7".to_string()),
+                    ("award", "30000000000000000".to_string()),
+                    ("account", get_hex(3)),
+                ]
+                .into_iter()
+                .map(|(name, value)| SubscanEventParam {
+                    type_name: String::new(),
+                    value,
+                    name: name.to_string(),
+                })
+                .collect(),
+            },
+        };
+        let treasury_spend = convert_treasury_award(award, 42).unwrap();
+        assert_eq!(treasury_spend.operation_type, OperationType::TreasurySpend);
+        assert_eq!(treasury_spend.operation_quantity, 30_000.0);
+        assert_eq!(treasury_spend.to_wallet, get_address(3));
+        assert_eq!(treasury_spend.extrinsic_index, "100-4");
+        assert_eq!(
+            treasury_spend.from_wallet,
+            "5EYCAe5ijiYfyeZ2JJCGq56LmPyNRAKzpG4QkoQkkQNB5e6Z"
+        );
+    }
+
+    #[test]
+    fn convert_vesting_unlock_works() {
+        let unlocked = SubscanEvent {
+            module_id: "balances".to_string(),
+            event_id: "Unlocked".to_string(),
+            event_index: "100-2".to_string(),
+            event_params: [
+                ("who", get_hex(4)),
+                ("amount", "1250000000000000".to_string()),
+            ]
+            .into_iter()
+            .map(|(name, value)| SubscanEventParam {
+                type_name: String::new(),
+                value,
+                name: name.to_string(),
+            })
+            .collect(),
+        };
+        let vest = SubscanOperation {
+            operation_type: OperationType::VestingUnlock,
+            from_wallet: get_address(4),
+            to_wallet: EMPTY_ADDRESS.to_string(),
+            ..Default::default()
+        };
+
+        let vesting_unlock = convert_vesting_unlock(vest.clone(), &[unlocked]).unwrap();
+        assert_eq!(vesting_unlock.operation_quantity, 1_250.0);
+
+        // nothing was vested yet
+        assert_eq!(convert_vesting_unlock(vest, &[]), None);
+    }
+}
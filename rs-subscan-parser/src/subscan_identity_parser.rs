@@ -0,0 +1,141 @@
+use crate::{
+    mongodb_client_identities::MongoDbClientIdentity,
+    subscan_parser::{Network, SubscanParser},
+    Identity,
+};
+use bson::DateTime;
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde_json::Value;
+
+pub static IDENTITY_REFRESH_INTERVAL_SECS: u64 = 600;
+static IDENTITY_TTL_SECS: i64 = 60 * 60 * 24 * 7;
+static IDENTITY_REFRESH_BATCH: i64 = 100;
+static IDENTITY_RETRY_DELAY_SECS: i64 = 60 * 60;
+
+/// Re-checks the identities not updated within the TTL. Cleared identities are removed.
+/// Returns the number of identities checked.
+pub async fn refresh_stale_identities() -> Option<usize> {
+    let updated_before =
+        DateTime::from_millis(DateTime::now().timestamp_millis() - IDENTITY_TTL_SECS * 1_000);
+    let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
+    let stale_identities = mongodb_client_identity
+        .get_stale_identities(updated_before, IDENTITY_REFRESH_BATCH)
+        .await;
+
+    let mut tasks = FuturesUnordered::new();
+    for s in stale_identities {
+        tasks.push(tokio::spawn(async move {
+            let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+            let identities = subscan_parser.parse_subscan_identity(&s.address).await;
+            (s.address, identities)
+        }));
+    }
+
+    // a failed lookup is retried later, not on every refresh ahead of the rest of the batch
+    let retry_timestamp = DateTime::from_millis(
+        updated_before.timestamp_millis() + IDENTITY_RETRY_DELAY_SECS * 1_000,
+    );
+    let mut checked_len = 0;
+    let mut identities = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok((address, s)) = res else {
+            continue;
+        };
+        let Some(mut s) = s else {
+            mongodb_client_identity
+                .set_identity_updated_timestamp(&address, retry_timestamp)
+                .await;
+            continue;
+        };
+
+        checked_len += 1;
+        if s.is_empty() {
+            mongodb_client_identity.delete_identity(&address).await;
+            continue;
+        }
+        identities.append(&mut s);
+    }
+
+    mongodb_client_identity
+        .import_or_update_identities(identities)
+        .await;
+
+    Some(checked_len)
+}
+
+/// Identity of a subscan `account_display`. A sub-identity is shown as `Parent/child`
+/// and takes the judgement of its parent, whose identity is the one judged.
+pub fn convert_account_display(address: &str, account_display: &Value) -> Option<Identity> {
+    let get_str = |value: &Value, name: &str| {
+        value
+            .get(name)
+            .and_then(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string())
+    };
+
+    let display = get_str(account_display, "display").filter(|p| p != address);
+    let parent = account_display.get("parent").filter(|p| !p.is_null());
+    let identity = match parent {
+        Some(parent) => format!(
+            "{}/{}",
+            get_str(parent, "display")?,
+            get_str(parent, "sub_symbol").or(display)?
+        ),
+        None => display?,
+    };
+
+    let judgement = parent
+        .unwrap_or(account_display)
+        .get("judgements")
+        .and_then(|p| p.as_array())
+        .and_then(|p| p.iter().find_map(|j| get_str(j, "judgement")));
+
+    Some(Identity {
+        address: address.to_string(),
+        identity,
+        parent: parent.and_then(|p| get_str(p, "address")),
+        judgement,
+        updated_timestamp: Some(DateTime::now()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subscan_identity_parser::convert_account_display;
+    use serde_json::json;
+
+    #[test]
+    fn convert_account_display_works() {
+        let account_display = json!({
+            "address": "5Main",
+            "display": "Exchange",
+            "identity": true,
+            "judgements": [{"index": 0, "judgement": "KnownGood"}],
+        });
+        let identity = convert_account_display("5Main", &account_display).unwrap();
+        assert_eq!(identity.identity, "Exchange");
+        assert_eq!(identity.judgement.as_deref(), Some("KnownGood"));
+        assert_eq!(identity.parent, None);
+
+        let account_display = json!({
+            "address": "5Sub",
+            "display": "",
+            "parent": {
+                "address": "5Main",
+                "display": "Exchange",
+                "sub_symbol": "Hot Wallet 2",
+                "identity": true,
+                "judgements": [{"index": 0, "judgement": "Reasonable"}],
+            },
+        });
+        let identity = convert_account_display("5Sub", &account_display).unwrap();
+        assert_eq!(identity.identity, "Exchange/Hot Wallet 2");
+        assert_eq!(identity.parent.as_deref(), Some("5Main"));
+        assert_eq!(identity.judgement.as_deref(), Some("Reasonable"));
+
+        // cleared identities fall back to the plain address
+        let account_display = json!({"address": "5Plain", "display": "5Plain", "parent": null});
+        assert_eq!(convert_account_display("5Plain", &account_display), None);
+    }
+}
@@ -1,21 +1,39 @@
 use crate::{
-    ExtrinsicsType, Identity, Module, OperationType, SubscanEvent, SubscanEventParam,
-    SubscanOperation,
+    balance_tracker::convert_account_balance,
+    subscan_api_key_pool::{get_api_key_pool, SubscanApiKeyOutcome},
+    subscan_call_decoder::{
+        decode_nested_calls, get_account_id, get_executed_multisigs, get_multisig_accounts,
+        SubscanCall,
+    },
+    subscan_governance_parser::decode_governance_call,
+    subscan_identity_parser::convert_account_display,
+    subscan_validator_parser::convert_validator_info,
+    Balance, ExtrinsicsType, Identity, Module, OperationType, StakingReward, SubscanEvent,
+    SubscanEventParam, SubscanEventRecord, SubscanOperation, ValidatorInfo, ValidatorStatus,
 };
 use bson::DateTime;
 use log::error;
-use rand::seq::IteratorRandom;
 use reqwest::header::{HeaderMap, HeaderValue};
 use rs_utils::clients::http_client::HttpClient;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
-use std::{env, time::Duration};
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
 use tokio::time::sleep;
 
 pub static EMPTY_ADDRESS: &str = "0x0";
 pub static AZERO_DENOMINATOR: f64 = 1e12;
+static MAX_REQUEST_FAILURES: u32 = 5;
+static MAX_REQUEST_ATTEMPTS: u32 = 20;
+static REQUEST_TIME_BUDGET_MS: u64 = 5 * 60 * 1_000;
+static STAKING_MODULE_ID: &str = "staking";
+static REWARDED_EVENT_ID: &str = "Rewarded";
 
 #[derive(
     Clone,
@@ -37,19 +55,55 @@ pub static AZERO_DENOMINATOR: f64 = 1e12;
 pub enum Network {
     #[default]
     Alephzero,
+    AlephzeroTestnet,
+}
+
+impl Network {
+    /// Network selected with `NETWORK`, mainnet when not set.
+    pub fn from_env() -> Network {
+        env::var("NETWORK")
+            .ok()
+            .and_then(|p| Network::from_str(&p).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get_subscan_name(&self) -> &'static str {
+        match self {
+            Network::Alephzero => "alephzero",
+            Network::AlephzeroTestnet => "alephzero-testnet",
+        }
+    }
+
+    pub fn get_ss58_prefix(&self) -> u16 {
+        match self {
+            Network::Alephzero => 42,
+            Network::AlephzeroTestnet => 42,
+        }
+    }
+
+    pub fn get_explorer_url(&self) -> String {
+        format!("https://{}.subscan.io", self.get_subscan_name())
+    }
+
+    pub fn get_token_symbol(&self) -> &'static str {
+        match self {
+            Network::Alephzero => "AZERO",
+            Network::AlephzeroTestnet => "TZERO",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct SubscanParser {
     http_client: HttpClient,
-    network: String,
+    network: Network,
 }
 
 impl SubscanParser {
     pub async fn new(network: Network) -> Self {
         let http_client = HttpClient::new("subscan_parser").await;
         SubscanParser {
-            network: network.to_string(),
+            network,
             http_client,
         }
     }
@@ -58,45 +112,21 @@ impl SubscanParser {
         &mut self,
         event_indexes: Vec<String>,
     ) -> Option<Vec<SubscanEvent>> {
-        let mut resp;
-
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/event/params",
-                self.network
-            );
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
-
-            let payload = json!({"event_index": event_indexes});
-
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
-
-            break;
-        }
+        let payload = json!({"event_index": event_indexes});
+        let resp = self
+            .post_subscan_request("api/scan/event/params", payload)
+            .await?;
 
         let data = resp.get("data")?.as_array()?;
         let subscan_events = data
             .iter()
             .filter_map(|d| -> Option<_> {
                 let module_id = d.get("module_id")?.as_str()?.to_string();
+                let event_id = d
+                    .get("event_id")
+                    .and_then(|p| p.as_str())
+                    .unwrap_or_default()
+                    .to_string();
                 let event_index = d.get("event_index")?.as_str()?.to_string();
                 let event_params = d
                     .get("params")?
@@ -104,7 +134,7 @@ impl SubscanParser {
                     .iter()
                     .filter_map(|p| {
                         let type_name = p.get("type_name")?.as_str()?.to_string();
-                        let value = p.get("value")?.as_str()?.to_string();
+                        let value = SubscanParser::get_param_value(p.get("value")?);
                         let name = p.get("name")?.as_str()?.to_string();
 
                         Some(SubscanEventParam {
@@ -117,6 +147,7 @@ impl SubscanParser {
 
                 Some(SubscanEvent {
                     module_id,
+                    event_id,
                     event_index,
                     event_params,
                 })
@@ -125,43 +156,111 @@ impl SubscanParser {
         Some(subscan_events)
     }
 
-    pub async fn parse_subscan_extrinsic_details(
+    /// Lists events of one kind within the block range together with their params.
+    pub async fn parse_subscan_event_records(
         &mut self,
-        extrinsic_index: String,
-    ) -> Option<Vec<SubscanEvent>> {
-        let mut resp;
-
-        loop {
-            let url = format!("https://{}.api.subscan.io/api/scan/extrinsic", self.network);
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
+        module_id: &str,
+        event_id: &str,
+        page: u32,
+        num_items: u32,
+        block_range: Option<(u64, u64)>,
+    ) -> Option<(Vec<SubscanEventRecord>, usize)> {
+        let mut payload = json!(
+            {"row": num_items, "page": page, "module": module_id, "event_id": event_id}
+        );
+        SubscanParser::set_block_range(&mut payload, block_range);
+        let resp = self
+            .post_subscan_request("api/v2/scan/events", payload)
+            .await?;
+
+        let Some(data) = resp.get("data")?.get("events")?.as_array() else {
+            return Some((Vec::new(), 0));
+        };
+        let event_indexes = data
+            .iter()
+            .filter_map(|d| Some(d.get("event_index")?.as_str()?.to_string()))
+            .collect::<Vec<_>>();
+        let subscan_events = self.parse_subscan_events(event_indexes).await?;
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+        let subscan_event_records = data
+            .iter()
+            .filter_map(|d| {
+                let event_index = d.get("event_index")?.as_str()?;
+                let event = subscan_events
+                    .iter()
+                    .find(|p| p.event_index == event_index)?;
+
+                Some(SubscanEventRecord {
+                    block_number: d.get("block_num")?.as_u64()?,
+                    extrinsic_index: d.get("extrinsic_index")?.as_str()?.to_string(),
+                    event_timestamp: DateTime::from_millis(
+                        d.get("block_timestamp")?.as_i64()? * 1_000,
+                    ),
+                    event: event.clone(),
+                })
+            })
+            .rev()
+            .collect();
 
-            let payload = json!({
-                "extrinsic_index": extrinsic_index,
-                "only_extrinsic_event" : true
-            });
+        Some((subscan_event_records, data.len()))
+    }
 
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
+    /// Lists `staking.Rewarded` events and reads their stash and amount. Era and validator
+    /// come from the `PayoutStarted` event of the same payout, resolved during enrichment.
+    pub async fn parse_subscan_reward_events(
+        &mut self,
+        page: u32,
+        num_items: u32,
+        block_range: Option<(u64, u64)>,
+    ) -> Option<(Vec<StakingReward>, usize)> {
+        let (subscan_event_records, rows_len) = self
+            .parse_subscan_event_records(
+                STAKING_MODULE_ID,
+                REWARDED_EVENT_ID,
+                page,
+                num_items,
+                block_range,
+            )
+            .await?;
+
+        let ss58_format = Ss58AddressFormat::custom(self.network.get_ss58_prefix());
+        let staking_rewards = subscan_event_records
+            .into_iter()
+            .filter_map(|d| {
+                let stash = d.get_param("stash", 0)?;
+                let stash = get_account_id(&json!(stash))?.to_ss58check_with_version(ss58_format);
+                let amount = d.get_param("amount", d.event.event_params.len().checked_sub(1)?)?;
+
+                Some(StakingReward {
+                    era: 0,
+                    block_number: d.block_number,
+                    event_index: d.event.event_index,
+                    extrinsic_index: d.extrinsic_index,
+                    reward_timestamp: d.event_timestamp,
+                    reward_quantity: amount.parse::<f64>().ok()? / AZERO_DENOMINATOR,
+                    reward_usd: 0.0,
+                    stash,
+                    validator: EMPTY_ADDRESS.to_string(),
+                    is_validator: false,
+                    network: self.network.clone(),
+                })
+            })
+            .collect();
 
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
+        Some((staking_rewards, rows_len))
+    }
 
-            break;
-        }
+    pub async fn parse_subscan_extrinsic_details(
+        &mut self,
+        extrinsic_index: String,
+    ) -> Option<Vec<SubscanEvent>> {
+        let payload = json!({
+            "extrinsic_index": extrinsic_index,
+            "only_extrinsic_event" : true
+        });
+        let resp = self
+            .post_subscan_request("api/scan/extrinsic", payload)
+            .await?;
 
         let data = resp.get("data")?.get("event")?.as_array()?;
 
@@ -169,6 +268,11 @@ impl SubscanParser {
             .iter()
             .filter_map(|d| -> Option<_> {
                 let module_id = d.get("module_id")?.as_str()?.to_string();
+                let event_id = d
+                    .get("event_id")
+                    .and_then(|p| p.as_str())
+                    .unwrap_or_default()
+                    .to_string();
                 let event_index = d.get("event_index")?.as_str()?.to_string();
                 let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
                 let event_params = params
@@ -176,7 +280,7 @@ impl SubscanParser {
                     .iter()
                     .filter_map(|p| {
                         let type_name = p.get("type_name")?.as_str()?.to_string();
-                        let value = p.get("value")?.as_str()?.to_string();
+                        let value = SubscanParser::get_param_value(p.get("value")?);
                         let name = p.get("name")?.as_str()?.to_string();
 
                         Some(SubscanEventParam {
@@ -189,6 +293,7 @@ impl SubscanParser {
 
                 Some(SubscanEvent {
                     module_id,
+                    event_id,
                     event_index,
                     event_params,
                 })
@@ -202,44 +307,22 @@ impl SubscanParser {
         address: &str,
         module: Module,
         extrinsics_type: ExtrinsicsType,
+        page: u32,
         num_items: u32,
-    ) -> Option<Vec<SubscanOperation>> {
-        let mut resp;
-
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/extrinsics",
-                self.network
-            );
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
-
-            let payload = json!(
-                {"address": address, "row": num_items, "page": 0, "module": module, "call": extrinsics_type.to_string(), "success": true}
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
-
-            break;
-        }
-
-        let data = resp.get("data")?.get("extrinsics")?.as_array()?;
+        block_range: Option<(u64, u64)>,
+    ) -> Option<(Vec<SubscanOperation>, usize)> {
+        let mut payload = json!(
+            {"address": address, "row": num_items, "page": page, "module": module.to_string(), "call": extrinsics_type.to_string(), "success": true}
+        );
+        SubscanParser::set_block_range(&mut payload, block_range);
+        let resp = self
+            .post_subscan_request("api/scan/extrinsics", payload)
+            .await?;
+
+        // subscan sends null instead of an empty list when there are no rows
+        let Some(data) = resp.get("data")?.get("extrinsics")?.as_array() else {
+            return Some((Vec::new(), 0));
+        };
         let subscan_operations = data
             .iter()
             .filter_map(|d| {
@@ -253,34 +336,31 @@ impl SubscanParser {
                 let block_number = d.get("block_num")?.as_u64()?;
                 let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
 
-                let operation_type = match extrinsics_type {
-                    ExtrinsicsType::Bond | ExtrinsicsType::BondExtra | ExtrinsicsType::Rebond => {
-                        OperationType::Stake
-                    }
-                    ExtrinsicsType::Nominate => OperationType::ReStake,
-                    ExtrinsicsType::Unbond => OperationType::RequestUnstake,
-                    ExtrinsicsType::WithdrawUnbonded => OperationType::WithdrawUnstaked,
-                };
+                let operation_type = OperationType::from_extrinsics_type(&module, &extrinsics_type);
 
-                let to_wallet = if extrinsics_type == ExtrinsicsType::Nominate {
+                let targets = if extrinsics_type == ExtrinsicsType::Nominate {
                     let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
 
-                    let addr = params
-                        .as_array()?
-                        .first()?
-                        .get("value")?
-                        .as_array()?
-                        .first()?
-                        .get("Id")?
-                        .as_str()?;
+                    let targets = params.as_array()?.first()?.get("value")?;
+                    SubscanParser::get_targets(targets, self.network.get_ss58_prefix())?
+                } else {
+                    Vec::new()
+                };
+                let to_wallet = targets
+                    .first()
+                    .cloned()
+                    .unwrap_or(EMPTY_ADDRESS.to_string());
 
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
+                // governance calls carry their amount, staking amounts come from events
+                let (operation_quantity, to_wallet) = if module.is_governance() {
+                    let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
+                    decode_governance_call(
+                        &extrinsics_type,
+                        &params,
+                        self.network.get_ss58_prefix(),
+                    )?
                 } else {
-                    EMPTY_ADDRESS.to_string()
+                    (0.321, to_wallet)
                 };
 
                 let controller_wallet = if extrinsics_type == ExtrinsicsType::Bond {
@@ -297,8 +377,9 @@ impl SubscanParser {
                     let addr = addr[2..].to_string();
                     let decoded = hex::decode(addr).ok()?;
                     let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
+                    AccountId32::from(byte_arr).to_ss58check_with_version(
+                        Ss58AddressFormat::custom(self.network.get_ss58_prefix()),
+                    )
                 } else {
                     EMPTY_ADDRESS.to_string()
                 };
@@ -307,20 +388,25 @@ impl SubscanParser {
                     hash: String::new(),
                     block_number,
                     operation_timestamp,
-                    operation_quantity: 0.321,
+                    operation_quantity,
                     operation_usd: 0.123,
                     operation_type,
                     from_wallet,
                     to_wallet,
                     controller_wallet,
                     extrinsic_index,
+                    pool_id: None,
+                    pool_name: None,
+                    targets,
+                    bridge: None,
+                    network: self.network.clone(),
                 };
 
                 Some(subscan_operation)
             })
             .rev()
             .collect();
-        Some(subscan_operations)
+        Some((subscan_operations, data.len()))
     }
 
     pub async fn parse_subscan_batch_all(
@@ -328,164 +414,133 @@ impl SubscanParser {
         address: &str,
         page: u32,
         num_items: u32,
+        block_range: Option<(u64, u64)>,
     ) -> Option<Vec<SubscanOperation>> {
-        let mut resp;
-
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/extrinsics",
-                self.network
-            );
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+        let (subscan_operations, _) = self
+            .parse_subscan_wrapped_calls(
+                address,
+                Module::Utility,
+                ExtrinsicsType::BatchAll,
+                page,
+                num_items,
+                block_range,
+            )
+            .await?;
+        Some(subscan_operations)
+    }
 
-            let payload = json!(
-                {"address": address, "row": num_items, "page": page, "module": "utility", "call": "batch_all", "success": true}
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
+    /// Parses batch, proxy and multisig extrinsics that dispatch staking calls. The stash
+    /// is the account the staking calls are made from, not the signer.
+    pub async fn parse_subscan_wrapped_calls(
+        &mut self,
+        address: &str,
+        module: Module,
+        extrinsics_type: ExtrinsicsType,
+        page: u32,
+        num_items: u32,
+        block_range: Option<(u64, u64)>,
+    ) -> Option<(Vec<SubscanOperation>, usize)> {
+        let mut payload = json!(
+            {"address": address, "row": num_items, "page": page, "module": module.to_string(), "call": extrinsics_type.to_string(), "success": true}
+        );
+        SubscanParser::set_block_range(&mut payload, block_range);
+        let resp = self
+            .post_subscan_request("api/scan/extrinsics", payload)
+            .await?;
+
+        let ss58_format = Ss58AddressFormat::custom(self.network.get_ss58_prefix());
+        let Some(data) = resp.get("data")?.get("extrinsics")?.as_array() else {
+            return Some((Vec::new(), 0));
+        };
+        let get_call = |d: &Value| -> Option<(String, AccountId32, Value)> {
+            if !d.get("success")?.as_bool()? {
+                return None;
+            };
+
+            let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
+            let signer = get_account_id(d.get("account_id")?)?;
+            let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
+            let call = json!({
+                "call_module": module.to_string(),
+                "call_name": extrinsics_type.to_string(),
+                "params": params,
+            });
+            Some((extrinsic_index, signer, call))
+        };
 
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
+        // approvals of a multisig succeed too, only its events tell which one executed it
+        let mut executed_multisigs = HashMap::new();
+        for (extrinsic_index, signer, call) in data.iter().filter_map(get_call) {
+            if get_multisig_accounts(&call, signer).is_empty() {
                 continue;
             }
 
-            break;
+            let events = self
+                .parse_subscan_extrinsic_details(extrinsic_index.clone())
+                .await?;
+            executed_multisigs.insert(extrinsic_index, get_executed_multisigs(&events));
         }
 
-        let data = resp.get("data")?.get("extrinsics")?.as_array()?;
         let subscan_operations = data
             .iter()
             .filter_map(|d| {
-                if !d.get("success")?.as_bool()? {
-                    return None;
-                };
-
+                let (extrinsic_index, signer, call) = get_call(d)?;
                 let operation_timestamp =
                     DateTime::from_millis(d.get("block_timestamp")?.as_i64()? * 1_000);
-                let from_wallet = d.get("account_id")?.as_str()?.to_string();
                 let block_number = d.get("block_num")?.as_u64()?;
-                let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
 
-                let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
-                let value = params.as_array()?.first()?.get("value")?.as_array()?;
-                let bond_extra = value
-                    .iter()
-                    .find(|p| p.get("call_name").unwrap() == "bond_extra");
-                let bond = value.iter().find(|p| p.get("call_name").unwrap() == "bond");
-                let unbond = value
-                    .iter()
-                    .find(|p| p.get("call_name").unwrap() == "unbond");
-                let nominate = value
-                    .iter()
-                    .find(|p| p.get("call_name").unwrap() == "nominate");
-
-                let bond_amount = if bond.is_some() {
-                    str::parse::<f64>(
-                        bond.unwrap()
-                            .get("params")?
-                            .as_array()?
-                            .iter()
-                            .find(|p| p.get("name").unwrap() == "value")?
-                            .get("value")?
-                            .as_str()?,
-                    )
-                    .ok()?
-                        / AZERO_DENOMINATOR
-                } else {
-                    0.0
-                };
-
-                let bond_extra_amount = if bond_extra.is_some() {
-                    str::parse::<f64>(
-                        bond_extra
-                            .unwrap()
-                            .get("params")?
-                            .as_array()?
-                            .iter()
-                            .find(|p| p.get("name").unwrap() == "max_additional")?
-                            .get("value")?
-                            .as_str()?,
-                    )
-                    .ok()?
-                        / AZERO_DENOMINATOR
-                } else {
-                    0.0
-                };
-
-                let unbond_amount = if unbond.is_some() {
-                    str::parse::<f64>(
-                        unbond
-                            .unwrap()
-                            .get("params")?
-                            .as_array()?
-                            .iter()
-                            .find(|p| p.get("name").unwrap() == "value")?
-                            .get("value")?
-                            .as_str()?,
-                    )
-                    .ok()?
-                        / AZERO_DENOMINATOR
-                } else {
-                    0.0
+                let executed_multisigs = executed_multisigs
+                    .get(&extrinsic_index)
+                    .map(|p| p.as_slice())
+                    .unwrap_or_default();
+                let calls = decode_nested_calls(&call, signer, executed_multisigs)
+                    .into_iter()
+                    .filter(|p| p.call_module == STAKING_MODULE_ID)
+                    .collect::<Vec<_>>();
+                let from_wallet = calls.first()?.origin.to_ss58check_with_version(ss58_format);
+
+                let find_call = |name: &str| calls.iter().find(|p| p.call_name == name);
+                let get_amount = |call: Option<&SubscanCall>, name: &str| match call {
+                    Some(call) => Some(
+                        str::parse::<f64>(call.get_param(name)?.as_str()?).ok()?
+                            / AZERO_DENOMINATOR,
+                    ),
+                    None => Some(0.0),
                 };
+                let bond = find_call("bond");
+                let unbond = find_call("unbond");
+                let nominate = find_call("nominate");
 
-                let operation_quantity = bond_amount + bond_extra_amount + unbond_amount;
+                let bond_amount = get_amount(bond, "value")?;
+                let bond_extra_amount = get_amount(find_call("bond_extra"), "max_additional")?;
+                let unbond_amount = get_amount(unbond, "value")?;
+                let rebond_amount = get_amount(find_call("rebond"), "value")?;
 
-                let to_wallet = if nominate.is_some() {
-                    let addr = nominate
-                        .unwrap()
-                        .get("params")?
-                        .as_array()?
-                        .first()?
-                        .get("value")?
-                        .as_array()?
-                        .first()?
-                        .get("Id")?
-                        .as_str()?;
+                let operation_quantity =
+                    bond_amount + bond_extra_amount + unbond_amount + rebond_amount;
 
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
+                let targets = if let Some(nominate) = nominate {
+                    let targets = nominate.get_param("targets")?;
+                    SubscanParser::get_targets(targets, self.network.get_ss58_prefix())?
                 } else {
-                    EMPTY_ADDRESS.to_string()
+                    Vec::new()
                 };
-
-                let controller_wallet = if bond.is_some() {
-                    let params = bond.unwrap().get("params")?;
-
-                    let addr = params
-                        .as_array()?
-                        .iter()
-                        .find(|p| p.get("name").unwrap().as_str().unwrap() == "controller")?
-                        .get("value")?
-                        .get("Id")?
-                        .as_str()?;
-
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
+                let to_wallet = targets
+                    .first()
+                    .cloned()
+                    .unwrap_or(EMPTY_ADDRESS.to_string());
+
+                let controller_wallet = if let Some(bond) = bond {
+                    let controller = bond.get_param("controller")?;
+                    get_account_id(controller)?.to_ss58check_with_version(ss58_format)
                 } else {
                     EMPTY_ADDRESS.to_string()
                 };
 
                 let operation_type = if unbond_amount > 1e-12 {
                     OperationType::RequestUnstake
+                } else if rebond_amount > 1e-12 {
+                    OperationType::Rebond
                 } else if to_wallet != EMPTY_ADDRESS {
                     OperationType::ReStake
                 } else {
@@ -503,6 +558,11 @@ impl SubscanParser {
                     to_wallet,
                     controller_wallet,
                     extrinsic_index,
+                    pool_id: None,
+                    pool_name: None,
+                    targets,
+                    bridge: None,
+                    network: self.network.clone(),
                 };
 
                 Some(subscan_operation)
@@ -510,128 +570,131 @@ impl SubscanParser {
             .rev()
             .collect();
 
-        Some(subscan_operations)
+        Some((subscan_operations, data.len()))
     }
 
-    pub async fn parse_subscan_identity(
-        &mut self,
-        address: &str,
-        page: u32,
-        num_items: u32,
-    ) -> Option<Vec<Identity>> {
-        if SubscanParser::is_address_empty(address) {
-            return None;
-        }
+    /// Total stake backing the validator, `None` if the stash is not a validator.
+    pub async fn parse_subscan_validator_total_stake(&mut self, stash: &str) -> Option<f64> {
+        let payload = json!({ "stash": stash });
+        let resp = self
+            .post_subscan_request("api/scan/staking/validator", payload)
+            .await?;
 
-        let mut resp;
+        let bonded_total = resp.get("data")?.get("info")?.get("bonded_total")?;
+        let bonded_total = SubscanParser::get_param_value(bonded_total);
+        Some(bonded_total.parse::<f64>().ok()? / AZERO_DENOMINATOR)
+    }
 
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/extrinsics",
-                self.network
-            );
+    /// Active or waiting validators, both lists are returned in full.
+    pub async fn parse_subscan_validators(
+        &mut self,
+        status: ValidatorStatus,
+        era: u32,
+    ) -> Option<Vec<ValidatorInfo>> {
+        let path = match status {
+            ValidatorStatus::Active => "api/scan/staking/validators",
+            ValidatorStatus::Waiting => "api/scan/staking/waiting",
+            // subscan doesn't list them, they are whatever is missing from the other two
+            ValidatorStatus::Inactive => return Some(Vec::new()),
+        };
+        let payload = json!({ "order": "desc", "order_field": "bonded_total" });
+        let resp = self.post_subscan_request(path, payload).await?;
+
+        let data = resp.get("data")?.get("list")?.as_array()?;
+        let validator_infos = data
+            .iter()
+            .filter_map(|d| convert_validator_info(d, &status, era, &self.network))
+            .collect::<Vec<_>>();
 
-            let subscan_api_key = SubscanParser::get_random_api_key();
+        Some(validator_infos)
+    }
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+    pub async fn parse_subscan_pool_name(&mut self, pool_id: u32) -> Option<String> {
+        let payload = json!({ "pool_id": pool_id });
+        let resp = self
+            .post_subscan_request("api/scan/nomination_pool/pool", payload)
+            .await?;
 
-            let payload = json!(
-                {"address": address, "row": num_items, "page": page, "module": "identity", "call": "set_identity", "success": true}
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
+        let pool_name = resp.get("data")?.get("metadata")?.as_str()?.to_string();
+        Some(pool_name)
+    }
 
-            break;
+    /// Current identity of the address, resolved through its parent for sub-identities.
+    /// Empty when the address has none, e.g. after the identity was cleared.
+    pub async fn parse_subscan_identity(&mut self, address: &str) -> Option<Vec<Identity>> {
+        if SubscanParser::is_address_empty(address) {
+            return None;
         }
 
-        let data = resp.get("data")?.get("extrinsics")?.as_array()?;
-        let identities = data
-            .iter()
-            .filter_map(|d| {
-                if !d.get("success")?.as_bool()? {
-                    return None;
-                };
-
-                let address = d
-                    .get("account_display")?
-                    .get("address")?
-                    .as_str()?
-                    .to_string();
-                let identity = d
-                    .get("account_display")?
-                    .get("display")?
-                    .as_str()?
-                    .to_string();
-                let status = d.get("account_display")?.get("identity")?.as_bool()?;
-                if !status {
-                    return None;
-                }
+        let payload = json!({ "key": address });
+        let resp = self
+            .post_subscan_request("api/v2/scan/search", payload)
+            .await?;
 
-                Some(Identity { address, identity })
-            })
-            .rev()
-            .collect::<Vec<_>>();
+        let account_display = resp.get("data")?.get("account")?.get("account_display")?;
+        let identities = convert_account_display(address, account_display)
+            .into_iter()
+            .collect();
 
         Some(identities)
     }
 
-    pub async fn parse_subscan_transfers(
+    /// Balance of the account, `total_issuance` in AZERO gives its share of the supply.
+    pub async fn parse_subscan_balance(
         &mut self,
-        page: u32,
-        num_items: u32,
-    ) -> Option<(Vec<SubscanOperation>, Vec<Identity>)> {
-        let mut resp;
-
-        loop {
-            let url = format!("https://{}.api.subscan.io/api/scan/transfers", self.network);
+        address: &str,
+        total_issuance: f64,
+    ) -> Option<Balance> {
+        if SubscanParser::is_address_empty(address) {
+            return None;
+        }
 
-            let subscan_api_key = SubscanParser::get_random_api_key();
+        let payload = json!({ "key": address });
+        let resp = self
+            .post_subscan_request("api/v2/scan/search", payload)
+            .await?;
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+        let account = resp.get("data")?.get("account")?;
+        convert_account_balance(address, account, total_issuance, &self.network)
+    }
 
-            let payload = json!(
-                {
-                    "row": num_items,
-                    "page": page,
-                    "success": true,
-                    "asset_symbol": "AZERO",
-                }
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
+    /// Total issuance of the native token in AZERO.
+    pub async fn parse_subscan_total_issuance(&mut self) -> Option<f64> {
+        let resp = self
+            .post_subscan_request("api/scan/token", json!({}))
+            .await?;
+
+        let total_issuance = resp
+            .get("data")?
+            .get("detail")?
+            .get(self.network.get_token_symbol())?
+            .get("total_issuance")?;
+        let total_issuance = SubscanParser::get_param_value(total_issuance);
+        Some(total_issuance.parse::<f64>().ok()? / AZERO_DENOMINATOR)
+    }
 
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
+    pub async fn parse_subscan_transfers(
+        &mut self,
+        page: u32,
+        num_items: u32,
+        block_range: Option<(u64, u64)>,
+    ) -> Option<(Vec<SubscanOperation>, Vec<Identity>, usize)> {
+        let mut payload = json!(
+            {
+                "row": num_items,
+                "page": page,
+                "success": true,
+                "asset_symbol": self.network.get_token_symbol(),
             }
-
-            break;
-        }
-
-        let data = resp.get("data")?.get("transfers")?.as_array()?;
+        );
+        SubscanParser::set_block_range(&mut payload, block_range);
+        let resp = self
+            .post_subscan_request("api/scan/transfers", payload)
+            .await?;
+
+        let Some(data) = resp.get("data")?.get("transfers")?.as_array() else {
+            return Some((Vec::new(), Vec::new(), 0));
+        };
         let subscan_operations = data
             .iter()
             .filter_map(|d| {
@@ -662,6 +725,11 @@ impl SubscanParser {
                     to_wallet,
                     controller_wallet,
                     extrinsic_index,
+                    pool_id: None,
+                    pool_name: None,
+                    targets: Vec::new(),
+                    bridge: None,
+                    network: self.network.clone(),
                 };
 
                 Some(subscan_operation)
@@ -676,25 +744,12 @@ impl SubscanParser {
                     return None;
                 };
 
-                let from_address = d.get("from")?.as_str()?.to_string();
-                let from_identity = d
-                    .get("from_account_display")?
-                    .get("display")
-                    .and_then(|v| v.as_str())
-                    .map(|v| Identity {
-                        address: from_address,
-                        identity: v.to_string(),
-                    });
-
-                let to_address = d.get("to")?.as_str()?.to_string();
-                let to_identity = d
-                    .get("to_account_display")?
-                    .get("display")
-                    .and_then(|v| v.as_str())
-                    .map(|v| Identity {
-                        address: to_address,
-                        identity: v.to_string(),
-                    });
+                let from_identity = convert_account_display(
+                    d.get("from")?.as_str()?,
+                    d.get("from_account_display")?,
+                );
+                let to_identity =
+                    convert_account_display(d.get("to")?.as_str()?, d.get("to_account_display")?);
 
                 let identities = vec![from_identity, to_identity]
                     .into_iter()
@@ -710,16 +765,127 @@ impl SubscanParser {
             .flatten()
             .collect::<Vec<_>>();
 
-        Some((subscan_operations, identities))
+        Some((subscan_operations, identities, data.len()))
     }
 
-    fn get_random_api_key() -> String {
-        env::var("SUBSCAN_API_KEY")
-            .unwrap()
-            .split(',')
-            .choose(&mut rand::thread_rng())
-            .unwrap()
-            .to_string()
+    pub async fn get_head_block_number(&mut self) -> Option<u64> {
+        let payload = json!({});
+        let resp = self
+            .post_subscan_request("api/scan/metadata", payload)
+            .await?;
+
+        str::parse::<u64>(resp.get("data")?.get("blockNum")?.as_str()?).ok()
+    }
+
+    pub async fn get_block_number_by_timestamp(&mut self, timestamp: i64) -> Option<u64> {
+        let payload = json!({"block_timestamp": timestamp, "only_head": true});
+        let resp = self.post_subscan_request("api/scan/block", payload).await?;
+
+        resp.get("data")?.get("block_num")?.as_u64()
+    }
+
+    fn set_block_range(payload: &mut Value, block_range: Option<(u64, u64)>) {
+        let Some((from_block, to_block)) = block_range else {
+            return;
+        };
+
+        payload["block_range"] = json!(format!("{from_block}-{to_block}"));
+    }
+
+    /// Sends a request with a key from the shared pool, moving to another key when the
+    /// current one is rate limited or revoked. Every attempt, whatever its outcome, counts
+    /// against the same attempt and time budget.
+    async fn post_subscan_request(&mut self, path: &str, payload: Value) -> Option<Value> {
+        let url = format!(
+            "https://{}.api.subscan.io/{path}",
+            self.network.get_subscan_name()
+        );
+
+        let deadline = Instant::now() + Duration::from_millis(REQUEST_TIME_BUDGET_MS);
+        let mut attempts_counter = 0;
+        let mut failures_counter = 0;
+        loop {
+            attempts_counter += 1;
+            if attempts_counter > MAX_REQUEST_ATTEMPTS || Instant::now() >= deadline {
+                error!(target: "subscan_parser", "Giving up on {path} after {} attempts.", attempts_counter - 1);
+                return None;
+            }
+
+            let subscan_api_key = get_api_key_pool().lock().unwrap().acquire(Instant::now());
+            let subscan_api_key = match subscan_api_key {
+                Ok(subscan_api_key) => subscan_api_key,
+                Err(wait) => {
+                    let wait = wait.min(deadline.saturating_duration_since(Instant::now()));
+                    error!(target: "subscan_parser", "All api keys are cooling down. Sleeping {} ms.", wait.as_millis());
+                    sleep(wait).await;
+                    continue;
+                }
+            };
+
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "X-API-Key",
+                HeaderValue::from_str(&subscan_api_key).unwrap(),
+            );
+
+            let resp = self
+                .http_client
+                .post_request::<Value, Value>(&url, headers, payload.clone())
+                .await;
+
+            // a response without a code is a failed request, not a reason to skip the report
+            let code = resp.get("code").and_then(|p| p.as_u64());
+            let outcome = code
+                .map(SubscanApiKeyOutcome::from_code)
+                .unwrap_or(SubscanApiKeyOutcome::Failure);
+            get_api_key_pool().lock().unwrap().report(
+                &subscan_api_key,
+                outcome.clone(),
+                Instant::now(),
+            );
+
+            if outcome == SubscanApiKeyOutcome::Success {
+                return Some(resp);
+            }
+
+            let message = resp
+                .get("message")
+                .and_then(|p| p.as_str())
+                .unwrap_or_default();
+            error!(target: "subscan_parser", "Parse error[{}]: {message}.", code.unwrap_or_default());
+
+            // rate limited and revoked keys are retried right away with another key
+            if outcome == SubscanApiKeyOutcome::Failure {
+                failures_counter += 1;
+                if failures_counter >= MAX_REQUEST_FAILURES {
+                    return None;
+                }
+
+                sleep(Duration::from_millis(1_000)).await;
+            }
+        }
+    }
+
+    /// Nominate targets in the order chosen, `None` if any of them is undecodable.
+    fn get_targets(targets: &Value, ss58_prefix: u16) -> Option<Vec<String>> {
+        targets
+            .as_array()?
+            .iter()
+            .map(|p| {
+                Some(
+                    get_account_id(p)?
+                        .to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)),
+                )
+            })
+            .collect()
+    }
+
+    /// Numbers and flags come as plain json values, everything else as strings.
+    fn get_param_value(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        }
     }
 
     pub fn is_address_empty(addr: &str) -> bool {
@@ -0,0 +1,131 @@
+use crate::{
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_cursor::parse_stream_since_cursor,
+    subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR},
+    subscan_price::set_usd_prices,
+    SubscanCursor, SubscanOperation, SubscanStream, MINIMUM_AZERO_TO_SAVE_TO_DB,
+};
+use futures::{stream::FuturesUnordered, StreamExt};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use std::collections::{hash_map::Entry, HashMap};
+
+static POOL_MODULE_ID: &str = "nominationpools";
+static POOL_AMOUNT_PARAMS: [&str; 3] = ["bonded", "balance", "payout"];
+static POOL_MEMBER_PARAMS: [&str; 2] = ["member", "depositor"];
+
+pub async fn parse_pools(
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, Vec<SubscanCursor>)> {
+    let mut tasks = FuturesUnordered::new();
+    for stream in SubscanStream::get_pool_streams() {
+        tasks.push(tokio::spawn(async move {
+            parse_stream_since_cursor(stream, head_block_number).await
+        }));
+    }
+
+    let mut pool_operations = Vec::new();
+    let mut cursors = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some((mut s, _, cursor))) = res else {
+            continue;
+        };
+
+        pool_operations.append(&mut s);
+        cursors.push(cursor);
+    }
+
+    let subscan_operations = enrich_pool_operations(pool_operations, Vec::new()).await?;
+
+    Some((subscan_operations, cursors))
+}
+
+/// Resolves member, pool id and amount of freshly parsed pool operations from their events,
+/// then values them in USD and names the pools. `resolved_pool_operations` already carry
+/// member, pool id and amount. Shared by live ingestion and backfill.
+pub async fn enrich_pool_operations(
+    pool_operations: Vec<SubscanOperation>,
+    resolved_pool_operations: Vec<SubscanOperation>,
+) -> Option<Vec<SubscanOperation>> {
+    // skipping already existing records
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let pool_operations = mongodb_client_subscan
+        .get_not_existing_operations(pool_operations)
+        .await;
+
+    let mut tasks = FuturesUnordered::new();
+    for s in pool_operations {
+        tasks.push(tokio::spawn(async move {
+            let mut subscan_parser = SubscanParser::new(s.network.clone()).await;
+            let events = subscan_parser
+                .parse_subscan_extrinsic_details(s.extrinsic_index.clone())
+                .await?;
+
+            // pool creation emits Created and Bonded, only the latter has the amount
+            let pool_event = events.iter().find(|p| {
+                p.module_id == POOL_MODULE_ID
+                    && p.event_params
+                        .iter()
+                        .any(|e| POOL_AMOUNT_PARAMS.contains(&e.name.as_str()))
+            })?;
+            let get_param = |names: &[&str]| {
+                pool_event
+                    .event_params
+                    .iter()
+                    .find(|p| names.contains(&p.name.as_str()))
+                    .map(|p| p.value.clone())
+            };
+
+            let member_wallet = get_param(&POOL_MEMBER_PARAMS)?[2..].to_string();
+            let decoded = hex::decode(member_wallet).ok()?;
+            let byte_arr: [u8; 32] = decoded.try_into().ok()?;
+            let from_wallet = AccountId32::from(byte_arr)
+                .to_ss58check_with_version(Ss58AddressFormat::custom(s.network.get_ss58_prefix()));
+
+            Some(SubscanOperation {
+                from_wallet,
+                operation_quantity: get_param(&POOL_AMOUNT_PARAMS)?.parse::<f64>().ok()?
+                    / AZERO_DENOMINATOR,
+                pool_id: Some(get_param(&["pool_id"])?.parse().ok()?),
+                ..s
+            })
+        }));
+    }
+
+    let mut subscan_operations = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some(s)) = res else {
+            continue;
+        };
+        subscan_operations.push(s);
+    }
+
+    // skipping already existing records
+    let mut resolved_pool_operations = mongodb_client_subscan
+        .get_not_existing_operations(resolved_pool_operations)
+        .await;
+    subscan_operations.append(&mut resolved_pool_operations);
+
+    // removing operations with less than MINIMUM_AZERO_TO_SAVE_TO_DB AZERO amount
+    let mut subscan_operations = subscan_operations
+        .into_iter()
+        .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
+        .collect::<Vec<_>>();
+
+    // valuing in USD at the time of operation
+    set_usd_prices(&mut subscan_operations).await?;
+
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    let mut pool_names: HashMap<u32, Option<String>> = HashMap::new();
+    for s in subscan_operations.iter_mut() {
+        if let Some(pool_id) = s.pool_id {
+            if let Entry::Vacant(e) = pool_names.entry(pool_id) {
+                e.insert(subscan_parser.parse_subscan_pool_name(pool_id).await);
+            }
+            s.pool_name = pool_names.get(&pool_id).cloned().flatten();
+        }
+
+        s.set_hash();
+    }
+
+    Some(subscan_operations)
+}
@@ -0,0 +1,133 @@
+use crate::{
+    subscan_parser::Network, EvmTransfer, StakingReward, SubscanOperation, TokenOperation,
+};
+use bson::DateTime;
+use rs_exchanges_parser::{
+    mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
+};
+use std::collections::HashMap;
+
+static PRICE_GRANULARITY_SECONDS: i64 = 60;
+
+/// Anything holding an AZERO amount at a point in time. `None` marks an amount that
+/// couldn't be valued.
+pub trait UsdValued {
+    fn get_timestamp(&self) -> DateTime;
+    fn get_quantity(&self) -> f64;
+    fn get_network(&self) -> &Network;
+    fn set_usd(&mut self, usd: Option<f64>);
+}
+
+impl UsdValued for SubscanOperation {
+    fn get_timestamp(&self) -> DateTime {
+        self.operation_timestamp
+    }
+
+    fn get_quantity(&self) -> f64 {
+        self.operation_quantity
+    }
+
+    fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    fn set_usd(&mut self, usd: Option<f64>) {
+        self.operation_usd = usd.unwrap_or_default();
+    }
+}
+
+impl UsdValued for StakingReward {
+    fn get_timestamp(&self) -> DateTime {
+        self.reward_timestamp
+    }
+
+    fn get_quantity(&self) -> f64 {
+        self.reward_quantity
+    }
+
+    fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    fn set_usd(&mut self, usd: Option<f64>) {
+        self.reward_usd = usd.unwrap_or_default();
+    }
+}
+
+impl UsdValued for TokenOperation {
+    fn get_timestamp(&self) -> DateTime {
+        self.operation_timestamp
+    }
+
+    fn get_quantity(&self) -> f64 {
+        self.operation_quantity
+    }
+
+    fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    fn set_usd(&mut self, usd: Option<f64>) {
+        self.operation_usd = usd;
+    }
+}
+
+impl UsdValued for EvmTransfer {
+    fn get_timestamp(&self) -> DateTime {
+        self.transfer_timestamp
+    }
+
+    fn get_quantity(&self) -> f64 {
+        self.transfer_quantity
+    }
+
+    fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    fn set_usd(&mut self, usd: Option<f64>) {
+        self.transfer_usd = usd;
+    }
+}
+
+/// Values operations in USD using the last AZERO trade before each operation. Operations
+/// older than the stored trades (backfill past their TTL) are left unvalued rather than
+/// valued at today's price. Testnet tokens have no market, so their operations are never
+/// valued.
+pub async fn set_usd_prices<T: UsdValued>(subscan_operations: &mut [T]) -> Option<()> {
+    let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
+    if subscan_operations
+        .iter()
+        .any(|p| *p.get_network() == Network::Alephzero)
+    {
+        // without any trade yet every operation would be left unvalued, retrying instead
+        mongodb_client_exchanges
+            .get_usd_price(PrimaryToken::Azero, SecondaryToken::Usdt)
+            .await?;
+    }
+
+    let mut prices = HashMap::new();
+    for s in subscan_operations.iter_mut() {
+        if *s.get_network() != Network::Alephzero {
+            s.set_usd(None);
+            continue;
+        }
+
+        let timestamp = s.get_timestamp().timestamp_millis() / 1_000;
+        let bucket = timestamp / PRICE_GRANULARITY_SECONDS;
+        let price = match prices.get(&bucket) {
+            Some(price) => *price,
+            None => {
+                let price = mongodb_client_exchanges
+                    .get_usd_price_at(PrimaryToken::Azero, SecondaryToken::Usdt, timestamp)
+                    .await;
+                prices.insert(bucket, price);
+                price
+            }
+        };
+
+        s.set_usd(price.map(|p| s.get_quantity() * p));
+    }
+
+    Some(())
+}
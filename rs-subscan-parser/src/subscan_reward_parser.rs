@@ -0,0 +1,287 @@
+use crate::{
+    mongodb_client_era_reward::MongoDbClientEraReward,
+    mongodb_client_reward::MongoDbClientReward,
+    mongodb_client_validator::MongoDbClientValidator,
+    subscan_call_decoder::get_account_id,
+    subscan_cursor::{parse_extrinsic_index, parse_pages_since_cursor, CURSOR_PAGE_SIZE},
+    subscan_parser::{Network, SubscanParser},
+    subscan_price::set_usd_prices,
+    EraReward, StakingReward, SubscanCursor, SubscanEvent, Validator,
+};
+use bson::DateTime;
+use futures::{stream::FuturesUnordered, StreamExt};
+use itertools::Itertools;
+use serde_json::json;
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use std::collections::HashMap;
+
+pub static REWARD_CURSOR_STREAM: &str = "staking_rewarded";
+static STAKING_MODULE_ID: &str = "staking";
+
+/// `staking.PayoutStarted` event, emitted once per validator before the rewards it pays.
+#[derive(Clone, Debug, PartialEq)]
+struct PayoutStarted {
+    event_position: (u64, u64),
+    era: u32,
+    validator: String,
+}
+
+pub async fn parse_rewards(head_block_number: u64) -> Option<(Vec<StakingReward>, SubscanCursor)> {
+    let (staking_rewards, _, cursor) = parse_pages_since_cursor(
+        REWARD_CURSOR_STREAM,
+        head_block_number,
+        |page, block_range| async move {
+            let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+            let (s, rows_len) = subscan_parser
+                .parse_subscan_reward_events(page, CURSOR_PAGE_SIZE, Some(block_range))
+                .await?;
+            Some((s, Vec::new(), rows_len))
+        },
+    )
+    .await?;
+
+    let staking_rewards = enrich_staking_rewards(staking_rewards).await?;
+
+    Some((staking_rewards, cursor))
+}
+
+/// Resolves era and validator of freshly parsed rewards from the payout extrinsics and
+/// values them in USD. Nominators missing from the validator collection are added.
+pub async fn enrich_staking_rewards(
+    staking_rewards: Vec<StakingReward>,
+) -> Option<Vec<StakingReward>> {
+    // skipping already existing records
+    let mut mongodb_client_reward = MongoDbClientReward::new().await;
+    let staking_rewards = mongodb_client_reward
+        .get_not_existing_rewards(staking_rewards)
+        .await;
+
+    // one extrinsic can pay out several validators, e.g. a batch of payout_stakers
+    let mut tasks = FuturesUnordered::new();
+    for extrinsic_index in staking_rewards
+        .iter()
+        .map(|p| p.extrinsic_index.clone())
+        .unique()
+    {
+        tasks.push(tokio::spawn(async move {
+            let network = Network::from_env();
+            let ss58_prefix = network.get_ss58_prefix();
+            let mut subscan_parser = SubscanParser::new(network).await;
+            let events = subscan_parser
+                .parse_subscan_extrinsic_details(extrinsic_index.clone())
+                .await?;
+
+            let payouts = events
+                .iter()
+                .filter_map(|p| get_payout_started(p, ss58_prefix))
+                .collect::<Vec<_>>();
+            Some((extrinsic_index, payouts))
+        }));
+    }
+
+    let mut payouts = HashMap::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some((extrinsic_index, p))) = res else {
+            continue;
+        };
+        payouts.insert(extrinsic_index, p);
+    }
+
+    let mut staking_rewards = set_payouts(staking_rewards, &payouts);
+
+    // valuing in USD at the time of payout
+    set_usd_prices(&mut staking_rewards).await?;
+
+    // a nominator's reward tells which validator it backs
+    let nominators = staking_rewards
+        .iter()
+        .filter(|p| !p.is_validator)
+        .map(|p| p.stash.clone())
+        .unique()
+        .collect::<Vec<_>>();
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    let nominators = mongodb_client_validator
+        .get_not_existing_nominators(nominators)
+        .await;
+    let validators = nominators
+        .into_iter()
+        .filter_map(|nominator| {
+            let s = staking_rewards.iter().find(|p| p.stash == nominator)?;
+            Some(Validator {
+                nominator,
+                validator: s.validator.clone(),
+            })
+        })
+        .collect();
+    mongodb_client_validator
+        .import_or_update_validators(validators)
+        .await;
+
+    Some(staking_rewards)
+}
+
+/// Recomputes totals of the given eras from all of their stored rewards, so payouts
+/// claimed late are counted as well.
+pub async fn update_era_rewards(eras: Vec<u32>) {
+    let mut mongodb_client_reward = MongoDbClientReward::new().await;
+
+    let mut era_rewards = Vec::new();
+    for era in eras.into_iter().unique() {
+        let staking_rewards = mongodb_client_reward.get_rewards_by_era(era).await;
+        era_rewards.push(EraReward {
+            era,
+            reward_quantity: staking_rewards.iter().map(|p| p.reward_quantity).sum(),
+            reward_usd: staking_rewards.iter().map(|p| p.reward_usd).sum(),
+            validators: staking_rewards
+                .iter()
+                .map(|p| &p.validator)
+                .unique()
+                .count() as u32,
+            nominators: staking_rewards
+                .iter()
+                .filter(|p| !p.is_validator)
+                .map(|p| &p.stash)
+                .unique()
+                .count() as u32,
+            updated_timestamp: DateTime::now(),
+            network: Network::from_env(),
+        });
+    }
+
+    let mut mongodb_client_era_reward = MongoDbClientEraReward::new().await;
+    mongodb_client_era_reward
+        .import_or_update_era_rewards(era_rewards)
+        .await;
+}
+
+/// Rewards follow the `PayoutStarted` of their validator within the same extrinsic.
+/// Rewards without one are dropped.
+fn set_payouts(
+    staking_rewards: Vec<StakingReward>,
+    payouts: &HashMap<String, Vec<PayoutStarted>>,
+) -> Vec<StakingReward> {
+    staking_rewards
+        .into_iter()
+        .filter_map(|s| {
+            let event_position = parse_extrinsic_index(&s.event_index)?;
+            let payout = payouts
+                .get(&s.extrinsic_index)?
+                .iter()
+                .filter(|p| p.event_position < event_position)
+                .max_by_key(|p| p.event_position)?;
+
+            Some(StakingReward {
+                era: payout.era,
+                is_validator: s.stash == payout.validator,
+                validator: payout.validator.clone(),
+                ..s
+            })
+        })
+        .collect()
+}
+
+/// Only `PayoutStarted` carries an era index among staking events.
+fn get_payout_started(event: &SubscanEvent, ss58_prefix: u16) -> Option<PayoutStarted> {
+    if event.module_id != STAKING_MODULE_ID {
+        return None;
+    }
+
+    let era = event
+        .event_params
+        .iter()
+        .find(|p| p.name == "era_index" || p.type_name == "EraIndex")?;
+    let validator = event
+        .event_params
+        .iter()
+        .find(|p| p.name == "validator_stash" || p.type_name.contains("AccountId"))?;
+
+    Some(PayoutStarted {
+        event_position: parse_extrinsic_index(&event.event_index)?,
+        era: era.value.parse().ok()?,
+        validator: get_account_id(&json!(validator.value))?
+            .to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_parser::{Network, EMPTY_ADDRESS},
+        subscan_reward_parser::{get_payout_started, set_payouts},
+        StakingReward, SubscanEvent, SubscanEventParam,
+    };
+    use bson::DateTime;
+    use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+    use std::collections::HashMap;
+
+    fn get_address(byte: u8) -> String {
+        AccountId32::from([byte; 32]).to_ss58check_with_version(Ss58AddressFormat::custom(42))
+    }
+
+    fn get_payout_event(event_index: &str, era: u32, validator: u8) -> SubscanEvent {
+        SubscanEvent {
+            module_id: "staking".to_string(),
+            event_id: "PayoutStarted".to_string(),
+            event_index: event_index.to_string(),
+            event_params: vec![
+                SubscanEventParam {
+                    type_name: "EraIndex".to_string(),
+                    value: era.to_string(),
+                    name: "era_index".to_string(),
+                },
+                SubscanEventParam {
+                    type_name: "AccountId".to_string(),
+                    value: format!("0x{}", hex::encode([validator; 32])),
+                    name: "validator_stash".to_string(),
+                },
+            ],
+        }
+    }
+
+    fn get_reward(event_index: &str, stash: u8) -> StakingReward {
+        StakingReward {
+            era: 0,
+            block_number: 100,
+            event_index: event_index.to_string(),
+            extrinsic_index: "100-2".to_string(),
+            reward_timestamp: DateTime::now(),
+            reward_quantity: 10.0,
+            reward_usd: 0.0,
+            stash: get_address(stash),
+            validator: EMPTY_ADDRESS.to_string(),
+            is_validator: false,
+            network: Network::Alephzero,
+        }
+    }
+
+    #[test]
+    fn set_payouts_works() {
+        // a batch paying out two validators, each followed by its own rewards
+        let payouts = [
+            get_payout_event("100-5", 611, 1),
+            get_payout_event("100-9", 612, 2),
+        ]
+        .iter()
+        .filter_map(|p| get_payout_started(p, 42))
+        .collect();
+        let payouts = HashMap::from([("100-2".to_string(), payouts)]);
+
+        let staking_rewards = vec![
+            get_reward("100-4", 3),
+            get_reward("100-6", 1),
+            get_reward("100-7", 3),
+            get_reward("100-10", 4),
+        ];
+        let staking_rewards = set_payouts(staking_rewards, &payouts);
+        assert_eq!(staking_rewards.len(), 3);
+
+        assert!(staking_rewards[0].is_validator);
+        assert_eq!(staking_rewards[0].era, 611);
+
+        assert!(!staking_rewards[1].is_validator);
+        assert_eq!(staking_rewards[1].validator, get_address(1));
+
+        assert_eq!(staking_rewards[2].era, 612);
+        assert_eq!(staking_rewards[2].validator, get_address(2));
+    }
+}
@@ -1,310 +1,388 @@
 use crate::{
-    mongodb_client_identities::MongoDbClientIdentity,
-    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_nomination::MongoDbClientNomination,
     mongodb_client_validator::MongoDbClientValidator,
+    subscan_cursor::parse_stream_since_cursor,
+    subscan_enrichment::{EnrichmentPipeline, EnrichmentStage, EnrichmentStageKind},
     subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR},
-    ExtrinsicsType, Module, SubscanOperation, Validator, MINIMUM_AZERO_TO_SAVE_TO_DB,
+    ExtrinsicsType, Module, Nomination, SubscanCursor, SubscanEvent, SubscanOperation,
+    SubscanStream, Validator,
 };
-use futures::{stream::FuturesUnordered, StreamExt};
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
 use itertools::Itertools;
-use rs_exchanges_parser::{
-    mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
-};
 use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
-use std::collections::HashSet;
-use strum::IntoEnumIterator;
-
-pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
-    let price_task = tokio::spawn(async move {
-        let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
-        mongodb_client_exchanges
-            .get_usd_price(PrimaryToken::Azero, SecondaryToken::Usdt)
-            .await
-    });
 
+pub async fn parse_staking(
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, Vec<SubscanCursor>)> {
     let mut tasks = FuturesUnordered::new();
-    for e in ExtrinsicsType::iter() {
+    for stream in SubscanStream::get_staking_streams() {
         tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser
-                .parse_subscan_operations("", Module::Staking, e, 100)
-                .await
+            (
+                stream.clone(),
+                parse_stream_since_cursor(stream, head_block_number).await,
+            )
         }));
     }
 
-    let mut subscan_operations = Vec::new();
+    let mut staking_operations = Vec::new();
+    let mut wrapped_operations = Vec::new();
+    let mut cursors = Vec::new();
     while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
+        let Ok((stream, s)) = res else {
             continue;
         };
 
-        let Some(mut s) = s else {
+        let Some((mut s, _, cursor)) = s else {
             continue;
         };
-        subscan_operations.append(&mut s);
+
+        // wrapped calls already carry stash and amount
+        if stream.is_wrapper() {
+            wrapped_operations.append(&mut s);
+        } else {
+            staking_operations.append(&mut s);
+        }
+        cursors.push(cursor);
     }
 
-    // skipping already existing records
-    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
-    let subscan_operations = mongodb_client_subscan
-        .get_not_existing_operations(subscan_operations)
-        .await;
+    let subscan_operations =
+        enrich_staking_operations(staking_operations, wrapped_operations).await?;
 
-    // adding from_wallet and operation_quantity
-    let mut tasks = FuturesUnordered::new();
-    for s in subscan_operations {
-        let mut s_clone = s.clone();
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            let events = subscan_parser
-                .parse_subscan_extrinsic_details(s.extrinsic_index)
-                .await?;
+    Some((subscan_operations, cursors))
+}
 
-            let stake_event = events.iter().find(|p| p.module_id == "staking")?;
+static STAKING_ENRICHMENT_STAGES: [EnrichmentStageKind; 10] = [
+    EnrichmentStageKind::SkipExisting,
+    EnrichmentStageKind::SaveNominations,
+    EnrichmentStageKind::FilterDust,
+    EnrichmentStageKind::ValueInUsd,
+    EnrichmentStageKind::ResolveNominators,
+    EnrichmentStageKind::ResolveValidator,
+    EnrichmentStageKind::ResolveControllers,
+    EnrichmentStageKind::SetHash,
+    // the hash is taken before the controller nominations apply, as it always was, so it
+    // matches operations stored earlier; this pass then points stashes with a separate
+    // controller to the validator `ResolveControllers` just saved
+    EnrichmentStageKind::ResolveValidator,
+    EnrichmentStageKind::ResolveIdentities,
+];
+
+/// Resolves stash, amount, validator, USD value and identities of freshly parsed staking
+/// operations. Shared by live ingestion and backfill. The stages after decoding can be
+/// changed with `STAKING_ENRICHMENT_STAGES`.
+pub async fn enrich_staking_operations(
+    staking_operations: Vec<SubscanOperation>,
+    mut wrapped_operations: Vec<SubscanOperation>,
+) -> Option<Vec<SubscanOperation>> {
+    // direct calls take stash and amount from their events, wrapped calls carry them
+    let mut subscan_operations = EnrichmentPipeline::from_kinds(&[
+        EnrichmentStageKind::SkipExisting,
+        EnrichmentStageKind::DecodeStakeEvent,
+    ])
+    .run(staking_operations)
+    .await?;
+    subscan_operations.append(&mut wrapped_operations);
+
+    EnrichmentPipeline::from_env("STAKING_ENRICHMENT_STAGES", &STAKING_ENRICHMENT_STAGES)
+        .run(subscan_operations)
+        .await
+}
 
-            // event must have at least 2 parameters
-            if stake_event.event_params.len() < 2 {
-                return None;
-            }
+/// Sets stash and amount from the staking event of the extrinsic, operations without
+/// one are dropped.
+pub struct DecodeStakeEventStage;
 
-            let stash_param = stake_event.event_params.first()?;
-            if stash_param.name != "stash" && stash_param.name != "who" {
-                return None;
-            }
+impl EnrichmentStage for DecodeStakeEventStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::DecodeStakeEvent.into()
+    }
 
-            let amount_param = stake_event.event_params.last()?;
-            if amount_param.name != "amount" {
-                return None;
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let mut tasks = FuturesUnordered::new();
+            for s in subscan_operations {
+                tasks.push(tokio::spawn(async move { decode_stake_event(s).await }));
             }
 
-            let stash_wallet = stash_param.value.clone()[2..].to_string();
-            let decoded = hex::decode(stash_wallet).ok()?;
-            let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-            let address = AccountId32::from(byte_arr)
-                .to_ss58check_with_version(Ss58AddressFormat::custom(42));
-            s_clone.from_wallet = address;
-            s_clone.operation_quantity =
-                amount_param.value.parse::<f64>().ok()? / AZERO_DENOMINATOR;
+            let mut subscan_operations = Vec::new();
+            while let Some(res) = tasks.next().await {
+                let Ok(Some(s)) = res else {
+                    continue;
+                };
+                subscan_operations.push(s);
+            }
 
-            Some(s_clone)
-        }));
+            Some(subscan_operations)
+        }
+        .boxed()
     }
+}
 
-    let mut subscan_operations = Vec::new();
-    while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
-            continue;
-        };
-
-        let Some(s) = s else {
-            continue;
-        };
-        subscan_operations.push(s);
-    }
+async fn decode_stake_event(mut s: SubscanOperation) -> Option<SubscanOperation> {
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    let events = subscan_parser
+        .parse_subscan_extrinsic_details(s.extrinsic_index.clone())
+        .await?;
 
-    // parsing batch all operations
-    let batch_all_operations = tokio::spawn(async move {
-        let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-        subscan_parser.parse_subscan_batch_all("", 0, 20).await
-    })
-    .await
-    .ok()??;
-
-    // skipping already existing records
-    let mut batch_all_operations = mongodb_client_subscan
-        .get_not_existing_operations(batch_all_operations)
-        .await;
+    let stake_event = events.iter().find(|p| p.module_id == "staking")?;
+    let (stash, amount) = decode_stake_event_params(stake_event, s.network.get_ss58_prefix())?;
+    s.from_wallet = stash;
+    s.operation_quantity = amount;
 
-    subscan_operations.append(&mut batch_all_operations);
+    Some(s)
+}
 
-    // saving validators to db
-    let validators = convert_operations_to_validators(subscan_operations.clone());
-    let validators_task = tokio::spawn(async move {
-        let mut mongodb_client_validator = MongoDbClientValidator::new().await;
-        mongodb_client_validator
-            .import_or_update_validators(validators)
-            .await
-    });
+/// Returns stash and amount of a staking event, none when its parameters are not
+/// `stash`/`who` first and `amount` last.
+fn decode_stake_event_params(
+    stake_event: &SubscanEvent,
+    ss58_prefix: u16,
+) -> Option<(String, f64)> {
+    // event must have at least 2 parameters
+    if stake_event.event_params.len() < 2 {
+        return None;
+    }
 
-    // removing operations with less than MINIMUM_AZERO_TO_SAVE_TO_DB AZERO amount
-    let mut subscan_operations = subscan_operations
-        .into_iter()
-        .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
-        .collect::<Vec<_>>();
+    let stash_param = stake_event.event_params.first()?;
+    if stash_param.name != "stash" && stash_param.name != "who" {
+        return None;
+    }
 
-    // updating to current price
-    let price = price_task.await.ok()??;
-    for s in subscan_operations.iter_mut() {
-        s.operation_usd = s.operation_quantity * price;
+    let amount_param = stake_event.event_params.last()?;
+    if amount_param.name != "amount" {
+        return None;
     }
 
-    validators_task.await.ok()?;
+    let stash_wallet = stash_param.value.strip_prefix("0x")?;
+    let decoded = hex::decode(stash_wallet).ok()?;
+    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
+    let address = AccountId32::from(byte_arr)
+        .to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix));
+    let amount = amount_param.value.parse::<f64>().ok()? / AZERO_DENOMINATOR;
 
-    // getting nominators missing in validators DB to update them
-    let nominators = subscan_operations
-        .iter()
-        .map(|m| m.from_wallet.clone())
-        .unique()
-        .collect::<Vec<String>>();
-    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
-    let not_existing_nominators = mongodb_client_validator
-        .get_not_existing_nominators(nominators)
-        .await;
+    Some((address, amount))
+}
 
-    // parsing validators for given non existing nominators
-    let mut tasks = FuturesUnordered::new();
-    for nominator in not_existing_nominators.into_iter() {
-        let nominator_clone = nominator.clone();
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser
-                .parse_subscan_batch_all(&nominator_clone, 0, 100)
-                .await
-        }));
+/// Saves validators and nominations the operations themselves carry.
+pub struct SaveNominationsStage;
 
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser
-                .parse_subscan_operations(&nominator, Module::Staking, ExtrinsicsType::Nominate, 1)
-                .await
-        }));
+impl EnrichmentStage for SaveNominationsStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::SaveNominations.into()
     }
 
-    let mut validators = Vec::new();
-    while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
-            continue;
-        };
-
-        let Some(s) = s else {
-            continue;
-        };
-
-        let mut v = convert_operations_to_validators(s);
-        validators.append(&mut v);
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+            mongodb_client_validator
+                .import_or_update_validators(convert_operations_to_validators(
+                    subscan_operations.clone(),
+                ))
+                .await;
+
+            let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+            mongodb_client_nomination
+                .import_nominations(convert_operations_to_nominations(&subscan_operations))
+                .await;
+
+            Some(subscan_operations)
+        }
+        .boxed()
     }
+}
 
-    // updating validators
-    mongodb_client_validator
-        .import_or_update_validators(validators)
-        .await;
+/// Parses the latest nominations of stashes missing in the validators DB.
+pub struct ResolveNominatorsStage;
 
-    for s in subscan_operations.iter_mut() {
-        let to_wallet = mongodb_client_validator
-            .get_validator_by_nominator(&s.from_wallet)
-            .await;
-        let Some(to_wallet) = to_wallet else {
-            continue;
-        };
-        s.to_wallet = to_wallet.validator;
+impl EnrichmentStage for ResolveNominatorsStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::ResolveNominators.into()
     }
 
-    // for wallets with separate controller wallet, we should find out to which validator they staked from controller wallet
-    for s in subscan_operations.iter_mut() {
-        if !SubscanParser::is_address_empty(&s.to_wallet) {
-            continue;
-        }
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let nominators = get_nominators(&subscan_operations);
+            let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+            let not_existing_nominators = mongodb_client_validator
+                .get_not_existing_nominators(nominators)
+                .await;
+
+            // parsing validators for given non existing nominators
+            let mut tasks = FuturesUnordered::new();
+            for nominator in not_existing_nominators.into_iter() {
+                let nominator_clone = nominator.clone();
+                tasks.push(tokio::spawn(async move {
+                    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+                    subscan_parser
+                        .parse_subscan_batch_all(&nominator_clone, 0, 100, None)
+                        .await
+                }));
+
+                tasks.push(tokio::spawn(async move {
+                    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+                    subscan_parser
+                        .parse_subscan_operations(
+                            &nominator,
+                            Module::Staking,
+                            ExtrinsicsType::Nominate,
+                            0,
+                            1,
+                            None,
+                        )
+                        .await
+                        .map(|(s, _)| s)
+                }));
+            }
 
-        if SubscanParser::is_address_empty(&s.controller_wallet) {
-            continue;
-        }
+            let mut validators = Vec::new();
+            let mut nominations = Vec::new();
+            while let Some(res) = tasks.next().await {
+                let Ok(Some(s)) = res else {
+                    continue;
+                };
 
-        let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-        let controller_operations = subscan_parser
-            .parse_subscan_operations(
-                &s.controller_wallet,
-                Module::Staking,
-                ExtrinsicsType::Nominate,
-                1,
-            )
-            .await;
+                nominations.append(&mut convert_operations_to_nominations(&s));
+                validators.append(&mut convert_operations_to_validators(s));
+            }
 
-        let Some(mut controller_operations) = controller_operations else {
-            continue;
-        };
+            // updating validators and nominations
+            mongodb_client_validator
+                .import_or_update_validators(validators)
+                .await;
+            let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+            mongodb_client_nomination
+                .import_nominations(nominations)
+                .await;
 
-        for c in controller_operations.iter_mut() {
-            c.from_wallet = s.from_wallet.clone();
+            Some(subscan_operations)
         }
-
-        // updating validators
-        mongodb_client_validator
-            .import_or_update_validators(convert_operations_to_validators(controller_operations))
-            .await;
+        .boxed()
     }
+}
 
-    for s in subscan_operations.iter_mut() {
-        s.set_hash();
+/// Points operations to the validators their stashes nominated.
+pub struct ResolveValidatorStage;
 
-        let to_wallet = mongodb_client_validator
-            .get_validator_by_nominator(&s.from_wallet)
-            .await;
-        let Some(to_wallet) = to_wallet else {
-            continue;
-        };
-        s.to_wallet = to_wallet.validator;
+impl EnrichmentStage for ResolveValidatorStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::ResolveValidator.into()
     }
 
-    // removing operations with less than MINIMUM_AZERO_TO_SAVE_TO_DB AZERO amount
-    let subscan_operations = subscan_operations
-        .into_iter()
-        .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
-        .collect::<Vec<_>>();
+    fn enrich(
+        &self,
+        mut subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+            let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+            for s in subscan_operations.iter_mut() {
+                set_validator(
+                    s,
+                    &mut mongodb_client_nomination,
+                    &mut mongodb_client_validator,
+                )
+                .await;
+            }
 
-    let from_wallets = subscan_operations
-        .iter()
-        .map(|m| m.from_wallet.to_string())
-        .collect::<Vec<_>>();
+            Some(subscan_operations)
+        }
+        .boxed()
+    }
+}
 
-    let to_wallets = subscan_operations
-        .iter()
-        .map(|m| m.to_wallet.to_string())
-        .collect::<Vec<_>>();
-    let new_addresses: HashSet<String> =
-        HashSet::from_iter(from_wallets.into_iter().chain(to_wallets.into_iter()));
-    let new_addresses = new_addresses.into_iter().collect::<Vec<_>>();
-
-    // skipping already existing records
-    let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
-    let new_addresses = mongodb_client_identity
-        .get_not_existing_addresses(new_addresses)
-        .await;
+/// For stashes with a separate controller, saves the nominations made from the
+/// controller, so a following `ResolveValidator` finds their validator.
+pub struct ResolveControllersStage;
 
-    // parsing non existing identities
-    let mut tasks = FuturesUnordered::new();
-    for a in new_addresses {
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser.parse_subscan_identity(&a, 0, 1).await
-        }));
+impl EnrichmentStage for ResolveControllersStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::ResolveControllers.into()
     }
 
-    let mut identities = Vec::new();
-    while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
-            continue;
-        };
-
-        let Some(mut s) = s else {
-            continue;
-        };
+    fn enrich(
+        &self,
+        subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+            let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+            for s in subscan_operations.iter() {
+                if !needs_controller_nominations(s) {
+                    continue;
+                }
+
+                let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+                let controller_operations = subscan_parser
+                    .parse_subscan_operations(
+                        &s.controller_wallet,
+                        Module::Staking,
+                        ExtrinsicsType::Nominate,
+                        0,
+                        1,
+                        None,
+                    )
+                    .await
+                    .map(|(s, _)| s);
+
+                let Some(mut controller_operations) = controller_operations else {
+                    continue;
+                };
+
+                attribute_to_stash(&mut controller_operations, &s.from_wallet);
+
+                // updating validators and nominations
+                mongodb_client_nomination
+                    .import_nominations(convert_operations_to_nominations(&controller_operations))
+                    .await;
+                mongodb_client_validator
+                    .import_or_update_validators(convert_operations_to_validators(
+                        controller_operations,
+                    ))
+                    .await;
+            }
 
-        identities.append(&mut s);
+            Some(subscan_operations)
+        }
+        .boxed()
     }
+}
 
-    // saving newly parsed identities
-    mongodb_client_identity
-        .import_or_update_identities(identities)
-        .await;
+/// Unique stashes of the operations.
+fn get_nominators(subscan_operations: &[SubscanOperation]) -> Vec<String> {
+    subscan_operations
+        .iter()
+        .map(|m| m.from_wallet.clone())
+        .unique()
+        .collect()
+}
+
+/// Operations without a validator yet, made by a stash with a separate controller.
+fn needs_controller_nominations(subscan_operation: &SubscanOperation) -> bool {
+    SubscanParser::is_address_empty(&subscan_operation.to_wallet)
+        && !SubscanParser::is_address_empty(&subscan_operation.controller_wallet)
+}
 
-    Some(subscan_operations)
+/// Nominations made from a controller count for its stash.
+fn attribute_to_stash(controller_operations: &mut [SubscanOperation], stash: &str) {
+    for c in controller_operations.iter_mut() {
+        c.from_wallet = stash.to_string();
+    }
 }
 
 fn convert_operations_to_validators(source: Vec<SubscanOperation>) -> Vec<Validator> {
     source
         .into_iter()
         .filter_map(|p| {
-            if SubscanParser::is_address_empty(&p.to_wallet)
+            if SubscanParser::is_address_empty(&p.from_wallet)
                 || SubscanParser::is_address_empty(&p.to_wallet)
             {
                 return None;
@@ -317,3 +395,227 @@ fn convert_operations_to_validators(source: Vec<SubscanOperation>) -> Vec<Valida
         })
         .collect()
 }
+
+/// Points the operation to the first validator its stash nominated as of the operation's
+/// block, none after a chill. Falls back to the latest known validator when the history
+/// does not reach back.
+async fn set_validator(
+    subscan_operation: &mut SubscanOperation,
+    mongodb_client_nomination: &mut MongoDbClientNomination,
+    mongodb_client_validator: &mut MongoDbClientValidator,
+) {
+    let nomination = mongodb_client_nomination
+        .get_nomination_at_block(
+            &subscan_operation.from_wallet,
+            subscan_operation.block_number,
+        )
+        .await;
+    if let Some(nomination) = nomination {
+        apply_nomination(subscan_operation, nomination);
+        return;
+    }
+
+    let Some(validator) = mongodb_client_validator
+        .get_validator_by_nominator(&subscan_operation.from_wallet)
+        .await
+    else {
+        return;
+    };
+    subscan_operation.to_wallet = validator.validator;
+}
+
+/// Points the operation to the first target of the nomination, a chill has none and
+/// leaves the operation as is.
+fn apply_nomination(subscan_operation: &mut SubscanOperation, nomination: Nomination) {
+    if let Some(target) = nomination.targets.first() {
+        subscan_operation.to_wallet = target.clone();
+        subscan_operation.targets = nomination.targets;
+    }
+}
+
+fn convert_operations_to_nominations(source: &[SubscanOperation]) -> Vec<Nomination> {
+    source
+        .iter()
+        .filter(|p| !p.targets.is_empty() && !SubscanParser::is_address_empty(&p.from_wallet))
+        .map(|p| Nomination {
+            nominator: p.from_wallet.clone(),
+            targets: p.targets.clone(),
+            extrinsic_index: p.extrinsic_index.clone(),
+            from_block: p.block_number,
+            to_block: None,
+            network: p.network.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_parser::EMPTY_ADDRESS,
+        subscan_stake_parser::{
+            apply_nomination, attribute_to_stash, convert_operations_to_nominations,
+            convert_operations_to_validators, decode_stake_event_params, get_nominators,
+            needs_controller_nominations,
+        },
+        Nomination, SubscanEvent, SubscanEventParam, SubscanOperation, Validator,
+    };
+    use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+
+    fn get_address(byte: u8) -> String {
+        AccountId32::from([byte; 32]).to_ss58check_with_version(Ss58AddressFormat::custom(42))
+    }
+
+    fn get_stake_event(stash_name: &str, amount_name: &str) -> SubscanEvent {
+        SubscanEvent {
+            module_id: "staking".to_string(),
+            event_id: "Bonded".to_string(),
+            event_index: "100-1".to_string(),
+            event_params: vec![
+                SubscanEventParam {
+                    type_name: "AccountId".to_string(),
+                    value: format!("0x{}", hex::encode([1; 32])),
+                    name: stash_name.to_string(),
+                },
+                SubscanEventParam {
+                    type_name: "BalanceOf".to_string(),
+                    value: "25000000000000000".to_string(),
+                    name: amount_name.to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn decode_stake_event_params_works() {
+        assert_eq!(
+            decode_stake_event_params(&get_stake_event("stash", "amount"), 42),
+            Some((get_address(1), 25_000.0))
+        );
+        assert_eq!(
+            decode_stake_event_params(&get_stake_event("who", "amount"), 42),
+            Some((get_address(1), 25_000.0))
+        );
+        assert_eq!(
+            decode_stake_event_params(&get_stake_event("era", "amount"), 42),
+            None
+        );
+        assert_eq!(
+            decode_stake_event_params(&get_stake_event("stash", "value"), 42),
+            None
+        );
+
+        let mut short_stash = get_stake_event("stash", "amount");
+        short_stash.event_params[0].value = "0x0101".to_string();
+        assert_eq!(decode_stake_event_params(&short_stash, 42), None);
+
+        let mut single_param = get_stake_event("stash", "amount");
+        single_param.event_params.pop();
+        assert_eq!(decode_stake_event_params(&single_param, 42), None);
+    }
+
+    #[test]
+    fn apply_nomination_works() {
+        let nomination = Nomination {
+            nominator: get_address(1),
+            targets: vec![get_address(2), get_address(3)],
+            extrinsic_index: "100-1".to_string(),
+            from_block: 100,
+            to_block: None,
+            network: Default::default(),
+        };
+
+        let mut subscan_operation = SubscanOperation {
+            from_wallet: get_address(1),
+            to_wallet: EMPTY_ADDRESS.to_string(),
+            ..Default::default()
+        };
+        apply_nomination(&mut subscan_operation, nomination.clone());
+        assert_eq!(subscan_operation.to_wallet, get_address(2));
+        assert_eq!(subscan_operation.targets, nomination.targets);
+
+        // a chill keeps the validator found by the earlier pass
+        let chill = Nomination {
+            targets: vec![],
+            ..nomination
+        };
+        apply_nomination(&mut subscan_operation, chill);
+        assert_eq!(subscan_operation.to_wallet, get_address(2));
+    }
+
+    #[test]
+    fn controller_nominations_work() {
+        let stash_operation = SubscanOperation {
+            from_wallet: get_address(1),
+            to_wallet: EMPTY_ADDRESS.to_string(),
+            controller_wallet: get_address(4),
+            ..Default::default()
+        };
+        assert!(needs_controller_nominations(&stash_operation));
+        assert!(!needs_controller_nominations(&SubscanOperation {
+            to_wallet: get_address(2),
+            ..stash_operation.clone()
+        }));
+        assert!(!needs_controller_nominations(&SubscanOperation {
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            ..stash_operation.clone()
+        }));
+
+        let mut controller_operations = vec![SubscanOperation {
+            from_wallet: get_address(4),
+            to_wallet: get_address(2),
+            targets: vec![get_address(2)],
+            extrinsic_index: "100-1".to_string(),
+            block_number: 100,
+            ..Default::default()
+        }];
+        attribute_to_stash(&mut controller_operations, &stash_operation.from_wallet);
+        assert_eq!(
+            convert_operations_to_nominations(&controller_operations),
+            vec![Nomination {
+                nominator: get_address(1),
+                targets: vec![get_address(2)],
+                extrinsic_index: "100-1".to_string(),
+                from_block: 100,
+                to_block: None,
+                network: Default::default(),
+            }]
+        );
+        assert_eq!(
+            convert_operations_to_validators(controller_operations),
+            vec![Validator {
+                nominator: get_address(1),
+                validator: get_address(2),
+            }]
+        );
+    }
+
+    #[test]
+    fn get_nominators_works() {
+        let subscan_operations = vec![
+            SubscanOperation {
+                from_wallet: get_address(1),
+                ..Default::default()
+            },
+            SubscanOperation {
+                from_wallet: get_address(2),
+                ..Default::default()
+            },
+            SubscanOperation {
+                from_wallet: get_address(1),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            get_nominators(&subscan_operations),
+            vec![get_address(1), get_address(2)]
+        );
+
+        // operations without a stash or validator are not saved
+        assert!(convert_operations_to_validators(vec![SubscanOperation {
+            from_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: get_address(2),
+            ..Default::default()
+        }])
+        .is_empty());
+    }
+}
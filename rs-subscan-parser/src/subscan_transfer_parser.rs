@@ -1,62 +1,50 @@
 use crate::{
+    bridge_parser::load_bridges_from_env,
     mongodb_client_identities::MongoDbClientIdentity,
-    subscan_parser::{Network, SubscanParser},
-    SubscanOperation, MINIMUM_AZERO_TO_SAVE_TO_DB,
+    mongodb_client_label::MongoDbClientLabel,
+    subscan_cursor::parse_stream_since_cursor,
+    subscan_enrichment::{EnrichmentPipeline, EnrichmentStage, EnrichmentStageKind},
+    BridgeTransfer, Identity, LabelCategory, OperationType, SubscanCursor, SubscanOperation,
+    SubscanStream,
 };
-use futures::{stream::FuturesUnordered, StreamExt};
+use futures::{future::BoxFuture, FutureExt};
 use itertools::Itertools;
-use rs_exchanges_parser::{
-    mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
-};
-use std::collections::HashSet;
-
-pub async fn parse_transfers() -> Option<Vec<SubscanOperation>> {
-    let price_task = tokio::spawn(async move {
-        let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
-        mongodb_client_exchanges
-            .get_usd_price(PrimaryToken::Azero, SecondaryToken::Usdt)
-            .await
-    });
-
-    let mut tasks = FuturesUnordered::new();
-    for page in 0..10 {
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser.parse_subscan_transfers(page, 100).await
-        }));
-    }
+use rs_exchanges_parser::mongodb_client_exchange_wallets::MongoDbClientExchangeWallets;
+use std::collections::{HashMap, HashSet};
 
-    let mut subscan_operations = Vec::new();
-    let mut identities = HashSet::new();
-    while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
-            continue;
-        };
+// classifying after hashing, so registering a wallet later does not change hashes
+static TRANSFER_ENRICHMENT_STAGES: [EnrichmentStageKind; 4] = [
+    EnrichmentStageKind::FilterDust,
+    EnrichmentStageKind::ValueInUsd,
+    EnrichmentStageKind::SetHash,
+    EnrichmentStageKind::ClassifyTransfers,
+];
 
-        let Some((mut s, d)) = s else {
-            continue;
-        };
-        subscan_operations.append(&mut s);
-        for dd in d {
-            identities.insert(dd);
-        }
-    }
+pub async fn parse_transfers(
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, Vec<SubscanCursor>)> {
+    let (subscan_operations, identities, cursor) =
+        parse_stream_since_cursor(SubscanStream::Transfers, head_block_number).await?;
 
-    let identities = identities.into_iter().collect_vec();
+    let subscan_operations = enrich_transfers(subscan_operations, identities).await?;
 
-    // removing operations with less than MINIMUM_AZERO_TO_SAVE_TO_DB AZERO amount
-    let mut subscan_operations = subscan_operations
-        .into_iter()
-        .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
-        .collect::<Vec<_>>();
+    Some((subscan_operations, vec![cursor]))
+}
 
-    // updating to current price
-    let price = price_task.await.ok()??;
-    for s in subscan_operations.iter_mut() {
-        s.operation_usd = s.operation_quantity * price;
+/// Values transfers in USD, classifies them by exchange wallets and saves identities seen
+/// in them. Shared by live ingestion and backfill. The stages can be changed with
+/// `TRANSFER_ENRICHMENT_STAGES`.
+pub async fn enrich_transfers(
+    subscan_operations: Vec<SubscanOperation>,
+    identities: Vec<Identity>,
+) -> Option<Vec<SubscanOperation>> {
+    let identities: HashSet<_> = HashSet::from_iter(identities);
+    let identities = identities.into_iter().collect_vec();
 
-        s.set_hash();
-    }
+    let subscan_operations =
+        EnrichmentPipeline::from_env("TRANSFER_ENRICHMENT_STAGES", &TRANSFER_ENRICHMENT_STAGES)
+            .run(subscan_operations)
+            .await?;
 
     // saving newly parsed identities
     let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
@@ -66,3 +54,156 @@ pub async fn parse_transfers() -> Option<Vec<SubscanOperation>> {
 
     Some(subscan_operations)
 }
+
+/// Classifies transfers from and to exchange wallets.
+pub struct ClassifyTransfersStage;
+
+impl EnrichmentStage for ClassifyTransfersStage {
+    fn get_name(&self) -> &'static str {
+        EnrichmentStageKind::ClassifyTransfers.into()
+    }
+
+    fn enrich(
+        &self,
+        mut subscan_operations: Vec<SubscanOperation>,
+    ) -> BoxFuture<'_, Option<Vec<SubscanOperation>>> {
+        async move {
+            let exchange_addresses = get_exchange_addresses().await;
+            set_transfer_types(&mut subscan_operations, &exchange_addresses);
+            let bridge_transfers = get_bridge_transfers().await;
+            set_bridge_transfers(&mut subscan_operations, &bridge_transfers);
+            Some(subscan_operations)
+        }
+        .boxed()
+    }
+}
+
+/// Confirmed exchange wallets of the registry and addresses labeled as exchanges.
+async fn get_exchange_addresses() -> HashSet<String> {
+    let mut mongodb_client_exchange_wallets = MongoDbClientExchangeWallets::new().await;
+    let mut mongodb_client_label = MongoDbClientLabel::new().await;
+
+    mongodb_client_exchange_wallets
+        .get_confirmed_exchange_wallets()
+        .await
+        .into_iter()
+        .map(|p| p.address)
+        .chain(
+            mongodb_client_label
+                .get_labels_by_category(LabelCategory::Exchange)
+                .await
+                .into_iter()
+                .map(|p| p.address),
+        )
+        .collect()
+}
+
+/// Addresses labeled as bridges, with the chain of the ones in the `BRIDGES_PATH` registry.
+async fn get_bridge_transfers() -> HashMap<String, BridgeTransfer> {
+    let mut mongodb_client_label = MongoDbClientLabel::new().await;
+    let mut bridge_transfers = mongodb_client_label
+        .get_labels_by_category(LabelCategory::Bridge)
+        .await
+        .into_iter()
+        .map(|p| {
+            let bridge_transfer = BridgeTransfer {
+                bridge: p.label,
+                chain: None,
+                counterparty: None,
+            };
+            (p.address, bridge_transfer)
+        })
+        .collect::<HashMap<_, _>>();
+
+    for (address, bridge) in load_bridges_from_env().unwrap_or_default() {
+        bridge_transfers.insert(address, bridge.get_bridge_transfer());
+    }
+
+    bridge_transfers
+}
+
+pub fn set_transfer_types(
+    subscan_operations: &mut [SubscanOperation],
+    exchange_addresses: &HashSet<String>,
+) {
+    for s in subscan_operations
+        .iter_mut()
+        .filter(|p| p.operation_type == OperationType::Transfer)
+    {
+        s.operation_type = OperationType::from_transfer_sides(
+            exchange_addresses.contains(&s.from_wallet),
+            exchange_addresses.contains(&s.to_wallet),
+        );
+    }
+}
+
+/// Plain transfers to a bridge leave the chain, transfers from it arrive from another one.
+pub fn set_bridge_transfers(
+    subscan_operations: &mut [SubscanOperation],
+    bridge_transfers: &HashMap<String, BridgeTransfer>,
+) {
+    for s in subscan_operations
+        .iter_mut()
+        .filter(|p| p.operation_type == OperationType::Transfer)
+    {
+        if let Some(bridge_transfer) = bridge_transfers.get(&s.to_wallet) {
+            s.operation_type = OperationType::BridgeOut;
+            s.bridge = Some(bridge_transfer.clone());
+        } else if let Some(bridge_transfer) = bridge_transfers.get(&s.from_wallet) {
+            s.operation_type = OperationType::BridgeIn;
+            s.bridge = Some(bridge_transfer.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_transfer_parser::{set_bridge_transfers, set_transfer_types},
+        BridgeTransfer, OperationType, SubscanOperation,
+    };
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn set_transfer_types_works() {
+        let exchange_addresses = HashSet::from(["5Kucoin".to_string(), "5Mexc".to_string()]);
+        let mut subscan_operations = [
+            ("5User", "5Kucoin"),
+            ("5Kucoin", "5User"),
+            ("5Kucoin", "5Mexc"),
+            ("5User", "5Friend"),
+            ("5User", "5Most"),
+            ("5Most", "5Kucoin"),
+        ]
+        .map(|(from, to)| SubscanOperation {
+            operation_quantity: 1_000.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: from.to_string(),
+            to_wallet: to.to_string(),
+            ..Default::default()
+        });
+
+        let bridge_transfer = BridgeTransfer {
+            bridge: "Most".to_string(),
+            chain: Some("Ethereum".to_string()),
+            counterparty: None,
+        };
+        let bridge_transfers = HashMap::from([("5Most".to_string(), bridge_transfer.clone())]);
+
+        // exchange deposits from a bridge stay deposits
+        set_transfer_types(&mut subscan_operations, &exchange_addresses);
+        set_bridge_transfers(&mut subscan_operations, &bridge_transfers);
+        assert_eq!(subscan_operations[4].bridge, Some(bridge_transfer));
+        assert_eq!(
+            subscan_operations.map(|p| p.operation_type),
+            [
+                OperationType::DepositToExchange,
+                OperationType::WithdrawFromExchange,
+                OperationType::ExchangeToExchange,
+                OperationType::Transfer,
+                OperationType::BridgeOut,
+                OperationType::DepositToExchange,
+            ]
+        );
+    }
+}
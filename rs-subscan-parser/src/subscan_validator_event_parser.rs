@@ -0,0 +1,279 @@
+use crate::{
+    mongodb_client_nomination::MongoDbClientNomination,
+    mongodb_client_validator::MongoDbClientValidator,
+    mongodb_client_validator_event::MongoDbClientValidatorEvent,
+    subscan_call_decoder::get_account_id,
+    subscan_cursor::{parse_pages_since_cursor, CURSOR_PAGE_SIZE},
+    subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR, EMPTY_ADDRESS},
+    Nomination, SubscanCursor, SubscanEventRecord, ValidatorEvent, ValidatorEventType,
+};
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde_json::{json, Value};
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use strum::IntoEnumIterator;
+
+pub async fn parse_validator_events(
+    head_block_number: u64,
+) -> Option<(Vec<ValidatorEvent>, Vec<SubscanCursor>)> {
+    let mut tasks = FuturesUnordered::new();
+    for event_type in ValidatorEventType::iter() {
+        tasks.push(tokio::spawn(async move {
+            let s = parse_pages_since_cursor(
+                &event_type.get_stream_name(),
+                head_block_number,
+                |page, block_range| {
+                    let event_type = event_type.clone();
+                    async move {
+                        let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+                        let (s, rows_len) = subscan_parser
+                            .parse_subscan_event_records(
+                                event_type.get_module_id(),
+                                event_type.get_event_id(),
+                                page,
+                                CURSOR_PAGE_SIZE,
+                                Some(block_range),
+                            )
+                            .await?;
+                        Some((s, Vec::new(), rows_len))
+                    }
+                },
+            )
+            .await;
+            (event_type, s)
+        }));
+    }
+
+    let ss58_prefix = Network::from_env().get_ss58_prefix();
+    let mut validator_events = Vec::new();
+    let mut cursors = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok((event_type, Some((s, _, cursor)))) = res else {
+            continue;
+        };
+
+        for d in s {
+            let mut s = convert_validator_events(&event_type, d, ss58_prefix).unwrap_or_default();
+            validator_events.append(&mut s);
+        }
+        cursors.push(cursor);
+    }
+
+    // chilled stashes stop nominating, enrichment then drops the chilled nominators
+    let chills = validator_events
+        .iter()
+        .filter(|p| p.event_type == ValidatorEventType::Chilled)
+        .map(|p| Nomination {
+            nominator: p.validator.clone(),
+            targets: Vec::new(),
+            extrinsic_index: p.extrinsic_index.clone(),
+            from_block: p.block_number,
+            to_block: None,
+            network: p.network.clone(),
+        })
+        .collect();
+    let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+    mongodb_client_nomination.import_nominations(chills).await;
+
+    let validator_events = enrich_validator_events(validator_events).await?;
+
+    Some((validator_events, cursors))
+}
+
+/// Attributes slashed nominators to their validator and adds the total stake of every
+/// affected validator. Chilled nominators are dropped, only validators are watched.
+pub async fn enrich_validator_events(
+    validator_events: Vec<ValidatorEvent>,
+) -> Option<Vec<ValidatorEvent>> {
+    // skipping already existing records
+    let mut mongodb_client_validator_event = MongoDbClientValidatorEvent::new().await;
+    let validator_events = mongodb_client_validator_event
+        .get_not_existing_validator_events(validator_events)
+        .await;
+
+    let mut tasks = FuturesUnordered::new();
+    for s in validator_events {
+        tasks.push(tokio::spawn(async move {
+            let mut validator = s.validator.clone();
+            if s.event_type == ValidatorEventType::Slashed {
+                let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+                if let Some(v) = mongodb_client_validator
+                    .get_validator_by_nominator(&s.affected_wallet)
+                    .await
+                {
+                    validator = v.validator;
+                }
+            }
+
+            let mut subscan_parser = SubscanParser::new(s.network.clone()).await;
+            let total_stake = subscan_parser
+                .parse_subscan_validator_total_stake(&validator)
+                .await;
+            if s.event_type == ValidatorEventType::Chilled && total_stake.is_none() {
+                return None;
+            }
+
+            Some(ValidatorEvent {
+                validator,
+                total_stake: total_stake.unwrap_or_default(),
+                ..s
+            })
+        }));
+    }
+
+    let mut enriched_validator_events = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some(s)) = res else {
+            continue;
+        };
+        enriched_validator_events.push(s);
+    }
+
+    Some(enriched_validator_events)
+}
+
+/// One event per affected validator, a ban event can list several of them.
+fn convert_validator_events(
+    event_type: &ValidatorEventType,
+    subscan_event_record: SubscanEventRecord,
+    ss58_prefix: u16,
+) -> Option<Vec<ValidatorEvent>> {
+    let get_address = |value: &Value| {
+        Some(
+            get_account_id(value)?
+                .to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)),
+        )
+    };
+    let get_param_address = |name: &str, position: usize| {
+        get_address(&json!(subscan_event_record.get_param(name, position)?))
+    };
+
+    let validator_event = ValidatorEvent {
+        event_index: subscan_event_record.event.event_index.clone(),
+        extrinsic_index: subscan_event_record.extrinsic_index.clone(),
+        block_number: subscan_event_record.block_number,
+        event_timestamp: subscan_event_record.event_timestamp,
+        event_type: event_type.clone(),
+        validator: EMPTY_ADDRESS.to_string(),
+        affected_wallet: EMPTY_ADDRESS.to_string(),
+        amount: 0.0,
+        total_stake: 0.0,
+        reason: None,
+        network: Network::from_env(),
+    };
+
+    match event_type {
+        // the staker is resolved to its validator during enrichment
+        ValidatorEventType::Slashed => {
+            let staker = get_param_address("staker", 0)?;
+            let amount = subscan_event_record.get_param("amount", 1)?;
+            Some(vec![ValidatorEvent {
+                validator: staker.clone(),
+                affected_wallet: staker,
+                amount: amount.parse::<f64>().ok()? / AZERO_DENOMINATOR,
+                ..validator_event
+            }])
+        }
+        ValidatorEventType::Kicked => Some(vec![ValidatorEvent {
+            validator: get_param_address("stash", 1)?,
+            affected_wallet: get_param_address("nominator", 0)?,
+            ..validator_event
+        }]),
+        ValidatorEventType::Chilled => Some(vec![ValidatorEvent {
+            validator: get_param_address("stash", 0)?,
+            ..validator_event
+        }]),
+        // aleph elections pallet: BanValidators(Vec<(AccountId, BanInfo)>)
+        ValidatorEventType::Banned => {
+            let banned = subscan_event_record.get_param("banned_validators", 0)?;
+            let banned: Value = serde_json::from_str(&banned).ok()?;
+            banned
+                .as_array()?
+                .iter()
+                .map(|p| {
+                    Some(ValidatorEvent {
+                        validator: get_address(p.get(0)?)?,
+                        reason: p.get(1).and_then(|p| p.get("reason")).map(get_ban_reason),
+                        ..validator_event.clone()
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// Ban reasons are enum variants, either plain or with data like `{"InsufficientUptime": 3}`.
+fn get_ban_reason(reason: &Value) -> String {
+    match reason {
+        Value::String(s) => s.clone(),
+        Value::Object(o) => o.keys().next().cloned().unwrap_or_default(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_validator_event_parser::convert_validator_events, SubscanEvent, SubscanEventParam,
+        SubscanEventRecord, ValidatorEventType,
+    };
+    use bson::DateTime;
+    use serde_json::json;
+    use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+
+    fn get_address(byte: u8) -> String {
+        AccountId32::from([byte; 32]).to_ss58check_with_version(Ss58AddressFormat::custom(42))
+    }
+
+    fn get_hex(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 32]))
+    }
+
+    fn get_record(params: Vec<(&str, String)>) -> SubscanEventRecord {
+        SubscanEventRecord {
+            block_number: 100,
+            extrinsic_index: "100-0".to_string(),
+            event_timestamp: DateTime::now(),
+            event: SubscanEvent {
+                module_id: "staking".to_string(),
+                event_id: String::new(),
+                event_index: "100-3".to_string(),
+                event_params: params
+                    .into_iter()
+                    .map(|(name, value)| SubscanEventParam {
+                        type_name: String::new(),
+                        value,
+                        name: name.to_string(),
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn convert_validator_events_works() {
+        let slashed = get_record(vec![
+            ("staker", get_hex(1)),
+            ("amount", "2500000000000000".to_string()),
+        ]);
+        let slashed = convert_validator_events(&ValidatorEventType::Slashed, slashed, 42).unwrap();
+        assert_eq!(slashed.len(), 1);
+        assert_eq!(slashed[0].affected_wallet, get_address(1));
+        assert_eq!(slashed[0].amount, 2_500.0);
+
+        let kicked = get_record(vec![("nominator", get_hex(2)), ("stash", get_hex(3))]);
+        let kicked = convert_validator_events(&ValidatorEventType::Kicked, kicked, 42).unwrap();
+        assert_eq!(kicked[0].validator, get_address(3));
+        assert_eq!(kicked[0].affected_wallet, get_address(2));
+
+        let banned = json!([
+            [get_hex(4), {"reason": {"InsufficientUptime": 3}, "start": 611}],
+            [get_hex(5), {"reason": "OtherReason", "start": 611}],
+        ]);
+        let banned = get_record(vec![("banned_validators", banned.to_string())]);
+        let banned = convert_validator_events(&ValidatorEventType::Banned, banned, 42).unwrap();
+        assert_eq!(banned.len(), 2);
+        assert_eq!(banned[0].validator, get_address(4));
+        assert_eq!(banned[0].reason.as_deref(), Some("InsufficientUptime"));
+        assert_eq!(banned[1].reason.as_deref(), Some("OtherReason"));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    mongodb_client_validator_info::MongoDbClientValidatorInfo,
+    subscan_identity_parser::convert_account_display,
+    subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR},
+    substrate_rpc_client::SubstrateRpcClient,
+    ValidatorInfo, ValidatorStatus,
+};
+use bson::DateTime;
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde_json::Value;
+
+pub static VALIDATOR_REFRESH_INTERVAL_SECS: u64 = 3_600;
+static PERBILL_PERCENT: f64 = 1e7;
+
+/// Refreshes the registry from the active and waiting lists and marks validators missing
+/// from both inactive. Returns the number of validators updated.
+pub async fn refresh_validator_infos() -> Option<usize> {
+    let mut substrate_rpc_client = SubstrateRpcClient::new().await;
+    let era = substrate_rpc_client.get_active_era().await?;
+
+    let mut tasks = FuturesUnordered::new();
+    for status in [ValidatorStatus::Active, ValidatorStatus::Waiting] {
+        tasks.push(tokio::spawn(async move {
+            let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+            subscan_parser.parse_subscan_validators(status, era).await
+        }));
+    }
+
+    let mut validator_infos = Vec::new();
+    while let Some(res) = tasks.next().await {
+        let Ok(Some(mut s)) = res else {
+            // with one list missing its validators would be marked inactive
+            return None;
+        };
+        validator_infos.append(&mut s);
+    }
+
+    let validator_infos_len = validator_infos.len();
+    let validators = validator_infos
+        .iter()
+        .map(|p| p.validator.clone())
+        .collect();
+    let mut mongodb_client_validator_info = MongoDbClientValidatorInfo::new().await;
+    mongodb_client_validator_info
+        .import_or_update_validator_infos(validator_infos)
+        .await;
+    mongodb_client_validator_info
+        .mark_missing_validator_infos_inactive(validators)
+        .await;
+
+    Some(validator_infos_len)
+}
+
+/// Entry of `staking/validators` or `staking/waiting`. Commission comes as perbill and
+/// stakes in plancks.
+pub fn convert_validator_info(
+    value: &Value,
+    status: &ValidatorStatus,
+    era: u32,
+    network: &Network,
+) -> Option<ValidatorInfo> {
+    let account_display = value.get("stash_account_display")?;
+    let validator = account_display.get("address")?.as_str()?.to_string();
+    let identity = convert_account_display(&validator, account_display).map(|p| p.identity);
+
+    let self_stake = get_amount(value.get("bonded_owner")?)?;
+    let total_stake = match value.get("bonded_total").and_then(get_amount) {
+        Some(total_stake) => total_stake,
+        None => self_stake + get_amount(value.get("bonded_nominators")?)?,
+    };
+
+    let active_eras = match status {
+        ValidatorStatus::Active => vec![era],
+        ValidatorStatus::Waiting | ValidatorStatus::Inactive => Vec::new(),
+    };
+
+    Some(ValidatorInfo {
+        validator,
+        identity,
+        commission: get_number(value.get("validator_prefs_value")?)? / PERBILL_PERCENT,
+        self_stake,
+        total_stake,
+        nominators: get_number(value.get("count_nominators")?)? as u32,
+        status: status.clone(),
+        era,
+        active_eras,
+        updated_timestamp: DateTime::now(),
+        network: network.clone(),
+    })
+}
+
+fn get_amount(value: &Value) -> Option<f64> {
+    Some(get_number(value)? / AZERO_DENOMINATOR)
+}
+
+fn get_number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_parser::Network, subscan_validator_parser::convert_validator_info, ValidatorStatus,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn convert_validator_info_works() {
+        let active = json!({
+            "stash_account_display": {"address": "5Validator", "display": "Validator X", "identity": true},
+            "validator_prefs_value": 50000000,
+            "bonded_owner": "25000000000000000",
+            "bonded_nominators": "11975000000000000000",
+            "bonded_total": "12000000000000000000",
+            "count_nominators": 512,
+        });
+        let validator_info =
+            convert_validator_info(&active, &ValidatorStatus::Active, 611, &Network::Alephzero)
+                .unwrap();
+        assert_eq!(validator_info.identity.as_deref(), Some("Validator X"));
+        assert_eq!(validator_info.commission, 5.0);
+        assert_eq!(validator_info.self_stake, 25_000.0);
+        assert_eq!(validator_info.total_stake, 12_000_000.0);
+        assert_eq!(validator_info.nominators, 512);
+        assert_eq!(validator_info.active_eras, vec![611]);
+
+        // waiting validators may come without a total and without an identity
+        let waiting = json!({
+            "stash_account_display": {"address": "5Waiting", "display": ""},
+            "validator_prefs_value": "100000000",
+            "bonded_owner": "1000000000000000",
+            "bonded_nominators": "500000000000000",
+            "count_nominators": "3",
+        });
+        let validator_info = convert_validator_info(
+            &waiting,
+            &ValidatorStatus::Waiting,
+            611,
+            &Network::Alephzero,
+        )
+        .unwrap();
+        assert_eq!(validator_info.identity, None);
+        assert_eq!(validator_info.commission, 10.0);
+        assert_eq!(validator_info.total_stake, 1_500.0);
+        assert!(validator_info.active_eras.is_empty());
+    }
+}
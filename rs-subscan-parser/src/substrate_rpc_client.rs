@@ -0,0 +1,229 @@
+use log::error;
+use parity_scale_codec::{Compact, Decode};
+use reqwest::header::{HeaderMap, HeaderValue};
+use rs_utils::clients::http_client::HttpClient;
+use serde_json::{json, Value};
+use sp_core::{
+    crypto::AccountId32,
+    hashing::{blake2_128, twox_128, twox_64},
+};
+use std::env;
+
+#[derive(Clone, Debug)]
+pub struct SubstrateRpcClient {
+    http_client: HttpClient,
+    rpc_url: String,
+}
+
+impl SubstrateRpcClient {
+    pub async fn new() -> Self {
+        let rpc_url = env::var("SUBSTRATE_RPC_URL").unwrap();
+        let http_client = HttpClient::new("substrate_rpc_client").await;
+        SubstrateRpcClient {
+            http_client,
+            rpc_url,
+        }
+    }
+
+    pub async fn get_finalized_head_number(&mut self) -> Option<u64> {
+        let hash = self
+            .rpc_request("chain_getFinalizedHead", json!([]))
+            .await?;
+        let header = self.rpc_request("chain_getHeader", json!([hash])).await?;
+
+        parse_hex_number(header.get("number")?.as_str()?)
+    }
+
+    pub async fn get_finalized_head_hash(&mut self) -> Option<String> {
+        let hash = self
+            .rpc_request("chain_getFinalizedHead", json!([]))
+            .await?;
+
+        Some(hash.as_str()?.to_string())
+    }
+
+    pub async fn get_block_hash(&mut self, block_number: u64) -> Option<String> {
+        let hash = self
+            .rpc_request("chain_getBlockHash", json!([block_number]))
+            .await?;
+
+        Some(hash.as_str()?.to_string())
+    }
+
+    /// Returns raw SCALE encoded extrinsics of the block in their on-chain order.
+    pub async fn get_block_extrinsics(&mut self, block_hash: &str) -> Option<Vec<Vec<u8>>> {
+        let block = self
+            .rpc_request("chain_getBlock", json!([block_hash]))
+            .await?;
+
+        block
+            .get("block")?
+            .get("extrinsics")?
+            .as_array()?
+            .iter()
+            .map(|p| decode_hex(p.as_str()?))
+            .collect()
+    }
+
+    /// Returns raw SCALE encoded `System.Events` storage of the block.
+    pub async fn get_block_events(&mut self, block_hash: &str) -> Option<Vec<u8>> {
+        let storage_key = format!(
+            "0x{}{}",
+            hex::encode(twox_128(b"System")),
+            hex::encode(twox_128(b"Events"))
+        );
+        let events = self
+            .rpc_request("state_getStorage", json!([storage_key, block_hash]))
+            .await?;
+
+        decode_hex(events.as_str()?)
+    }
+
+    /// Index of the era currently rewarded, `Staking.ActiveEra` at the best block.
+    pub async fn get_active_era(&mut self) -> Option<u32> {
+        let storage_key = format!(
+            "0x{}{}",
+            hex::encode(twox_128(b"Staking")),
+            hex::encode(twox_128(b"ActiveEra"))
+        );
+        let active_era = self
+            .rpc_request("state_getStorage", json!([storage_key]))
+            .await?;
+
+        // ActiveEraInfo { index: u32, start: Option<u64> }
+        let active_era = decode_hex(active_era.as_str()?)?;
+        Some(u32::from_le_bytes(active_era.get(..4)?.try_into().ok()?))
+    }
+
+    /// Unlocking chunks of the stash ledger as `(planck, era)` at the best block, empty
+    /// when the stash is not bonded anymore.
+    pub async fn get_unlocking_chunks(&mut self, stash: &AccountId32) -> Option<Vec<(u128, u32)>> {
+        let stash: &[u8] = stash.as_ref();
+        let storage_key = format!(
+            "0x{}{}{}{}",
+            hex::encode(twox_128(b"Staking")),
+            hex::encode(twox_128(b"Bonded")),
+            hex::encode(twox_64(stash)),
+            hex::encode(stash)
+        );
+        let controller = self
+            .rpc_request_nullable("state_getStorage", json!([storage_key]))
+            .await?;
+        let Some(controller) = controller.as_str() else {
+            return Some(Vec::new());
+        };
+
+        let controller = decode_hex(controller)?;
+        let storage_key = format!(
+            "0x{}{}{}{}",
+            hex::encode(twox_128(b"Staking")),
+            hex::encode(twox_128(b"Ledger")),
+            hex::encode(blake2_128(&controller)),
+            hex::encode(&controller)
+        );
+        let ledger = self
+            .rpc_request_nullable("state_getStorage", json!([storage_key]))
+            .await?;
+        let Some(ledger) = ledger.as_str() else {
+            return Some(Vec::new());
+        };
+
+        decode_unlocking_chunks(&decode_hex(ledger)?)
+    }
+
+    pub async fn get_spec_version(&mut self, block_hash: &str) -> Option<u32> {
+        let runtime_version = self
+            .rpc_request("state_getRuntimeVersion", json!([block_hash]))
+            .await?;
+
+        Some(runtime_version.get("specVersion")?.as_u64()? as u32)
+    }
+
+    pub async fn get_metadata(&mut self, block_hash: &str) -> Option<Vec<u8>> {
+        let metadata = self
+            .rpc_request("state_getMetadata", json!([block_hash]))
+            .await?;
+
+        decode_hex(metadata.as_str()?)
+    }
+
+    async fn rpc_request(&mut self, method: &str, params: Value) -> Option<Value> {
+        let result = self.rpc_request_nullable(method, params).await?;
+        if result.is_null() {
+            return None;
+        }
+
+        Some(result)
+    }
+
+    /// `Value::Null` is a valid result here, e.g. for storage that is not set.
+    async fn rpc_request_nullable(&mut self, method: &str, params: Value) -> Option<Value> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+
+        let payload = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let resp: Value = self
+            .http_client
+            .post_request(&self.rpc_url, headers, payload)
+            .await;
+
+        if let Some(e) = resp.get("error") {
+            error!(target: "substrate_rpc_client", "{method} failed: {e}");
+            return None;
+        }
+
+        Some(resp.get("result")?.clone())
+    }
+}
+
+/// `StakingLedger { stash, total, active, unlocking, .. }`, the fields after `unlocking`
+/// changed between runtimes and are not read.
+fn decode_unlocking_chunks(ledger: &[u8]) -> Option<Vec<(u128, u32)>> {
+    let input = &mut &ledger[..];
+    <[u8; 32]>::decode(input).ok()?;
+    Compact::<u128>::decode(input).ok()?;
+    Compact::<u128>::decode(input).ok()?;
+
+    let unlocking_len = Compact::<u32>::decode(input).ok()?.0;
+    (0..unlocking_len)
+        .map(|_| {
+            let value = Compact::<u128>::decode(input).ok()?.0;
+            let era = Compact::<u32>::decode(input).ok()?.0;
+            Some((value, era))
+        })
+        .collect()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.strip_prefix("0x")?).ok()
+}
+
+pub fn parse_hex_number(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::substrate_rpc_client::decode_unlocking_chunks;
+    use parity_scale_codec::{Compact, Encode};
+
+    #[test]
+    fn decode_unlocking_chunks_works() {
+        let mut ledger = [7u8; 32].to_vec();
+        ledger.extend(Compact(3_000u128).encode());
+        ledger.extend(Compact(1_000u128).encode());
+        ledger.extend(Compact(2u32).encode());
+        ledger.extend(Compact(1_500u128).encode());
+        ledger.extend(Compact(120u32).encode());
+        ledger.extend(Compact(500u128).encode());
+        ledger.extend(Compact(121u32).encode());
+        // claimed rewards
+        ledger.extend(vec![118u32, 119u32].encode());
+
+        assert_eq!(
+            decode_unlocking_chunks(&ledger),
+            Some(vec![(1_500, 120), (500, 121)])
+        );
+        assert_eq!(decode_unlocking_chunks(&ledger[..40]), None);
+    }
+}
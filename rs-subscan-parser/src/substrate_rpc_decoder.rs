@@ -0,0 +1,1203 @@
+use crate::{
+    subscan_call_decoder::get_multisig_account,
+    subscan_parser::{Network, AZERO_DENOMINATOR, EMPTY_ADDRESS},
+    ExtrinsicsType, Module, Nomination, OperationType, SubscanOperation,
+};
+use bson::DateTime;
+use frame_metadata::{v14::StorageEntryType, RuntimeMetadata, RuntimeMetadataPrefixed};
+use log::error;
+use parity_scale_codec::{Compact, Decode};
+use scale_value::{
+    scale::{decode_as_type, PortableRegistry, TypeId},
+    At, Composite, Value, ValueDef,
+};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use std::str::FromStr;
+
+static SIGNED_EXTRINSIC_MASK: u8 = 0b1000_0000;
+static SUPPORTED_EXTRINSIC_VERSION: u8 = 4;
+static STAKING_AMOUNT_EVENTS: [&str; 3] = ["Bonded", "Unbonded", "Withdrawn"];
+static POOL_AMOUNT_FIELDS: [&str; 3] = ["bonded", "balance", "payout"];
+static MAX_CALL_DEPTH: usize = 8;
+
+#[derive(Clone, Debug)]
+pub struct SubstrateCall {
+    pub pallet: String,
+    pub name: String,
+    pub fields: Composite<TypeId>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubstrateExtrinsic {
+    pub signer: Option<AccountId32>,
+    pub call: SubstrateCall,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubstrateEvent {
+    pub extrinsic_position: Option<u32>,
+    pub pallet: String,
+    pub name: String,
+    pub fields: Composite<TypeId>,
+}
+
+/// `Contracts.ContractEmitted` event with the raw event data of the contract.
+#[derive(Clone, Debug)]
+pub struct ContractEvent {
+    pub block_number: u64,
+    pub event_index: String,
+    pub extrinsic_index: Option<String>,
+    pub event_timestamp: DateTime,
+    pub contract: String,
+    pub data: Vec<u8>,
+}
+
+/// Type information of a single runtime version, enough to decode its extrinsics and events.
+#[derive(Clone, Debug)]
+pub struct SubstrateRuntime {
+    network: Network,
+    spec_version: u32,
+    registry: PortableRegistry,
+    address_ty: TypeId,
+    signature_ty: TypeId,
+    call_ty: TypeId,
+    extension_tys: Vec<TypeId>,
+    events_ty: TypeId,
+}
+
+impl SubstrateRuntime {
+    pub fn from_metadata(network: Network, spec_version: u32, metadata: &[u8]) -> Option<Self> {
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..]).ok()?;
+        let RuntimeMetadata::V14(metadata) = metadata.1 else {
+            return None;
+        };
+
+        // extrinsic type is UncheckedExtrinsic<Address, Call, Signature, Extra>
+        let extrinsic_ty = metadata.types.resolve(metadata.extrinsic.ty.id)?;
+        let get_type_param = |name: &str| {
+            extrinsic_ty
+                .type_params
+                .iter()
+                .find(|p| p.name == name)?
+                .ty
+                .map(|p| p.id)
+        };
+
+        let events_ty = metadata
+            .pallets
+            .iter()
+            .find(|p| p.name == "System")?
+            .storage
+            .as_ref()?
+            .entries
+            .iter()
+            .find(|p| p.name == "Events")
+            .and_then(|p| match &p.ty {
+                StorageEntryType::Plain(ty) => Some(ty.id),
+                _ => None,
+            })?;
+
+        Some(SubstrateRuntime {
+            network,
+            spec_version,
+            address_ty: get_type_param("Address")?,
+            signature_ty: get_type_param("Signature")?,
+            call_ty: get_type_param("Call")?,
+            extension_tys: metadata
+                .extrinsic
+                .signed_extensions
+                .iter()
+                .map(|p| p.ty.id)
+                .collect(),
+            events_ty,
+            registry: metadata.types,
+        })
+    }
+
+    /// Value of a `u32` pallet constant, such as `Staking.BondingDuration`.
+    pub fn decode_constant_u32(metadata: &[u8], pallet: &str, name: &str) -> Option<u32> {
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..]).ok()?;
+        let RuntimeMetadata::V14(metadata) = metadata.1 else {
+            return None;
+        };
+
+        let constant = metadata
+            .pallets
+            .iter()
+            .find(|p| p.name == pallet)?
+            .constants
+            .iter()
+            .find(|p| p.name == name)?;
+        u32::decode(&mut &constant.value[..]).ok()
+    }
+
+    pub fn get_spec_version(&self) -> u32 {
+        self.spec_version
+    }
+
+    pub fn decode_extrinsic(&self, extrinsic: &[u8]) -> Option<SubstrateExtrinsic> {
+        let mut data = extrinsic;
+        Compact::<u32>::decode(&mut data).ok()?;
+
+        let version = u8::decode(&mut data).ok()?;
+        if version & !SIGNED_EXTRINSIC_MASK != SUPPORTED_EXTRINSIC_VERSION {
+            return None;
+        }
+
+        let signer = if version & SIGNED_EXTRINSIC_MASK != 0 {
+            let address = decode_as_type(&mut data, self.address_ty, &self.registry).ok()?;
+            decode_as_type(&mut data, self.signature_ty, &self.registry).ok()?;
+            for extension_ty in self.extension_tys.iter() {
+                decode_as_type(&mut data, *extension_ty, &self.registry).ok()?;
+            }
+            Some(get_account_id(&address)?)
+        } else {
+            None
+        };
+
+        let call = decode_as_type(&mut data, self.call_ty, &self.registry).ok()?;
+        Some(SubstrateExtrinsic {
+            signer,
+            call: get_call(&call)?,
+        })
+    }
+
+    /// Extrinsics of the block in their on-chain order. An undecodable extrinsic is logged and
+    /// left as `None` so the positions of the rest still match their events.
+    pub fn decode_extrinsics(
+        &self,
+        block_number: u64,
+        extrinsics: &[Vec<u8>],
+    ) -> Vec<Option<SubstrateExtrinsic>> {
+        extrinsics
+            .iter()
+            .enumerate()
+            .map(|(position, p)| {
+                let extrinsic = self.decode_extrinsic(p);
+                if extrinsic.is_none() {
+                    error!(
+                        target: "substrate_rpc_decoder", "Skipping undecodable extrinsic {block_number}-{position}",
+                    );
+                }
+                extrinsic
+            })
+            .collect()
+    }
+
+    pub fn decode_events(&self, events: &[u8]) -> Option<Vec<SubstrateEvent>> {
+        let records = decode_as_type(&mut &events[..], self.events_ty, &self.registry).ok()?;
+        let ValueDef::Composite(records) = records.value else {
+            return None;
+        };
+
+        records
+            .values()
+            .map(|record| {
+                let ValueDef::Variant(phase) = &record.at("phase")?.value else {
+                    return None;
+                };
+                let extrinsic_position = if phase.name == "ApplyExtrinsic" {
+                    Some(get_u128(phase.values.at(0)?)? as u32)
+                } else {
+                    None
+                };
+
+                let event = get_call(record.at("event")?)?;
+                Some(SubstrateEvent {
+                    extrinsic_position,
+                    pallet: event.pallet,
+                    name: event.name,
+                    fields: event.fields,
+                })
+            })
+            .collect()
+    }
+
+    /// Stashes chilled in the block, as nominations without targets.
+    pub fn decode_chills(&self, block_number: u64, events: &[u8]) -> Option<Vec<Nomination>> {
+        let ss58_prefix = self.network.get_ss58_prefix();
+        let chills = self
+            .decode_events(events)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, p)| p.pallet == "Staking" && p.name == "Chilled")
+            .filter_map(|(position, p)| {
+                let extrinsic_position = p.extrinsic_position.unwrap_or(position as u32);
+                Some(Nomination {
+                    nominator: get_account(p.fields.at("stash")?, ss58_prefix)?,
+                    targets: Vec::new(),
+                    extrinsic_index: format!("{block_number}-{extrinsic_position}"),
+                    from_block: block_number,
+                    to_block: None,
+                    network: self.network.clone(),
+                })
+            })
+            .collect();
+
+        Some(chills)
+    }
+
+    /// Events emitted by contracts in the block, in their on-chain order.
+    pub fn decode_contract_events(
+        &self,
+        block_number: u64,
+        extrinsics: &[Vec<u8>],
+        events: &[u8],
+    ) -> Option<Vec<ContractEvent>> {
+        let extrinsics = self.decode_extrinsics(block_number, extrinsics);
+        let event_timestamp = DateTime::from_millis(get_block_timestamp(&extrinsics)? as i64);
+
+        let ss58_prefix = self.network.get_ss58_prefix();
+        let contract_events = self
+            .decode_events(events)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, p)| p.pallet == "Contracts" && p.name == "ContractEmitted")
+            .filter_map(|(position, p)| {
+                Some(ContractEvent {
+                    block_number,
+                    event_index: format!("{block_number}-{position}"),
+                    extrinsic_index: p.extrinsic_position.map(|e| format!("{block_number}-{e}")),
+                    event_timestamp,
+                    contract: get_account(p.fields.at("contract")?, ss58_prefix)?,
+                    data: get_bytes(p.fields.at("data")?)?,
+                })
+            })
+            .collect();
+
+        Some(contract_events)
+    }
+
+    /// Converts a block into the same operations the subscan streams produce: one transfer per
+    /// `Balances.Transfer` event and one staking operation per extrinsic touching `Staking`,
+    /// batched or not. Failed extrinsics are skipped.
+    pub fn decode_block(
+        &self,
+        block_number: u64,
+        extrinsics: &[Vec<u8>],
+        events: &[u8],
+    ) -> Option<Vec<SubscanOperation>> {
+        let extrinsics = self.decode_extrinsics(block_number, extrinsics);
+        let events = self.decode_events(events)?;
+
+        let operation_timestamp = DateTime::from_millis(get_block_timestamp(&extrinsics)? as i64);
+
+        let ss58_prefix = self.network.get_ss58_prefix();
+        let mut subscan_operations = Vec::new();
+        for (position, extrinsic) in extrinsics.iter().enumerate() {
+            let extrinsic_events = events
+                .iter()
+                .filter(|p| p.extrinsic_position == Some(position as u32))
+                .collect::<Vec<_>>();
+            if !extrinsic_events
+                .iter()
+                .any(|p| p.pallet == "System" && p.name == "ExtrinsicSuccess")
+            {
+                continue;
+            }
+
+            let subscan_operation = SubscanOperation {
+                hash: String::new(),
+                block_number,
+                extrinsic_index: format!("{block_number}-{position}"),
+                operation_timestamp,
+                operation_quantity: 0.0,
+                operation_usd: 0.0,
+                operation_type: OperationType::Transfer,
+                from_wallet: EMPTY_ADDRESS.to_string(),
+                controller_wallet: EMPTY_ADDRESS.to_string(),
+                to_wallet: EMPTY_ADDRESS.to_string(),
+                pool_id: None,
+                pool_name: None,
+                targets: Vec::new(),
+                bridge: None,
+                network: self.network.clone(),
+            };
+
+            let mut transfers = extrinsic_events
+                .iter()
+                .filter(|p| p.pallet == "Balances" && p.name == "Transfer")
+                .filter_map(|p| {
+                    Some(SubscanOperation {
+                        operation_quantity: get_u128(p.fields.at("amount")?)? as f64
+                            / AZERO_DENOMINATOR,
+                        from_wallet: get_account(p.fields.at("from")?, ss58_prefix)?,
+                        to_wallet: get_account(p.fields.at("to")?, ss58_prefix)?,
+                        ..subscan_operation.clone()
+                    })
+                })
+                .collect::<Vec<_>>();
+            subscan_operations.append(&mut transfers);
+
+            // transfers come from events alone, everything else needs the call
+            let Some(extrinsic) = extrinsic else {
+                continue;
+            };
+
+            if let Some(s) =
+                convert_pool_operation(extrinsic, &extrinsic_events, subscan_operation.clone())
+            {
+                subscan_operations.push(s);
+            }
+
+            if let Some(s) =
+                convert_staking_operation(extrinsic, &extrinsic_events, subscan_operation)
+            {
+                subscan_operations.push(s);
+            }
+        }
+
+        Some(subscan_operations)
+    }
+}
+
+/// Mirrors `parse_subscan_wrapped_calls`: amounts are summed, nominate sets the validator
+/// and bond sets the controller.
+fn convert_staking_operation(
+    extrinsic: &SubstrateExtrinsic,
+    events: &[&SubstrateEvent],
+    subscan_operation: SubscanOperation,
+) -> Option<SubscanOperation> {
+    let executed_multisigs = get_executed_multisigs(events);
+    let (origins, staking_calls): (Vec<_>, Vec<_>) = get_nested_calls(
+        &extrinsic.call,
+        extrinsic.signer.clone(),
+        &executed_multisigs,
+        0,
+    )
+    .into_iter()
+    .filter(|(_, p)| p.pallet == "Staking")
+    .unzip();
+    let origin = origins.into_iter().next()?;
+
+    let ss58_prefix = subscan_operation.network.get_ss58_prefix();
+    let staking_events = events
+        .iter()
+        .filter(|p| p.pallet == "Staking" && STAKING_AMOUNT_EVENTS.contains(&p.name.as_str()))
+        .collect::<Vec<_>>();
+    let amount = staking_events
+        .iter()
+        .filter_map(|p| get_u128(p.fields.at("amount")?))
+        .sum::<u128>();
+    let from_wallet = staking_events
+        .iter()
+        .find_map(|p| get_account(p.fields.at("stash")?, ss58_prefix))
+        .or(origin.map(|p| p.to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix))))?;
+
+    let targets = staking_calls
+        .iter()
+        .find(|p| p.name == "nominate")
+        .and_then(|p| match &p.fields.at("targets")?.value {
+            ValueDef::Composite(c) => c
+                .values()
+                .map(|p| get_account(p, ss58_prefix))
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let to_wallet = targets
+        .first()
+        .cloned()
+        .unwrap_or(EMPTY_ADDRESS.to_string());
+    let controller_wallet = staking_calls
+        .iter()
+        .find(|p| p.name == "bond")
+        .and_then(|p| get_account(p.fields.at("controller")?, ss58_prefix))
+        .unwrap_or(EMPTY_ADDRESS.to_string());
+
+    let has_event = |name: &str| staking_events.iter().any(|p| p.name == name);
+    let operation_type = if has_event("Unbonded") {
+        OperationType::RequestUnstake
+    } else if has_event("Withdrawn") {
+        OperationType::WithdrawUnstaked
+    } else if staking_calls.iter().any(|p| p.name == "rebond") {
+        OperationType::Rebond
+    } else if to_wallet != EMPTY_ADDRESS {
+        OperationType::ReStake
+    } else {
+        OperationType::Stake
+    };
+
+    Some(SubscanOperation {
+        operation_quantity: amount as f64 / AZERO_DENOMINATOR,
+        operation_type,
+        from_wallet,
+        controller_wallet,
+        to_wallet,
+        targets,
+        ..subscan_operation
+    })
+}
+
+/// One operation per extrinsic calling `NominationPools`, typed by the first pool call.
+/// Pool name is resolved later during enrichment.
+fn convert_pool_operation(
+    extrinsic: &SubstrateExtrinsic,
+    events: &[&SubstrateEvent],
+    subscan_operation: SubscanOperation,
+) -> Option<SubscanOperation> {
+    let executed_multisigs = get_executed_multisigs(events);
+    let (origin, pool_call) = get_nested_calls(
+        &extrinsic.call,
+        extrinsic.signer.clone(),
+        &executed_multisigs,
+        0,
+    )
+    .into_iter()
+    .find(|(_, p)| p.pallet == "NominationPools")?;
+    let extrinsics_type = ExtrinsicsType::from_str(&pool_call.name).ok()?;
+    let operation_type =
+        OperationType::from_extrinsics_type(&Module::NominationPools, &extrinsics_type);
+
+    let ss58_prefix = subscan_operation.network.get_ss58_prefix();
+    let pool_events = events
+        .iter()
+        .filter(|p| p.pallet == "NominationPools")
+        .collect::<Vec<_>>();
+    let amount = pool_events
+        .iter()
+        .filter_map(|p| {
+            let amount = POOL_AMOUNT_FIELDS.iter().find_map(|f| p.fields.at(*f))?;
+            get_u128(amount)
+        })
+        .sum::<u128>();
+    let from_wallet = pool_events
+        .iter()
+        .find_map(|p| get_account(p.fields.at("member")?, ss58_prefix))
+        .or(origin.map(|p| p.to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix))))?;
+    let pool_id = pool_events
+        .iter()
+        .find_map(|p| get_u128(p.fields.at("pool_id")?))?;
+
+    Some(SubscanOperation {
+        operation_quantity: amount as f64 / AZERO_DENOMINATOR,
+        operation_type,
+        from_wallet,
+        pool_id: Some(pool_id as u32),
+        ..subscan_operation
+    })
+}
+
+/// Flattens utility, proxy and multisig wrappers into their inner calls, each paired with
+/// the account it is dispatched from. Same rules as `decode_nested_calls` for subscan.
+fn get_nested_calls(
+    call: &SubstrateCall,
+    origin: Option<AccountId32>,
+    executed_multisigs: &[AccountId32],
+    depth: usize,
+) -> Vec<(Option<AccountId32>, SubstrateCall)> {
+    if depth > MAX_CALL_DEPTH {
+        return Vec::new();
+    }
+
+    let inner_origin = match (call.pallet.as_str(), call.name.as_str()) {
+        ("Utility", _) => origin,
+        ("Proxy", "proxy" | "proxy_announced") => call.fields.at("real").and_then(get_account_id),
+        ("Multisig", "as_multi" | "as_multi_threshold_1") => {
+            let threshold = call.fields.at("threshold").and_then(get_u128).unwrap_or(1);
+            let signatories = match call.fields.at("other_signatories").map(|p| &p.value) {
+                Some(ValueDef::Composite(c)) => {
+                    c.values().map(get_account_id).collect::<Option<Vec<_>>>()
+                }
+                _ => None,
+            };
+            let multisig = signatories.zip(origin).map(|(mut signatories, origin)| {
+                signatories.push(origin);
+                get_multisig_account(signatories, threshold as u16)
+            });
+
+            // an approval that doesn't reach the threshold dispatches nothing
+            if call.name == "as_multi"
+                && !multisig
+                    .as_ref()
+                    .is_some_and(|p| executed_multisigs.contains(p))
+            {
+                return Vec::new();
+            }
+            multisig
+        }
+        _ => return vec![(origin, call.clone())],
+    };
+
+    let inner_calls = if let Some(calls) = call.fields.at("calls") {
+        match &calls.value {
+            ValueDef::Composite(c) => c.values().filter_map(get_call).collect(),
+            _ => Vec::new(),
+        }
+    } else if let Some(inner_call) = call.fields.at("call") {
+        get_call(inner_call).into_iter().collect()
+    } else {
+        Vec::new()
+    };
+
+    inner_calls
+        .iter()
+        .flat_map(|p| get_nested_calls(p, inner_origin.clone(), executed_multisigs, depth + 1))
+        .collect()
+}
+
+/// Multisigs executed by the extrinsic, from `MultisigExecuted` events with an `Ok` result.
+fn get_executed_multisigs(events: &[&SubstrateEvent]) -> Vec<AccountId32> {
+    events
+        .iter()
+        .filter(|p| p.pallet == "Multisig" && p.name == "MultisigExecuted")
+        .filter_map(|p| {
+            let ValueDef::Variant(result) = &p.fields.at("result")?.value else {
+                return None;
+            };
+            if result.name != "Ok" {
+                return None;
+            }
+            get_account_id(p.fields.at("multisig")?)
+        })
+        .collect()
+}
+
+/// Milliseconds set by the `Timestamp.set` inherent of the block.
+fn get_block_timestamp(extrinsics: &[Option<SubstrateExtrinsic>]) -> Option<u128> {
+    extrinsics.iter().flatten().find_map(|p| {
+        if p.call.pallet != "Timestamp" || p.call.name != "set" {
+            return None;
+        }
+        get_u128(p.call.fields.at("now")?)
+    })
+}
+
+/// Runtime calls and events are both encoded as `Pallet(Variant { fields })`.
+fn get_call(value: &Value<TypeId>) -> Option<SubstrateCall> {
+    let ValueDef::Variant(pallet) = &value.value else {
+        return None;
+    };
+    let ValueDef::Variant(call) = &pallet.values.at(0)?.value else {
+        return None;
+    };
+
+    Some(SubstrateCall {
+        pallet: pallet.name.clone(),
+        name: call.name.clone(),
+        fields: call.values.clone(),
+    })
+}
+
+fn get_u128(value: &Value<TypeId>) -> Option<u128> {
+    match &value.value {
+        ValueDef::Primitive(p) => p.as_u128(),
+        ValueDef::Composite(c) if c.len() == 1 => get_u128(c.at(0)?),
+        _ => None,
+    }
+}
+
+/// Reads an `AccountId32` or a `MultiAddress::Id` into an ss58 address.
+fn get_account(value: &Value<TypeId>, ss58_prefix: u16) -> Option<String> {
+    Some(get_account_id(value)?.to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)))
+}
+
+fn get_account_id(value: &Value<TypeId>) -> Option<AccountId32> {
+    let mut bytes = Vec::new();
+    collect_account_bytes(value, &mut bytes)?;
+
+    let byte_arr: [u8; 32] = bytes.try_into().ok()?;
+    Some(AccountId32::from(byte_arr))
+}
+
+fn get_bytes(value: &Value<TypeId>) -> Option<Vec<u8>> {
+    let ValueDef::Composite(c) = &value.value else {
+        return None;
+    };
+
+    c.values()
+        .map(|p| match &p.value {
+            ValueDef::Primitive(p) => u8::try_from(p.as_u128()?).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+fn collect_account_bytes(value: &Value<TypeId>, bytes: &mut Vec<u8>) -> Option<()> {
+    match &value.value {
+        ValueDef::Composite(c) => {
+            for v in c.values() {
+                collect_account_bytes(v, bytes)?;
+            }
+        }
+        ValueDef::Variant(v) if v.name == "Id" => {
+            collect_account_bytes(v.values.at(0)?, bytes)?;
+        }
+        ValueDef::Primitive(p) => bytes.push(u8::try_from(p.as_u128()?).ok()?),
+        _ => return None,
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_call_decoder::get_multisig_account, subscan_parser::Network,
+        substrate_rpc_decoder::SubstrateRuntime, OperationType,
+    };
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata, PalletMetadata, PalletStorageMetadata, RuntimeMetadataV14,
+            SignedExtensionMetadata, StorageEntryMetadata, StorageEntryModifier, StorageEntryType,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use parity_scale_codec::{Compact, Encode};
+    use scale_info::{MetaType, Registry, TypeInfo};
+    use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+    use std::marker::PhantomData;
+
+    // minimal copies of the runtime types, registered the same way the node exposes them
+    #[derive(Encode, TypeInfo)]
+    struct AccountId32([u8; 32]);
+
+    #[derive(Encode, TypeInfo)]
+    enum MultiAddress {
+        Id(AccountId32),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum MultiSignature {
+        Sr25519([u8; 64]),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    struct CheckNonce(#[codec(compact)] u32);
+
+    #[derive(TypeInfo)]
+    struct UncheckedExtrinsic<Address, Call, Signature, Extra>(
+        PhantomData<(Address, Call, Signature, Extra)>,
+    );
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum TimestampCall {
+        set {
+            #[codec(compact)]
+            now: u64,
+        },
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum BalancesCall {
+        transfer_keep_alive {
+            dest: MultiAddress,
+            #[codec(compact)]
+            value: u128,
+        },
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum StakingCall {
+        bond {
+            #[codec(compact)]
+            value: u128,
+            payee: u8,
+        },
+        nominate {
+            targets: Vec<MultiAddress>,
+        },
+        unbond {
+            #[codec(compact)]
+            value: u128,
+        },
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum NominationPoolsCall {
+        join {
+            #[codec(compact)]
+            amount: u128,
+            pool_id: u32,
+        },
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum UtilityCall {
+        batch_all { calls: Vec<RuntimeCall> },
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum ProxyCall {
+        proxy {
+            real: MultiAddress,
+            force_proxy_type: Option<u8>,
+            call: Box<RuntimeCall>,
+        },
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum MultisigCall {
+        as_multi {
+            threshold: u16,
+            other_signatories: Vec<AccountId32>,
+            maybe_timepoint: Option<u32>,
+            call: Box<RuntimeCall>,
+            max_weight: u64,
+        },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum RuntimeCall {
+        Timestamp(TimestampCall),
+        Balances(BalancesCall),
+        Staking(StakingCall),
+        Utility(UtilityCall),
+        NominationPools(NominationPoolsCall),
+        Proxy(ProxyCall),
+        Multisig(MultisigCall),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum SystemEvent {
+        ExtrinsicSuccess { weight: u64 },
+        ExtrinsicFailed { weight: u64 },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum BalancesEvent {
+        Transfer {
+            from: AccountId32,
+            to: AccountId32,
+            amount: u128,
+        },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum StakingEvent {
+        Bonded { stash: AccountId32, amount: u128 },
+        Unbonded { stash: AccountId32, amount: u128 },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum NominationPoolsEvent {
+        Bonded {
+            member: AccountId32,
+            pool_id: u32,
+            bonded: u128,
+            joined: bool,
+        },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum MultisigEvent {
+        MultisigExecuted {
+            approving: AccountId32,
+            timepoint: u32,
+            multisig: AccountId32,
+            call_hash: [u8; 32],
+            result: Result<(), u8>,
+        },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum RuntimeEvent {
+        System(SystemEvent),
+        Balances(BalancesEvent),
+        Staking(StakingEvent),
+        NominationPools(NominationPoolsEvent),
+        Multisig(MultisigEvent),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum Phase {
+        ApplyExtrinsic(u32),
+        Finalization,
+    }
+
+    #[derive(Encode, TypeInfo)]
+    struct EventRecord {
+        phase: Phase,
+        event: RuntimeEvent,
+        topics: Vec<[u8; 32]>,
+    }
+
+    fn get_runtime() -> SubstrateRuntime {
+        let mut registry = Registry::new();
+        let mut register = |ty: MetaType| registry.register_type(&ty).id;
+        let address_ty = register(MetaType::new::<MultiAddress>());
+        let signature_ty = register(MetaType::new::<MultiSignature>());
+        let call_ty = register(MetaType::new::<RuntimeCall>());
+        let extension_ty = register(MetaType::new::<CheckNonce>());
+        let events_ty = register(MetaType::new::<Vec<EventRecord>>());
+
+        SubstrateRuntime {
+            network: Network::Alephzero,
+            spec_version: 1,
+            registry: registry.into(),
+            address_ty,
+            signature_ty,
+            call_ty,
+            extension_tys: vec![extension_ty],
+            events_ty,
+        }
+    }
+
+    /// Metadata the node would return from `state_getMetadata` for the runtime above.
+    fn get_metadata() -> Vec<u8> {
+        let system = PalletMetadata {
+            name: "System",
+            storage: Some(PalletStorageMetadata {
+                prefix: "System",
+                entries: vec![StorageEntryMetadata {
+                    name: "Events",
+                    modifier: StorageEntryModifier::Default,
+                    ty: StorageEntryType::Plain(MetaType::new::<Vec<EventRecord>>()),
+                    default: Vec::new(),
+                    docs: Vec::new(),
+                }],
+            }),
+            calls: None,
+            event: None,
+            constants: Vec::new(),
+            error: None,
+            index: 0,
+        };
+        let extrinsic = ExtrinsicMetadata {
+            ty: MetaType::new::<
+                UncheckedExtrinsic<MultiAddress, RuntimeCall, MultiSignature, CheckNonce>,
+            >(),
+            version: 4,
+            signed_extensions: vec![SignedExtensionMetadata {
+                identifier: "CheckNonce",
+                ty: MetaType::new::<CheckNonce>(),
+                additional_signed: MetaType::new::<()>(),
+            }],
+        };
+
+        let metadata = RuntimeMetadataV14::new(vec![system], extrinsic, MetaType::new::<()>());
+        RuntimeMetadataPrefixed::from(metadata).encode()
+    }
+
+    fn encode_extrinsic(signer: Option<u8>, call: RuntimeCall) -> Vec<u8> {
+        let mut extrinsic = Vec::new();
+        match signer {
+            Some(signer) => {
+                0b1000_0100u8.encode_to(&mut extrinsic);
+                MultiAddress::Id(AccountId32([signer; 32])).encode_to(&mut extrinsic);
+                MultiSignature::Sr25519([0; 64]).encode_to(&mut extrinsic);
+                CheckNonce(7).encode_to(&mut extrinsic);
+            }
+            None => 0b0000_0100u8.encode_to(&mut extrinsic),
+        }
+        call.encode_to(&mut extrinsic);
+
+        let mut encoded = Compact(extrinsic.len() as u32).encode();
+        encoded.append(&mut extrinsic);
+        encoded
+    }
+
+    fn get_address(byte: u8) -> String {
+        sp_core::crypto::AccountId32::from([byte; 32])
+            .to_ss58check_with_version(Ss58AddressFormat::custom(42))
+    }
+
+    fn get_record(position: u32, event: RuntimeEvent) -> EventRecord {
+        EventRecord {
+            phase: Phase::ApplyExtrinsic(position),
+            event,
+            topics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn decode_block_works() {
+        let extrinsics = vec![
+            encode_extrinsic(
+                None,
+                RuntimeCall::Timestamp(TimestampCall::set {
+                    now: 1_700_000_000_000,
+                }),
+            ),
+            encode_extrinsic(
+                Some(1),
+                RuntimeCall::Balances(BalancesCall::transfer_keep_alive {
+                    dest: MultiAddress::Id(AccountId32([2; 32])),
+                    value: 30_000_000_000_000_000,
+                }),
+            ),
+            encode_extrinsic(
+                Some(3),
+                RuntimeCall::Utility(UtilityCall::batch_all {
+                    calls: vec![
+                        RuntimeCall::Staking(StakingCall::bond {
+                            value: 5_000_000_000_000_000,
+                            payee: 0,
+                        }),
+                        RuntimeCall::Staking(StakingCall::nominate {
+                            targets: vec![
+                                MultiAddress::Id(AccountId32([4; 32])),
+                                MultiAddress::Id(AccountId32([6; 32])),
+                            ],
+                        }),
+                    ],
+                }),
+            ),
+            encode_extrinsic(
+                Some(5),
+                RuntimeCall::Staking(StakingCall::unbond {
+                    value: 1_000_000_000_000_000,
+                }),
+            ),
+            encode_extrinsic(
+                Some(6),
+                RuntimeCall::NominationPools(NominationPoolsCall::join {
+                    amount: 2_000_000_000_000_000,
+                    pool_id: 9,
+                }),
+            ),
+            encode_extrinsic(
+                Some(7),
+                RuntimeCall::Proxy(ProxyCall::proxy {
+                    real: MultiAddress::Id(AccountId32([8; 32])),
+                    force_proxy_type: None,
+                    call: Box::new(RuntimeCall::Staking(StakingCall::nominate {
+                        targets: vec![MultiAddress::Id(AccountId32([4; 32]))],
+                    })),
+                }),
+            ),
+        ];
+
+        let events = vec![
+            get_record(
+                0,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                1,
+                RuntimeEvent::Balances(BalancesEvent::Transfer {
+                    from: AccountId32([1; 32]),
+                    to: AccountId32([2; 32]),
+                    amount: 30_000_000_000_000_000,
+                }),
+            ),
+            get_record(
+                1,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                2,
+                RuntimeEvent::Staking(StakingEvent::Bonded {
+                    stash: AccountId32([3; 32]),
+                    amount: 5_000_000_000_000_000,
+                }),
+            ),
+            get_record(
+                2,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                3,
+                RuntimeEvent::Staking(StakingEvent::Unbonded {
+                    stash: AccountId32([5; 32]),
+                    amount: 1_000_000_000_000_000,
+                }),
+            ),
+            get_record(
+                3,
+                RuntimeEvent::System(SystemEvent::ExtrinsicFailed { weight: 1 }),
+            ),
+            get_record(
+                4,
+                RuntimeEvent::NominationPools(NominationPoolsEvent::Bonded {
+                    member: AccountId32([6; 32]),
+                    pool_id: 9,
+                    bonded: 2_000_000_000_000_000,
+                    joined: true,
+                }),
+            ),
+            get_record(
+                4,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                5,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            EventRecord {
+                phase: Phase::Finalization,
+                event: RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+                topics: Vec::new(),
+            },
+        ]
+        .encode();
+
+        let runtime = get_runtime();
+        let subscan_operations = runtime.decode_block(100, &extrinsics, &events).unwrap();
+        assert_eq!(subscan_operations.len(), 4);
+
+        let transfer = &subscan_operations[0];
+        assert_eq!(transfer.operation_type, OperationType::Transfer);
+        assert_eq!(transfer.extrinsic_index, "100-1");
+        assert_eq!(transfer.operation_quantity, 30_000.0);
+        assert_eq!(
+            transfer.operation_timestamp.timestamp_millis(),
+            1_700_000_000_000
+        );
+        assert_eq!(transfer.from_wallet, get_address(1));
+        assert_eq!(transfer.to_wallet, get_address(2));
+
+        let stake = &subscan_operations[1];
+        assert_eq!(stake.operation_type, OperationType::ReStake);
+        assert_eq!(stake.extrinsic_index, "100-2");
+        assert_eq!(stake.operation_quantity, 5_000.0);
+        assert_eq!(stake.from_wallet, get_address(3));
+        assert_eq!(stake.to_wallet, get_address(4));
+        assert_eq!(stake.targets, vec![get_address(4), get_address(6)]);
+
+        let pool_join = &subscan_operations[2];
+        assert_eq!(pool_join.operation_type, OperationType::PoolJoin);
+        assert_eq!(pool_join.operation_quantity, 2_000.0);
+        assert_eq!(pool_join.from_wallet, get_address(6));
+        assert_eq!(pool_join.pool_id, Some(9));
+
+        // proxied nominate is attributed to the real account, not the proxy
+        let proxied = &subscan_operations[3];
+        assert_eq!(proxied.operation_type, OperationType::ReStake);
+        assert_eq!(proxied.from_wallet, get_address(8));
+        assert_eq!(proxied.to_wallet, get_address(4));
+    }
+
+    #[test]
+    fn decode_block_skips_undecodable_extrinsics() {
+        let runtime =
+            SubstrateRuntime::from_metadata(Network::Alephzero, 1, &get_metadata()).unwrap();
+
+        let extrinsics = vec![
+            encode_extrinsic(
+                None,
+                RuntimeCall::Timestamp(TimestampCall::set {
+                    now: 1_700_000_000_000,
+                }),
+            ),
+            // a call index the runtime doesn't know, e.g. from a newer runtime upgrade
+            vec![12, 0b1000_0100, 0xff, 0xff, 0xff],
+            encode_extrinsic(
+                Some(5),
+                RuntimeCall::Staking(StakingCall::unbond {
+                    value: 1_000_000_000_000_000,
+                }),
+            ),
+        ];
+        let events = vec![
+            get_record(
+                0,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                1,
+                RuntimeEvent::Balances(BalancesEvent::Transfer {
+                    from: AccountId32([1; 32]),
+                    to: AccountId32([2; 32]),
+                    amount: 30_000_000_000_000_000,
+                }),
+            ),
+            get_record(
+                1,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                2,
+                RuntimeEvent::Staking(StakingEvent::Unbonded {
+                    stash: AccountId32([5; 32]),
+                    amount: 1_000_000_000_000_000,
+                }),
+            ),
+            get_record(
+                2,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+        ]
+        .encode();
+
+        assert!(runtime.decode_extrinsics(100, &extrinsics)[1].is_none());
+
+        // the transfer is still read from its events, the rest of the block is unaffected
+        let subscan_operations = runtime.decode_block(100, &extrinsics, &events).unwrap();
+        assert_eq!(subscan_operations.len(), 2);
+        assert_eq!(subscan_operations[0].extrinsic_index, "100-1");
+        assert_eq!(subscan_operations[0].from_wallet, get_address(1));
+        assert_eq!(subscan_operations[1].extrinsic_index, "100-2");
+        assert_eq!(
+            subscan_operations[1].operation_type,
+            OperationType::RequestUnstake
+        );
+        assert_eq!(subscan_operations[1].from_wallet, get_address(5));
+    }
+
+    #[test]
+    fn decode_block_skips_unexecuted_multisig_approvals() {
+        let as_multi = || {
+            RuntimeCall::Multisig(MultisigCall::as_multi {
+                threshold: 2,
+                other_signatories: vec![AccountId32([9; 32])],
+                maybe_timepoint: None,
+                call: Box::new(RuntimeCall::Staking(StakingCall::nominate {
+                    targets: vec![MultiAddress::Id(AccountId32([4; 32]))],
+                })),
+                max_weight: 0,
+            })
+        };
+        let extrinsics = vec![
+            encode_extrinsic(
+                None,
+                RuntimeCall::Timestamp(TimestampCall::set {
+                    now: 1_700_000_000_000,
+                }),
+            ),
+            encode_extrinsic(Some(1), as_multi()),
+            encode_extrinsic(Some(1), as_multi()),
+        ];
+
+        let multisig = get_multisig_account(
+            vec![
+                sp_core::crypto::AccountId32::from([9; 32]),
+                sp_core::crypto::AccountId32::from([1; 32]),
+            ],
+            2,
+        );
+        let events = vec![
+            get_record(
+                0,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            // the first approval only records itself
+            get_record(
+                1,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+            get_record(
+                2,
+                RuntimeEvent::Multisig(MultisigEvent::MultisigExecuted {
+                    approving: AccountId32([1; 32]),
+                    timepoint: 1,
+                    multisig: AccountId32(multisig.clone().into()),
+                    call_hash: [0; 32],
+                    result: Ok(()),
+                }),
+            ),
+            get_record(
+                2,
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 }),
+            ),
+        ]
+        .encode();
+
+        let runtime = get_runtime();
+        let subscan_operations = runtime.decode_block(100, &extrinsics, &events).unwrap();
+        assert_eq!(subscan_operations.len(), 1);
+        assert_eq!(subscan_operations[0].extrinsic_index, "100-2");
+        assert_eq!(
+            subscan_operations[0].from_wallet,
+            multisig.to_ss58check_with_version(Ss58AddressFormat::custom(42))
+        );
+        assert_eq!(subscan_operations[0].to_wallet, get_address(4));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    mongodb_client_nomination::MongoDbClientNomination,
+    subscan_cursor::{get_cursor_or_default, parse_extrinsic_index},
+    subscan_parser::Network,
+    subscan_pool_parser::enrich_pool_operations,
+    subscan_stake_parser::enrich_staking_operations,
+    subscan_transfer_parser::enrich_transfers,
+    substrate_rpc_client::SubstrateRpcClient,
+    substrate_rpc_decoder::{ContractEvent, SubstrateRuntime},
+    Nomination, OperationType, SubscanCursor, SubscanOperation,
+};
+use bson::DateTime;
+use log::{error, info};
+use std::cmp;
+
+pub static RPC_CURSOR_STREAM: &str = "rpc_blocks";
+static RPC_MAX_BLOCKS_PER_ITERATION: u64 = 100;
+static RPC_INITIAL_LOOKBACK_BLOCKS: u64 = 1_000;
+
+#[derive(Clone, Debug)]
+pub struct SubstrateRpcParser {
+    rpc_client: SubstrateRpcClient,
+    runtime: Option<SubstrateRuntime>,
+}
+
+impl SubstrateRpcParser {
+    pub async fn new() -> Self {
+        SubstrateRpcParser {
+            rpc_client: SubstrateRpcClient::new().await,
+            runtime: None,
+        }
+    }
+
+    pub async fn get_head_block_number(&mut self) -> Option<u64> {
+        self.rpc_client.get_finalized_head_number().await
+    }
+
+    /// Reads a block with its events and decodes it with the metadata of the runtime
+    /// the block was produced by. Chills come separately as they carry no operation.
+    pub async fn parse_block(
+        &mut self,
+        block_number: u64,
+    ) -> Option<(Vec<SubscanOperation>, Vec<Nomination>)> {
+        let (extrinsics, events) = self.get_block(block_number).await?;
+
+        let runtime = self.runtime.as_ref()?;
+        let subscan_operations = runtime.decode_block(block_number, &extrinsics, &events)?;
+        let chills = runtime.decode_chills(block_number, &events)?;
+        Some((subscan_operations, chills))
+    }
+
+    pub async fn parse_block_contract_events(
+        &mut self,
+        block_number: u64,
+    ) -> Option<Vec<ContractEvent>> {
+        let (extrinsics, events) = self.get_block(block_number).await?;
+
+        self.runtime
+            .as_ref()?
+            .decode_contract_events(block_number, &extrinsics, &events)
+    }
+
+    /// Raw extrinsics and events of the block. Metadata is fetched again only on runtime
+    /// upgrades.
+    async fn get_block(&mut self, block_number: u64) -> Option<(Vec<Vec<u8>>, Vec<u8>)> {
+        let block_hash = self.rpc_client.get_block_hash(block_number).await?;
+
+        let spec_version = self.rpc_client.get_spec_version(&block_hash).await?;
+        if self.runtime.as_ref().map(|p| p.get_spec_version()) != Some(spec_version) {
+            let metadata = self.rpc_client.get_metadata(&block_hash).await?;
+            let Some(runtime) =
+                SubstrateRuntime::from_metadata(Network::from_env(), spec_version, &metadata)
+            else {
+                error!(target: "substrate_rpc_parser", "Unsupported metadata of runtime {spec_version}.");
+                return None;
+            };
+
+            info!(target: "substrate_rpc_parser", "Loaded metadata of runtime {spec_version}.");
+            self.runtime = Some(runtime);
+        }
+
+        let extrinsics = self.rpc_client.get_block_extrinsics(&block_hash).await?;
+        let events = self.rpc_client.get_block_events(&block_hash).await?;
+
+        Some((extrinsics, events))
+    }
+}
+
+/// Reads finalized blocks after the rpc cursor, at most `RPC_MAX_BLOCKS_PER_ITERATION`
+/// per call, and runs them through the same enrichment as the subscan streams.
+/// Validator and identity lookups inside the enrichment still use subscan.
+pub async fn parse_rpc_blocks(
+    substrate_rpc_parser: &mut SubstrateRpcParser,
+    head_block_number: u64,
+) -> Option<(Vec<SubscanOperation>, SubscanCursor)> {
+    let mut cursor = get_cursor_or_default(
+        RPC_CURSOR_STREAM,
+        head_block_number.saturating_sub(RPC_INITIAL_LOOKBACK_BLOCKS),
+    )
+    .await;
+    cursor.head_block_number = head_block_number;
+    cursor.updated_timestamp = DateTime::now();
+
+    let to_block_number = cmp::min(
+        head_block_number,
+        cursor.block_number + RPC_MAX_BLOCKS_PER_ITERATION,
+    );
+
+    let mut subscan_operations = Vec::new();
+    let mut chills = Vec::new();
+    while cursor.block_number < to_block_number {
+        let block_number = cursor.block_number + 1;
+
+        // keeping the cursor where it is, next iteration will retry the same block
+        let Some((mut s, mut c)) = substrate_rpc_parser.parse_block(block_number).await else {
+            error!(target: "substrate_rpc_parser", "Failed to parse block {block_number}.");
+            break;
+        };
+
+        if let Some(newest) = s
+            .iter()
+            .max_by_key(|p| parse_extrinsic_index(&p.extrinsic_index))
+        {
+            cursor.extrinsic_index = newest.extrinsic_index.clone();
+        }
+        cursor.block_number = block_number;
+        subscan_operations.append(&mut s);
+        chills.append(&mut c);
+    }
+
+    let mut mongodb_client_nomination = MongoDbClientNomination::new().await;
+    mongodb_client_nomination.import_nominations(chills).await;
+
+    info!(
+        target: "substrate_rpc_parser", "Rpc is at block {} of {} (lag {} blocks), found {} items",
+        cursor.block_number,
+        cursor.head_block_number,
+        cursor.get_lag(),
+        subscan_operations.len(),
+    );
+
+    let (transfers, subscan_operations): (Vec<_>, Vec<_>) = subscan_operations
+        .into_iter()
+        .partition(|p| p.operation_type == OperationType::Transfer);
+    let (pool_operations, staking_operations): (Vec<_>, Vec<_>) = subscan_operations
+        .into_iter()
+        .partition(|p| p.operation_type.is_pool_operation());
+
+    // staking operations already carry stash and amount, same as wrapped ones
+    let mut subscan_operations = enrich_transfers(transfers, Vec::new()).await?;
+    let mut staking_operations = enrich_staking_operations(Vec::new(), staking_operations).await?;
+    let mut pool_operations = enrich_pool_operations(Vec::new(), pool_operations).await?;
+    subscan_operations.append(&mut staking_operations);
+    subscan_operations.append(&mut pool_operations);
+
+    Some((subscan_operations, cursor))
+}
@@ -0,0 +1,433 @@
+use crate::{
+    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_unbonding::MongoDbClientUnbonding,
+    subscan_parser::{Network, AZERO_DENOMINATOR},
+    substrate_rpc_client::SubstrateRpcClient,
+    substrate_rpc_decoder::SubstrateRuntime,
+    OperationType, SubscanOperation, UnbondingChunk,
+};
+use bson::DateTime;
+use chrono::{NaiveDate, TimeZone, Utc};
+use itertools::Itertools;
+use log::error;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::collections::HashSet;
+
+pub static UNBONDING_ANALYZER_INTERVAL_SECS: u64 = 60 * 60;
+static ERA_DURATION_SECS: i64 = 24 * 60 * 60;
+static FORECAST_DAYS: i64 = 7;
+// withdrawn amounts may differ from the unbonded ones by rounding
+static WITHDRAWAL_TOLERANCE_SHARE: f64 = 0.001;
+
+/// AZERO becoming liquid: `unlocked` can be withdrawn already, `days` holds the amounts
+/// unlocking on each of the coming days.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnlockForecast {
+    pub unlocked: f64,
+    pub next_24h: f64,
+    pub next_7d: f64,
+    pub days: Vec<(NaiveDate, f64)>,
+}
+
+/// Records unbonding chunks of the recent `RequestUnstake` operations and marks the ones
+/// covered by `Rebond` and `WithdrawUnstaked` since, then reconciles unlocked chunks with
+/// the stash ledgers. Returns the number of chunks seen.
+pub async fn analyze_unbondings() -> Option<usize> {
+    let bonding_duration = get_bonding_duration().await?;
+
+    // a withdrawal covers chunks unbonded at most the bonding duration before it
+    let from_timestamp = Utc::now().timestamp() - (bonding_duration as i64 + 2) * ERA_DURATION_SECS;
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let subscan_operations = mongodb_client_subscan
+        .get_filtered_operations(&Network::from_env(), from_timestamp, None)
+        .await;
+
+    let unbonding_chunks =
+        convert_operations_to_unbonding_chunks(&subscan_operations, bonding_duration);
+    let unbonding_chunks_len = unbonding_chunks.len();
+    let mut mongodb_client_unbonding = MongoDbClientUnbonding::new().await;
+    mongodb_client_unbonding
+        .import_unbonding_chunks(unbonding_chunks)
+        .await;
+
+    let (rebonds, withdrawals): (Vec<_>, Vec<_>) = subscan_operations
+        .into_iter()
+        .filter(|p| {
+            p.operation_type == OperationType::Rebond
+                || p.operation_type == OperationType::WithdrawUnstaked
+        })
+        .partition(|p| p.operation_type == OperationType::Rebond);
+    let stashes = rebonds
+        .iter()
+        .chain(withdrawals.iter())
+        .map(|p| p.from_wallet.clone())
+        .unique()
+        .collect();
+    let mut unbonding_chunks = mongodb_client_unbonding
+        .get_unbonding_chunks_by_stashes(stashes)
+        .await;
+    let mut changed_chunks = match_rebonds(&mut unbonding_chunks, &rebonds);
+    changed_chunks.extend(match_withdrawals(&mut unbonding_chunks, &withdrawals));
+    mongodb_client_unbonding
+        .update_unbonding_chunks(changed_chunks)
+        .await;
+
+    reconcile_unlocked_chunks(&mut mongodb_client_unbonding).await;
+
+    Some(unbonding_chunks_len)
+}
+
+/// Checks stashes with unlocked chunks against their ledger, a failed lookup leaves the
+/// stash for the next run.
+async fn reconcile_unlocked_chunks(mongodb_client_unbonding: &mut MongoDbClientUnbonding) {
+    let now = Utc::now().timestamp();
+    let stashes = mongodb_client_unbonding
+        .get_outstanding_unbonding_chunks()
+        .await
+        .into_iter()
+        .filter(|p| p.unlock_timestamp.timestamp_millis() <= now * 1_000)
+        .map(|p| p.stash)
+        .unique()
+        .collect::<Vec<_>>();
+
+    let mut rpc_client = SubstrateRpcClient::new().await;
+    for stash in stashes {
+        let Ok(account) = AccountId32::from_ss58check(&stash) else {
+            continue;
+        };
+        let Some(ledger_chunks) = rpc_client.get_unlocking_chunks(&account).await else {
+            error!(target: "unbonding_forecast", "Unable to read the ledger of {stash}");
+            continue;
+        };
+        let ledger_unlocking = ledger_chunks
+            .iter()
+            .map(|(value, _)| *value as f64 / AZERO_DENOMINATOR)
+            .sum::<f64>();
+
+        let mut unbonding_chunks = mongodb_client_unbonding
+            .get_unbonding_chunks_by_stashes(vec![stash])
+            .await;
+        let closed_chunks =
+            reconcile_unbonding_chunks(&mut unbonding_chunks, ledger_unlocking, now);
+        mongodb_client_unbonding
+            .update_unbonding_chunks(closed_chunks)
+            .await;
+    }
+}
+
+/// `Staking.BondingDuration` of the current runtime, in eras.
+async fn get_bonding_duration() -> Option<u32> {
+    let mut rpc_client = SubstrateRpcClient::new().await;
+    let block_hash = rpc_client.get_finalized_head_hash().await?;
+    let metadata = rpc_client.get_metadata(&block_hash).await?;
+
+    SubstrateRuntime::decode_constant_u32(&metadata, "Staking", "BondingDuration")
+}
+
+/// The chunk unlocks at the start of the era `bonding_duration` eras after the one it was
+/// requested in, so the estimate is at most one era late.
+pub fn convert_operations_to_unbonding_chunks(
+    source: &[SubscanOperation],
+    bonding_duration: u32,
+) -> Vec<UnbondingChunk> {
+    source
+        .iter()
+        .filter(|p| p.operation_type == OperationType::RequestUnstake)
+        .map(|p| UnbondingChunk {
+            stash: p.from_wallet.clone(),
+            extrinsic_index: p.extrinsic_index.clone(),
+            amount: p.operation_quantity,
+            unbond_timestamp: p.operation_timestamp,
+            unlock_timestamp: DateTime::from_millis(
+                p.operation_timestamp.timestamp_millis()
+                    + bonding_duration as i64 * ERA_DURATION_SECS * 1_000,
+            ),
+            withdrawn_extrinsic_index: None,
+            withdrawn_timestamp: None,
+            rebond_extrinsic_indexes: Vec::new(),
+            network: p.network.clone(),
+        })
+        .collect()
+}
+
+/// A withdrawal covers the oldest unlocked chunks of its stash up to the withdrawn amount,
+/// taking into account chunks matched to it before. Returns the newly covered chunks.
+pub fn match_withdrawals(
+    unbonding_chunks: &mut [UnbondingChunk],
+    withdrawals: &[SubscanOperation],
+) -> Vec<UnbondingChunk> {
+    unbonding_chunks.sort_by_key(|p| p.unbond_timestamp);
+
+    let mut withdrawn_chunks = Vec::new();
+    for w in withdrawals.iter().sorted_by_key(|p| p.operation_timestamp) {
+        let matched = unbonding_chunks
+            .iter()
+            .filter(|p| p.withdrawn_extrinsic_index.as_ref() == Some(&w.extrinsic_index))
+            .map(|p| p.amount)
+            .sum::<f64>();
+        let mut remaining = w.operation_quantity * (1.0 + WITHDRAWAL_TOLERANCE_SHARE) - matched;
+        let latest_unlock = DateTime::from_millis(
+            w.operation_timestamp.timestamp_millis() + ERA_DURATION_SECS * 1_000,
+        );
+
+        for c in unbonding_chunks.iter_mut().filter(|p| {
+            p.stash == w.from_wallet
+                && p.withdrawn_timestamp.is_none()
+                && p.unlock_timestamp <= latest_unlock
+        }) {
+            if c.amount > remaining {
+                break;
+            }
+
+            remaining -= c.amount;
+            c.withdrawn_extrinsic_index = Some(w.extrinsic_index.clone());
+            c.withdrawn_timestamp = Some(w.operation_timestamp);
+            withdrawn_chunks.push(c.clone());
+        }
+    }
+
+    withdrawn_chunks
+}
+
+/// A rebond takes back the newest outstanding chunks of its stash unbonded before it, the
+/// last one it reaches may be taken only in part. Rebonds applied before are skipped.
+/// Returns the changed chunks, the ones taken fully are closed by the rebond.
+pub fn match_rebonds(
+    unbonding_chunks: &mut [UnbondingChunk],
+    rebonds: &[SubscanOperation],
+) -> Vec<UnbondingChunk> {
+    unbonding_chunks.sort_by_key(|p| p.unbond_timestamp);
+
+    let mut changed_indexes = HashSet::new();
+    for r in rebonds.iter().sorted_by_key(|p| p.operation_timestamp) {
+        if unbonding_chunks
+            .iter()
+            .any(|p| p.rebond_extrinsic_indexes.contains(&r.extrinsic_index))
+        {
+            continue;
+        }
+
+        let mut remaining = r.operation_quantity;
+        for c in unbonding_chunks.iter_mut().rev().filter(|p| {
+            p.stash == r.from_wallet
+                && p.withdrawn_timestamp.is_none()
+                && p.unbond_timestamp <= r.operation_timestamp
+        }) {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            c.rebond_extrinsic_indexes.push(r.extrinsic_index.clone());
+            changed_indexes.insert(c.extrinsic_index.clone());
+            if c.amount > remaining * (1.0 + WITHDRAWAL_TOLERANCE_SHARE) {
+                c.amount -= remaining;
+                break;
+            }
+
+            remaining -= c.amount;
+            c.withdrawn_extrinsic_index = Some(r.extrinsic_index.clone());
+            c.withdrawn_timestamp = Some(r.operation_timestamp);
+        }
+    }
+
+    unbonding_chunks
+        .iter()
+        .filter(|p| changed_indexes.contains(&p.extrinsic_index))
+        .cloned()
+        .collect()
+}
+
+/// Closes the oldest outstanding chunks of a stash while they add up to more than its
+/// ledger still unlocks, `now` in seconds. Withdrawals outside the analyzed window or
+/// below `MINIMUM_AZERO_TO_SAVE_TO_DB` are never matched otherwise. Returns the closed
+/// chunks.
+pub fn reconcile_unbonding_chunks(
+    unbonding_chunks: &mut [UnbondingChunk],
+    ledger_unlocking: f64,
+    now: i64,
+) -> Vec<UnbondingChunk> {
+    unbonding_chunks.sort_by_key(|p| p.unbond_timestamp);
+
+    let outstanding = unbonding_chunks
+        .iter()
+        .filter(|p| p.withdrawn_timestamp.is_none())
+        .map(|p| p.amount)
+        .sum::<f64>();
+    let mut excess = outstanding - ledger_unlocking;
+
+    let mut closed_chunks = Vec::new();
+    for c in unbonding_chunks
+        .iter_mut()
+        .filter(|p| p.withdrawn_timestamp.is_none())
+    {
+        if excess <= ledger_unlocking * WITHDRAWAL_TOLERANCE_SHARE {
+            break;
+        }
+
+        excess -= c.amount;
+        c.withdrawn_timestamp = Some(DateTime::from_millis(now * 1_000));
+        closed_chunks.push(c.clone());
+    }
+
+    closed_chunks
+}
+
+/// Sums the outstanding chunks by when they unlock, as of `now` in seconds.
+pub fn get_unlock_forecast(unbonding_chunks: &[UnbondingChunk], now: i64) -> UnlockForecast {
+    let mut forecast = UnlockForecast::default();
+    let now_millis = now * 1_000;
+    for c in unbonding_chunks
+        .iter()
+        .filter(|p| p.withdrawn_timestamp.is_none())
+    {
+        let unlock_millis = c.unlock_timestamp.timestamp_millis();
+        if unlock_millis <= now_millis {
+            forecast.unlocked += c.amount;
+            continue;
+        }
+        if unlock_millis <= now_millis + ERA_DURATION_SECS * 1_000 {
+            forecast.next_24h += c.amount;
+        }
+        if unlock_millis <= now_millis + FORECAST_DAYS * ERA_DURATION_SECS * 1_000 {
+            forecast.next_7d += c.amount;
+        }
+    }
+
+    forecast.days = unbonding_chunks
+        .iter()
+        .filter(|p| {
+            p.withdrawn_timestamp.is_none() && p.unlock_timestamp.timestamp_millis() > now_millis
+        })
+        .filter_map(|p| {
+            let date = Utc
+                .timestamp_millis_opt(p.unlock_timestamp.timestamp_millis())
+                .single()?
+                .date_naive();
+            Some((date, p.amount))
+        })
+        .into_group_map()
+        .into_iter()
+        .map(|(date, amounts)| (date, amounts.into_iter().sum::<f64>()))
+        .sorted_by_key(|(date, _)| *date)
+        .collect();
+
+    forecast
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        unbonding_forecast::{
+            convert_operations_to_unbonding_chunks, get_unlock_forecast, match_rebonds,
+            match_withdrawals, reconcile_unbonding_chunks,
+        },
+        OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+    use chrono::NaiveDate;
+
+    static DAY: i64 = 24 * 60 * 60;
+
+    fn get_operation(
+        operation_type: OperationType,
+        extrinsic_index: &str,
+        quantity: f64,
+        timestamp: i64,
+    ) -> SubscanOperation {
+        SubscanOperation {
+            extrinsic_index: extrinsic_index.to_string(),
+            operation_timestamp: DateTime::from_millis(timestamp * 1_000),
+            operation_quantity: quantity,
+            operation_type,
+            from_wallet: "5Stash".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unlock_forecast_works() {
+        let unbonds = [
+            get_operation(OperationType::RequestUnstake, "1-1", 1_000.0, 0),
+            get_operation(OperationType::RequestUnstake, "2-1", 2_000.0, DAY),
+            get_operation(OperationType::RequestUnstake, "3-1", 4_000.0, 10 * DAY),
+        ];
+        let mut unbonding_chunks = convert_operations_to_unbonding_chunks(&unbonds, 14);
+
+        // the withdrawal after the first two unlocked does not reach the third chunk
+        let withdrawals = [get_operation(
+            OperationType::WithdrawUnstaked,
+            "20-1",
+            3_000.0,
+            20 * DAY,
+        )];
+        let withdrawn_chunks = match_withdrawals(&mut unbonding_chunks, &withdrawals);
+        assert_eq!(withdrawn_chunks.len(), 2);
+        assert!(match_withdrawals(&mut unbonding_chunks, &withdrawals).is_empty());
+
+        let forecast = get_unlock_forecast(&unbonding_chunks, 20 * DAY);
+        assert_eq!(forecast.unlocked, 0.0);
+        assert_eq!(forecast.next_24h, 0.0);
+        assert_eq!(forecast.next_7d, 4_000.0);
+        assert_eq!(
+            forecast.days,
+            vec![(NaiveDate::from_ymd_opt(1970, 1, 25).unwrap(), 4_000.0)]
+        );
+    }
+
+    #[test]
+    fn match_rebonds_takes_newest_chunks_first() {
+        let unbonds = [
+            get_operation(OperationType::RequestUnstake, "1-1", 1_000.0, 0),
+            get_operation(OperationType::RequestUnstake, "2-1", 2_000.0, DAY),
+            get_operation(OperationType::RequestUnstake, "3-1", 4_000.0, 2 * DAY),
+        ];
+        let mut unbonding_chunks = convert_operations_to_unbonding_chunks(&unbonds, 14);
+
+        // the rebond takes the newest chunk and half of the one before
+        let rebonds = [get_operation(
+            OperationType::Rebond,
+            "4-1",
+            5_000.0,
+            3 * DAY,
+        )];
+        let rebonded_chunks = match_rebonds(&mut unbonding_chunks, &rebonds);
+        assert_eq!(rebonded_chunks.len(), 2);
+        assert!(match_rebonds(&mut unbonding_chunks, &rebonds).is_empty());
+
+        assert_eq!(unbonding_chunks[0].withdrawn_timestamp, None);
+        assert_eq!(unbonding_chunks[1].amount, 1_000.0);
+        assert_eq!(unbonding_chunks[1].withdrawn_timestamp, None);
+        assert_eq!(
+            unbonding_chunks[2].withdrawn_extrinsic_index,
+            Some("4-1".to_string())
+        );
+
+        let forecast = get_unlock_forecast(&unbonding_chunks, 20 * DAY);
+        assert_eq!(forecast.unlocked, 2_000.0);
+    }
+
+    #[test]
+    fn reconcile_unbonding_chunks_closes_oldest_chunks() {
+        let unbonds = [
+            get_operation(OperationType::RequestUnstake, "1-1", 1_000.0, 0),
+            get_operation(OperationType::RequestUnstake, "2-1", 2_000.0, DAY),
+            get_operation(OperationType::RequestUnstake, "3-1", 4_000.0, 10 * DAY),
+        ];
+        let mut unbonding_chunks = convert_operations_to_unbonding_chunks(&unbonds, 14);
+
+        // the first two were withdrawn unnoticed, the ledger holds a small unbond we skipped
+        let closed_chunks = reconcile_unbonding_chunks(&mut unbonding_chunks, 4_100.0, 20 * DAY);
+        assert_eq!(closed_chunks.len(), 2);
+        assert!(reconcile_unbonding_chunks(&mut unbonding_chunks, 4_100.0, 20 * DAY).is_empty());
+
+        let forecast = get_unlock_forecast(&unbonding_chunks, 20 * DAY);
+        assert_eq!(forecast.unlocked, 0.0);
+        assert_eq!(forecast.next_7d, 4_000.0);
+
+        // a stash that is not bonded anymore has nothing outstanding
+        reconcile_unbonding_chunks(&mut unbonding_chunks, 0.0, 30 * DAY);
+        assert!(unbonding_chunks
+            .iter()
+            .all(|p| p.withdrawn_timestamp.is_some()));
+    }
+}
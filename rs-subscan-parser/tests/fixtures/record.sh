@@ -0,0 +1,108 @@
+#!/usr/bin/env bash
+# Records node responses for the fixtures in this directory. The tests do not run it,
+# check the recorded file in and point the tests at it.
+#
+# Usage:
+#   SUBSTRATE_RPC_URL=https://... ./record.sh block <name> <block_number>...
+#       state_getMetadata, chain_getBlock and System.Events of the blocks into <name>.json,
+#       for SubstrateRuntime::from_metadata and decode_block. The blocks must share a
+#       runtime.
+#   SUBSTRATE_RPC_URL=https://... ./record.sh dex <pair_metadata.json> <block_number>...
+#       the same for blocks with swaps of a pair, plus the ink metadata of the pair
+#       contract, into dex_swap_blocks.json. decode_contract_events gives the events
+#       convert_swap_event takes.
+#   EVM_RPC_URL=https://... ./record.sh evm <tokens.json> <block_number>
+#       eth_getBlockByNumber and the ERC-20 Transfer eth_getLogs of the block for the
+#       tokens of an EVM_TOKENS_PATH file into evm_transfers.json. Pick a block with a
+#       token transfer to a wallet that bridged from Substrate.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+TMP_DIR=$(mktemp -d)
+trap 'rm -rf "$TMP_DIR"' EXIT
+
+# keccak256("Transfer(address,address,uint256)")
+ERC20_TRANSFER_TOPIC=0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef
+
+# twox_128("System") ++ twox_128("Events")
+SYSTEM_EVENTS_KEY=0x26aa394eea5630e07c48ae0c9558cef780d41e5e16056765bc8461851072c9d7
+
+# rpc <url> <method> <params json> > result json
+rpc() {
+    jq -n --arg method "$2" --argjson params "$3" \
+        '{jsonrpc: "2.0", id: 1, method: $method, params: $params}' |
+        curl -sSf -X POST -H 'Content-Type: application/json' -d @- "$1" |
+        jq -e '.result'
+}
+
+# record_blocks <block_number>... > {spec_version, metadata, blocks}
+record_blocks() {
+    local spec_version="" number hash version
+    for number in "$@"; do
+        hash=$(rpc "$SUBSTRATE_RPC_URL" chain_getBlockHash "[$number]" | jq -r .)
+        version=$(rpc "$SUBSTRATE_RPC_URL" state_getRuntimeVersion "[\"$hash\"]" | jq .specVersion)
+        if [ -z "$spec_version" ]; then
+            spec_version=$version
+            rpc "$SUBSTRATE_RPC_URL" state_getMetadata "[\"$hash\"]" >"$TMP_DIR/metadata.json"
+        elif [ "$version" != "$spec_version" ]; then
+            echo "Block $number is of runtime $version, not $spec_version" >&2
+            exit 1
+        fi
+
+        rpc "$SUBSTRATE_RPC_URL" chain_getBlock "[\"$hash\"]" >"$TMP_DIR/block.json"
+        rpc "$SUBSTRATE_RPC_URL" state_getStorage "[\"$SYSTEM_EVENTS_KEY\", \"$hash\"]" \
+            >"$TMP_DIR/events.json"
+        jq -n --argjson block_number "$number" --arg block_hash "$hash" \
+            --slurpfile block "$TMP_DIR/block.json" --slurpfile events "$TMP_DIR/events.json" \
+            '{block_number: $block_number, block_hash: $block_hash,
+              extrinsics: $block[0].block.extrinsics, events: $events[0]}' \
+            >"$TMP_DIR/block_$number.json"
+    done
+
+    jq -n --argjson spec_version "$spec_version" --slurpfile metadata "$TMP_DIR/metadata.json" \
+        '{spec_version: $spec_version, metadata: $metadata[0], blocks: [inputs]}' \
+        $(printf "$TMP_DIR/block_%s.json " "$@")
+}
+
+# write <file> <source>, reads the fixture from stdin
+write() {
+    jq --arg source "$2" '{source: $source} + .' >"$TMP_DIR/fixture.json"
+    mv "$TMP_DIR/fixture.json" "$1"
+}
+
+command=${1:-}
+shift || true
+case "$command" in
+block)
+    name=$1
+    shift
+    record_blocks "$@" >"$TMP_DIR/recorded.json"
+    write "$name.json" "Recorded from $SUBSTRATE_RPC_URL with record.sh block $name $*." \
+        <"$TMP_DIR/recorded.json"
+    ;;
+dex)
+    pair_metadata=$1
+    shift
+    record_blocks "$@" >"$TMP_DIR/recorded.json"
+    jq --slurpfile pair_metadata "$pair_metadata" '{pair_metadata: $pair_metadata[0]} + .' \
+        "$TMP_DIR/recorded.json" |
+        write dex_swap_blocks.json "Recorded from $SUBSTRATE_RPC_URL with record.sh dex $pair_metadata $*."
+    ;;
+evm)
+    tokens=$1
+    block_number=$(printf '0x%x' "$2")
+    filter=$(jq -c --arg block "$block_number" --arg topic "$ERC20_TRANSFER_TOPIC" \
+        '{address: map(.address), fromBlock: $block, toBlock: $block, topics: [$topic]}' \
+        "$tokens")
+    rpc "$EVM_RPC_URL" eth_getBlockByNumber "[\"$block_number\", true]" >"$TMP_DIR/block.json"
+    rpc "$EVM_RPC_URL" eth_getLogs "[$filter]" >"$TMP_DIR/logs.json"
+    jq -n --slurpfile tokens "$tokens" --slurpfile block "$TMP_DIR/block.json" \
+        --slurpfile logs "$TMP_DIR/logs.json" \
+        '{tokens: $tokens[0], block: $block[0], logs: $logs[0]}' |
+        write evm_transfers.json "Recorded from $EVM_RPC_URL with record.sh evm $tokens $2."
+    ;;
+*)
+    sed -n '2,/^set/p' "$0" | sed '$d' >&2
+    exit 1
+    ;;
+esac
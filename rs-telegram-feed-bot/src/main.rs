@@ -2,31 +2,50 @@ use chrono::Utc;
 use log::info;
 use num_format::{Locale, ToFormattedString};
 use rs_exchanges_parser::{
-    mongodb_client_exchanges::MongoDbClientExchanges, ExchangeTrade, ExchangesWallets,
-    PrimaryToken, TradeType,
+    mongodb_client_exchange_wallets::MongoDbClientExchangeWallets,
+    mongodb_client_exchanges::MongoDbClientExchanges, ExchangeTrade, PrimaryToken, TradeType,
 };
 use rs_subscan_parser::{
-    mongodb_client_identities::MongoDbClientIdentity, mongodb_client_subscan::MongoDbClientSubscan,
-    subscan_parser::EMPTY_ADDRESS, OperationType,
+    mongodb_client_balance::MongoDbClientBalance,
+    mongodb_client_era_reward::MongoDbClientEraReward,
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_label::MongoDbClientLabel,
+    mongodb_client_reward::MongoDbClientReward,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_unbonding::MongoDbClientUnbonding,
+    mongodb_client_validator::MongoDbClientValidator,
+    mongodb_client_validator_event::MongoDbClientValidatorEvent,
+    mongodb_client_validator_info::MongoDbClientValidatorInfo,
+    subscan_parser::{Network, EMPTY_ADDRESS},
+    unbonding_forecast::get_unlock_forecast,
+    OperationType, ValidatorEventType, ValidatorInfo, ValidatorStatus,
 };
 use rs_telegram_feed_bot::{
     mongodb_client_telegram::MongoDbClientTelegram, telegram_posting::TelegramPosting, Telegram,
 };
 use rs_utils::utils::logger::initialize_logger;
-use std::{cmp, env, str::FromStr, time::Duration};
+use std::{cmp, env, time::Duration};
 use tokio::time::sleep;
 
 static FILTER_MIN_USD_STAKING: f64 = 10_000.0;
 static FILTER_MIN_USD_TRANSFER: f64 = 25_000.0;
 static FILTER_MIN_USD_DEPOSIT_WITHDRAW: f64 = 25_000.0;
 static FILTER_MIN_USD_TRADE: f64 = 2_500.0;
+static FILTER_MIN_USD_REWARD: f64 = 1_000.0;
+static FILTER_MIN_USD_TREASURY: f64 = 5_000.0;
+static FILTER_MIN_USD_GOVERNANCE_VOTE: f64 = 50_000.0;
+static FILTER_MIN_USD_VESTING: f64 = 10_000.0;
 static FROM_SECONDS_AGO: i64 = 60 * 60 * 24;
+static MAX_LISTED_NOMINATORS: usize = 10;
+static CONCENTRATED_STAKE_SHARE: f64 = 0.05;
+static CONCENTRATED_STAKE_FILTER_RATIO: f64 = 0.5;
+static TOP_HOLDERS: u64 = 100;
 
 #[tokio::main(worker_threads = 100)]
 async fn main() {
     initialize_logger().expect("failed to initialize logging.");
 
-    info!(target: "telegram_feed_bot", "Started telegram feed worker.");
+    info!(target: "telegram_feed_bot", "Started telegram feed worker for {}.", Network::from_env());
 
     start_worker().await;
 }
@@ -38,60 +57,298 @@ async fn start_worker() {
     let bot_father_key = &env::var("TELEGRAM_BOT_FATHER_KEY").unwrap();
     let channel_id = &env::var("TELEGRAM_CHANNEL_ID").unwrap();
 
+    // each network is posted by its own bot instance into its own channel
+    let network = Network::from_env();
+    let explorer_url = network.get_explorer_url();
+    let token_symbol = network.get_token_symbol();
+
     loop {
         let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
         let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
+        let mut mongodb_client_label = MongoDbClientLabel::new().await;
+        let mut mongodb_client_exchange_wallets = MongoDbClientExchangeWallets::new().await;
 
         let from_timestamp = Utc::now().timestamp() - FROM_SECONDS_AGO;
-        let mut subscan_operations = mongodb_client_subscan
-            .get_filtered_operations(from_timestamp, None)
+        let subscan_operations = mongodb_client_subscan
+            .get_filtered_operations(&network, from_timestamp, None)
             .await;
 
         let advertisement = r#"<a href="https://azero.live/validator?address=5DEu6VG3WkJ1rdPadU4SffSse4sodA5PUE4apnw74c451Lak">💘 Our validator</a>"#;
 
-        let mut subscan_counter = 0;
         let mut messages = Vec::new();
-        for subscan_operation in subscan_operations.iter_mut() {
-            let from_identity = mongodb_client_identity
-                .get_identity_by_address(&subscan_operation.from_wallet)
+
+        // validator health goes first, regardless of amounts
+        let mut mongodb_client_validator_event = MongoDbClientValidatorEvent::new().await;
+        let validator_events = mongodb_client_validator_event
+            .get_filtered_validator_events(from_timestamp)
+            .await;
+
+        let mut validator_event_counter = 0;
+        let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+        for validator_event in validator_events {
+            let validator_identity = get_address_name(
+                &validator_event.validator,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
+            .await;
+            let affected_identity = get_address_name(
+                &validator_event.affected_wallet,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
+            .await;
+
+            let nominators_count = mongodb_client_validator
+                .count_nominators_by_validator(&validator_event.validator)
+                .await;
+            let mut nominators = Vec::new();
+            for p in mongodb_client_validator
+                .get_nominators_by_validator(&validator_event.validator)
                 .await
-                .map(|p| p.identity)
-                .unwrap_or(subscan_operation.from_wallet.clone());
+                .into_iter()
+                .take(MAX_LISTED_NOMINATORS)
+            {
+                let nominator_identity = get_address_name(
+                    &p.nominator,
+                    &mut mongodb_client_exchange_wallets,
+                    &mut mongodb_client_label,
+                    &mut mongodb_client_identity,
+                )
+                .await;
+                nominators.push(format!(
+                    r#"<a href="{explorer_url}/account/{}">{nominator_identity}</a>"#,
+                    p.nominator,
+                ));
+            }
+            let nominators = nominators.join(", ");
+
+            let title = match validator_event.event_type {
+                ValidatorEventType::Slashed => format!(
+                    "🚨 Slashed <b>{}</b> {token_symbol} of validator stake",
+                    (validator_event.amount.floor() as u64).to_formatted_string(&Locale::en),
+                ),
+                ValidatorEventType::Kicked => "🚨 Validator kicked a nominator".to_string(),
+                ValidatorEventType::Chilled => "🚨 Validator was chilled".to_string(),
+                ValidatorEventType::Banned => format!(
+                    "🚨 Validator was banned ({})",
+                    validator_event.reason.clone().unwrap_or_default()
+                ),
+            };
+            let affected = match validator_event.event_type {
+                ValidatorEventType::Slashed => format!(
+                    r#"Slashed address: <a href="{explorer_url}/account/{}">{affected_identity}</a>
+"#,
+                    validator_event.affected_wallet,
+                ),
+                ValidatorEventType::Kicked => format!(
+                    r#"Kicked nominator: <a href="{explorer_url}/account/{}">{affected_identity}</a>
+"#,
+                    validator_event.affected_wallet,
+                ),
+                _ => String::new(),
+            };
+
+            let message = format!(
+                r#"{title}
+
+Validator: <a href="{explorer_url}/account/{}">{validator_identity}</a>
+{affected}Total stake: <b>{}</b> {token_symbol}
+Known nominators: <b>{nominators_count}</b> {nominators}
+
+<a href="{explorer_url}/block/{}">📶 Block</a> | "#,
+                validator_event.validator,
+                (validator_event.total_stake.floor() as u64).to_formatted_string(&Locale::en),
+                validator_event.block_number,
+            );
+
+            // the nominators listed change with every nomination, the event stays the same
+            let already_posted_hash = sha256::digest(format!(
+                "validator_event_{}_{}",
+                validator_event.event_index, validator_event.validator
+            ));
+            messages.push((already_posted_hash, message));
+
+            validator_event_counter += 1;
+        }
+
+        // ------------------------------------------------------- //
+
+        let mut mongodb_client_balance = MongoDbClientBalance::new().await;
+        let changed_balances = mongodb_client_balance
+            .get_changed_balances(from_timestamp)
+            .await;
+
+        let mut whale_counter = 0;
+        for balance in changed_balances {
+            let Some(change_share) = balance.get_change_share() else {
+                continue;
+            };
+
+            let identity = get_address_name(
+                &balance.address,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
+            .await;
+            let (title, circle) = if change_share > 0.0 {
+                ("🐋 Whale wallet grew", "🟢")
+            } else {
+                ("🐋 Whale wallet shrank", "🔴")
+            };
+            let rank = mongodb_client_balance.get_holder_rank(balance.total).await;
+            let rank = if rank <= TOP_HOLDERS {
+                format!("Top holder: <b>#{rank}</b> of tracked wallets\n")
+            } else {
+                String::new()
+            };
+
+            let message = format!(
+                r#"{title} by <b>{}%</b>
+
+{circle}
+
+Wallet: <a href="{explorer_url}/account/{}">{identity}</a>
+Now holds <b>{}</b> {token_symbol} ({}% of supply), was {} {token_symbol}
+Free: {} | Staked: {} | Reserved: {}
+{rank}
+<a href="{explorer_url}/account/{}">📶 Account</a> | "#,
+                get_compact_number(change_share.abs() * 100.0),
+                balance.address,
+                get_compact_number(balance.total),
+                get_compact_number(balance.supply_share * 100.0),
+                get_compact_number(balance.previous_total.unwrap_or_default()),
+                get_compact_number(balance.free),
+                get_compact_number(balance.staked),
+                get_compact_number(balance.reserved),
+                balance.address,
+            );
+
+            // later refreshes change the amounts, the alert itself stays the same
+            let hash = sha256::digest(format!(
+                "{}_{}",
+                balance.address,
+                balance
+                    .changed_timestamp
+                    .map(|p| p.timestamp_millis())
+                    .unwrap_or_default()
+            ));
+            messages.push((hash, message));
+
+            whale_counter += 1;
+        }
+
+        // ------------------------------------------------------- //
+
+        // once a day, the hash only changes with the date
+        let mut mongodb_client_unbonding = MongoDbClientUnbonding::new().await;
+        let unbonding_chunks = mongodb_client_unbonding
+            .get_outstanding_unbonding_chunks()
+            .await;
+        if !unbonding_chunks.is_empty() {
+            let now = Utc::now();
+            let forecast = get_unlock_forecast(&unbonding_chunks, now.timestamp());
+            let days = forecast
+                .days
+                .iter()
+                .map(|(date, amount)| {
+                    format!(
+                        "{}: <b>{}</b> {token_symbol}",
+                        date.format("%b %d"),
+                        get_compact_number(*amount)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let message = format!(
+                r#"🔓 Unlock outlook
+
+Unlocking in 24h: <b>{}</b> {token_symbol}
+Unlocking in 7 days: <b>{}</b> {token_symbol}
+Ready to withdraw: <b>{}</b> {token_symbol}
+
+{days}
+
+<a href="{explorer_url}/validator">📶 Staking</a> | "#,
+                get_compact_number(forecast.next_24h),
+                get_compact_number(forecast.next_7d),
+                get_compact_number(forecast.unlocked),
+            );
+
+            let hash = sha256::digest(format!("unlock_outlook_{}", now.date_naive()));
+            messages.push((hash, message));
+        }
+
+        // ------------------------------------------------------- //
+
+        let mut mongodb_client_validator_info = MongoDbClientValidatorInfo::new().await;
+        let total_active_stake = mongodb_client_validator_info
+            .get_validator_infos_by_status(ValidatorStatus::Active)
+            .await
+            .iter()
+            .map(|p| p.total_stake)
+            .sum::<f64>();
+
+        let mut subscan_counter = 0;
+        for subscan_operation in subscan_operations.iter() {
+            let from_identity = get_address_name(
+                &subscan_operation.from_wallet,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
+            .await;
             let from_identity = if from_identity == EMPTY_ADDRESS {
                 "Unknown address".to_string()
             } else {
                 from_identity
             };
 
-            let to_identity = mongodb_client_identity
-                .get_identity_by_address(&subscan_operation.to_wallet)
-                .await
-                .map(|p| p.identity)
-                .unwrap_or(subscan_operation.to_wallet.clone());
+            let to_identity = get_address_name(
+                &subscan_operation.to_wallet,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
+            .await;
             let to_identity = if to_identity == EMPTY_ADDRESS {
                 "Unknown address".to_string()
             } else {
                 to_identity
             };
 
-            let from_exchange =
-                if let Ok(e) = ExchangesWallets::from_str(&subscan_operation.from_wallet) {
-                    e.get_beautiful_name()
-                } else {
-                    "".to_string()
-                };
-            let to_exchange =
-                if let Ok(e) = ExchangesWallets::from_str(&subscan_operation.to_wallet) {
-                    e.get_beautiful_name()
-                } else {
-                    "".to_string()
-                };
-            if !from_exchange.is_empty() {
-                subscan_operation.operation_type = OperationType::WithdrawFromExchange;
-            }
-            if !to_exchange.is_empty() {
-                subscan_operation.operation_type = OperationType::DepositToExchange;
-            }
+            let has_validator_details = matches!(
+                subscan_operation.operation_type,
+                OperationType::Stake
+                    | OperationType::ReStake
+                    | OperationType::RequestUnstake
+                    | OperationType::WithdrawUnstaked
+            );
+            let validator_info = if has_validator_details {
+                mongodb_client_validator_info
+                    .get_validator_info(&subscan_operation.to_wallet)
+                    .await
+            } else {
+                None
+            };
+            let min_usd_staking = get_min_usd_staking(
+                &subscan_operation.operation_type,
+                validator_info.as_ref(),
+                total_active_stake,
+            );
+            let validator_details = validator_info
+                .map(|p| {
+                    format!(
+                        " ({}% commission, {} {token_symbol} staked)",
+                        get_compact_number(p.commission),
+                        get_compact_number(p.total_stake),
+                    )
+                })
+                .unwrap_or_default();
 
             // filtering happens here
             match subscan_operation.operation_type {
@@ -100,7 +357,11 @@ async fn start_worker() {
                 {
                     continue
                 }
-                OperationType::DepositToExchange | OperationType::WithdrawFromExchange
+                OperationType::DepositToExchange
+                | OperationType::WithdrawFromExchange
+                | OperationType::ExchangeToExchange
+                | OperationType::BridgeOut
+                | OperationType::BridgeIn
                     if subscan_operation.operation_usd < FILTER_MIN_USD_DEPOSIT_WITHDRAW =>
                 {
                     continue
@@ -109,7 +370,29 @@ async fn start_worker() {
                 | OperationType::ReStake
                 | OperationType::RequestUnstake
                 | OperationType::WithdrawUnstaked
-                    if subscan_operation.operation_usd < FILTER_MIN_USD_STAKING =>
+                | OperationType::Rebond
+                | OperationType::PoolJoin
+                | OperationType::PoolBondExtra
+                | OperationType::PoolUnbond
+                | OperationType::PoolWithdrawUnbonded
+                | OperationType::PoolCreate
+                | OperationType::PoolClaimPayout
+                    if subscan_operation.operation_usd < min_usd_staking =>
+                {
+                    continue
+                }
+                OperationType::TreasuryProposal | OperationType::TreasurySpend
+                    if subscan_operation.operation_usd < FILTER_MIN_USD_TREASURY =>
+                {
+                    continue
+                }
+                OperationType::GovernanceVote
+                    if subscan_operation.operation_usd < FILTER_MIN_USD_GOVERNANCE_VOTE =>
+                {
+                    continue
+                }
+                OperationType::VestingUnlock | OperationType::VestedTransfer
+                    if subscan_operation.operation_usd < FILTER_MIN_USD_VESTING =>
                 {
                     continue
                 }
@@ -121,23 +404,69 @@ async fn start_worker() {
                 OperationType::ReStake => "🟡",
                 OperationType::RequestUnstake => "🟣",
                 OperationType::WithdrawUnstaked => "🟠",
+                OperationType::Rebond => "🟢",
                 OperationType::Transfer => "🟤",
                 OperationType::DepositToExchange => "⚪",
                 OperationType::WithdrawFromExchange => "⚫",
+                OperationType::ExchangeToExchange => "🔘",
+                OperationType::BridgeOut => "🔷",
+                OperationType::BridgeIn => "🔶",
+                OperationType::PoolJoin => "🟦",
+                OperationType::PoolBondExtra => "🟨",
+                OperationType::PoolUnbond => "🟪",
+                OperationType::PoolWithdrawUnbonded => "🟧",
+                OperationType::PoolCreate => "🟩",
+                OperationType::PoolClaimPayout => "🟫",
+                OperationType::TreasuryProposal => "🔺",
+                OperationType::TreasurySpend => "🔻",
+                OperationType::GovernanceVote => "🔲",
+                OperationType::VestingUnlock => "🔹",
+                OperationType::VestedTransfer => "🔸",
             };
 
             let circles = get_circles(circle, subscan_operation.operation_usd);
 
+            let pool_id = subscan_operation.pool_id.unwrap_or_default();
+            let pool_name = subscan_operation
+                .pool_name
+                .clone()
+                .filter(|p| !p.is_empty())
+                .unwrap_or(format!("Pool #{pool_id}"));
+
+            let bridge_name = subscan_operation
+                .bridge
+                .as_ref()
+                .map(|p| p.bridge.clone())
+                .unwrap_or_default();
+            let bridge_chain = subscan_operation
+                .bridge
+                .as_ref()
+                .and_then(|p| p.chain.clone())
+                .unwrap_or("another chain".to_string());
+            let bridge_counterparty = subscan_operation
+                .bridge
+                .as_ref()
+                .and_then(|p| p.counterparty.clone())
+                .map(|p| format!("\nOn {bridge_chain}: <code>{p}</code>"))
+                .unwrap_or_default();
+
+            // `vest` unlocks the signer, `vest_other` its target
+            let (vested_wallet, vested_identity) = if subscan_operation.to_wallet == EMPTY_ADDRESS {
+                (&subscan_operation.from_wallet, &from_identity)
+            } else {
+                (&subscan_operation.to_wallet, &to_identity)
+            };
+
             let message = match subscan_operation.operation_type {
                 OperationType::Stake => format!(
-                    r#"📘 Started stake of <b>{}</b> AZERO (<b>${}</b>)
+                    r#"📘 Started stake of <b>{}</b> {token_symbol} (<b>${}</b>)
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To validator: <a href="{explorer_url}/account/{}">{to_identity}</a>{validator_details}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                     (subscan_operation.operation_quantity.floor() as u64)
                         .to_formatted_string(&Locale::en),
                     (subscan_operation.operation_usd.floor() as u64)
@@ -147,14 +476,14 @@ To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a
                     subscan_operation.extrinsic_index,
                 ),
                 OperationType::ReStake => format!(
-                    r#"📒 Re-staked stake of <b>{}</b> AZERO (<b>${}</b>)
+                    r#"📒 Re-staked stake of <b>{}</b> {token_symbol} (<b>${}</b>)
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To validator: <a href="{explorer_url}/account/{}">{to_identity}</a>{validator_details}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                     (subscan_operation.operation_quantity.floor() as u64)
                         .to_formatted_string(&Locale::en),
                     (subscan_operation.operation_usd.floor() as u64)
@@ -165,14 +494,14 @@ To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a
                 ),
                 OperationType::RequestUnstake => {
                     format!(
-                        r#"👿 Requested unstake of <b>{}</b> AZERO (<b>${}</b>)
+                        r#"👿 Requested unstake of <b>{}</b> {token_symbol} (<b>${}</b>)
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+From validator: <a href="{explorer_url}/account/{}">{to_identity}</a>{validator_details}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
@@ -184,14 +513,14 @@ From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}<
                 }
                 OperationType::WithdrawUnstaked => {
                     format!(
-                        r#"🤬 Withdraw unstaked of <b>{}</b> AZERO (<b>${}</b>)
+                        r#"🤬 Withdraw unstaked of <b>{}</b> {token_symbol} (<b>${}</b>)
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+From validator: <a href="{explorer_url}/account/{}">{to_identity}</a>{validator_details}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
@@ -201,16 +530,33 @@ From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}<
                         subscan_operation.extrinsic_index,
                     )
                 }
+                OperationType::Rebond => {
+                    format!(
+                        r#"📗 Rebonded unstaking <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index,
+                    )
+                }
                 OperationType::Transfer => {
                     format!(
-                        r#"🕵️ Transferred <b>{}</b> AZERO (<b>${}</b>)
+                        r#"🕵️ Transferred <b>{}</b> {token_symbol} (<b>${}</b>)
                     
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To address: <a href="{explorer_url}/account/{}">{to_identity}</a>
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
@@ -222,14 +568,14 @@ To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
                 }
                 OperationType::DepositToExchange => {
                     format!(
-                        r#"👀 Deposited <b>{}</b> AZERO (<b>${}</b>) to {to_exchange}
+                        r#"👀 Deposited <b>{}</b> {token_symbol} (<b>${}</b>) to {to_identity}
                     
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To exchange: <a href="https://alephzero.subscan.io/account/{}">{to_exchange}</a>
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To exchange: <a href="{explorer_url}/account/{}">{to_identity}</a>
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
@@ -241,14 +587,14 @@ To exchange: <a href="https://alephzero.subscan.io/account/{}">{to_exchange}</a>
                 }
                 OperationType::WithdrawFromExchange => {
                     format!(
-                        r#"💠 Withdrew <b>{}</b> AZERO (<b>${}</b>) from {from_exchange}
+                        r#"💠 Withdrew <b>{}</b> {token_symbol} (<b>${}</b>) from {from_identity}
                     
 {circles}
 
-From exchange: <a href="https://alephzero.subscan.io/account/{}">{from_exchange}</a>
-To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From exchange: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To address: <a href="{explorer_url}/account/{}">{to_identity}</a>
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
@@ -258,19 +604,364 @@ To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
                         subscan_operation.extrinsic_index
                     )
                 }
+                OperationType::ExchangeToExchange => {
+                    format!(
+                        r#"🔁 Moved <b>{}</b> {token_symbol} (<b>${}</b>) from {from_identity} to {to_identity}
+                    
+{circles}
+
+From exchange: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To exchange: <a href="{explorer_url}/account/{}">{to_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.to_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::BridgeOut => {
+                    format!(
+                        r#"🌉 Bridged <b>{}</b> {token_symbol} (<b>${}</b>) to {bridge_chain} via {bridge_name}
+                    
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To bridge: <a href="{explorer_url}/account/{}">{to_identity}</a>{bridge_counterparty}
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.to_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::BridgeIn => {
+                    format!(
+                        r#"🌁 Bridged <b>{}</b> {token_symbol} (<b>${}</b>) from {bridge_chain} via {bridge_name}
+                    
+{circles}
+
+From bridge: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To address: <a href="{explorer_url}/account/{}">{to_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.to_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::PoolJoin => {
+                    format!(
+                        r#"🏊 Joined nomination pool with <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To pool: <a href="{explorer_url}/nomination_pool/{pool_id}">{pool_name}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::PoolBondExtra => {
+                    format!(
+                        r#"🏄 Increased nomination pool stake by <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To pool: <a href="{explorer_url}/nomination_pool/{pool_id}">{pool_name}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::PoolUnbond => {
+                    format!(
+                        r#"🥶 Requested unstake from nomination pool of <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+From pool: <a href="{explorer_url}/nomination_pool/{pool_id}">{pool_name}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::PoolWithdrawUnbonded => {
+                    format!(
+                        r#"🏃 Withdraw unstaked from nomination pool of <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+From pool: <a href="{explorer_url}/nomination_pool/{pool_id}">{pool_name}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::PoolCreate => {
+                    format!(
+                        r#"🏗 Created nomination pool with <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+Pool: <a href="{explorer_url}/nomination_pool/{pool_id}">{pool_name}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::PoolClaimPayout => {
+                    format!(
+                        r#"🎁 Claimed nomination pool rewards of <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+From pool: <a href="{explorer_url}/nomination_pool/{pool_id}">{pool_name}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index
+                    )
+                }
+                OperationType::TreasuryProposal => {
+                    format!(
+                        r#"🏛 Proposed treasury spend of <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To beneficiary: <a href="{explorer_url}/account/{}">{to_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.to_wallet,
+                        subscan_operation.extrinsic_index,
+                    )
+                }
+                OperationType::TreasurySpend => {
+                    format!(
+                        r#"💰 Treasury paid out <b>{}</b> {token_symbol} (<b>${}</b>)
+
+{circles}
+
+From treasury: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To beneficiary: <a href="{explorer_url}/account/{}">{to_identity}</a>
+
+<a href="{explorer_url}/block/{}?tab=event">📶 Block</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.to_wallet,
+                        subscan_operation.block_number,
+                    )
+                }
+                OperationType::GovernanceVote => {
+                    format!(
+                        r#"🗳 Voted with <b>{}</b> {token_symbol} (<b>${}</b>) on a referendum
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.extrinsic_index,
+                    )
+                }
+                OperationType::VestingUnlock => {
+                    format!(
+                        r#"🔓 Unlocked <b>{}</b> vested {token_symbol} (<b>${}</b>)
+
+{circles}
+
+Vested address: <a href="{explorer_url}/account/{}">{vested_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        vested_wallet,
+                        subscan_operation.extrinsic_index,
+                    )
+                }
+                OperationType::VestedTransfer => {
+                    format!(
+                        r#"⏳ Transferred <b>{}</b> {token_symbol} (<b>${}</b>) with vesting
+
+{circles}
+
+From address: <a href="{explorer_url}/account/{}">{from_identity}</a>
+To address: <a href="{explorer_url}/account/{}">{to_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        subscan_operation.from_wallet,
+                        subscan_operation.to_wallet,
+                        subscan_operation.extrinsic_index,
+                    )
+                }
             };
 
-            messages.push(message);
+            // the counterparty of a bridge transfer may be set after it was posted, validator
+            // details and identity, label or exchange wallet names change on every refresh
+            let already_posted_hash = if subscan_operation.operation_type.is_bridge_transfer() {
+                sha256::digest(format!("bridge_{}", subscan_operation.hash))
+            } else {
+                sha256::digest(format!("operation_{}", subscan_operation.hash))
+            };
+            messages.push((already_posted_hash, message));
 
             subscan_counter += 1;
         }
 
         // ------------------------------------------------------- //
 
-        let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
-        let non_grouped_exchanges_operations = mongodb_client_exchanges
-            .get_filtered_trades(PrimaryToken::Azero, from_timestamp, None)
+        let mut mongodb_client_reward = MongoDbClientReward::new().await;
+        let staking_rewards = mongodb_client_reward
+            .get_filtered_rewards(from_timestamp, FILTER_MIN_USD_REWARD)
+            .await;
+
+        let mut reward_counter = 0;
+        for staking_reward in staking_rewards {
+            let stash_identity = get_address_name(
+                &staking_reward.stash,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
             .await;
+            let validator_identity = get_address_name(
+                &staking_reward.validator,
+                &mut mongodb_client_exchange_wallets,
+                &mut mongodb_client_label,
+                &mut mongodb_client_identity,
+            )
+            .await;
+
+            let circles = get_circles("💎", staking_reward.reward_usd);
+            let receiver = if staking_reward.is_validator {
+                "Validator"
+            } else {
+                "Nominator"
+            };
+
+            let message = format!(
+                r#"💰 Received staking reward of <b>{}</b> {token_symbol} (<b>${}</b>) for era <b>{}</b>
+
+{circles}
+
+{receiver}: <a href="{explorer_url}/account/{}">{stash_identity}</a>
+From validator: <a href="{explorer_url}/account/{}">{validator_identity}</a>
+
+<a href="{explorer_url}/extrinsic/{}">📶 Tx Hash</a> | "#,
+                (staking_reward.reward_quantity.floor() as u64).to_formatted_string(&Locale::en),
+                (staking_reward.reward_usd.floor() as u64).to_formatted_string(&Locale::en),
+                staking_reward.era,
+                staking_reward.stash,
+                staking_reward.validator,
+                staking_reward.extrinsic_index,
+            );
+
+            // stash and validator names change with identity and label refreshes
+            let already_posted_hash =
+                sha256::digest(format!("reward_{}", staking_reward.event_index));
+            messages.push((already_posted_hash, message));
+
+            reward_counter += 1;
+        }
+
+        // newest era is still being paid out, reporting the one before it
+        let mut mongodb_client_era_reward = MongoDbClientEraReward::new().await;
+        let era_rewards = mongodb_client_era_reward.get_latest_era_rewards(2).await;
+        if let [_, era_reward] = era_rewards.as_slice() {
+            let message = format!(
+                r#"🏆 Era <b>{}</b> staking rewards: <b>{}</b> {token_symbol} (<b>${}</b>)
+
+Paid out by <b>{}</b> validators to <b>{}</b> nominators
+
+"#,
+                era_reward.era,
+                (era_reward.reward_quantity.floor() as u64).to_formatted_string(&Locale::en),
+                (era_reward.reward_usd.floor() as u64).to_formatted_string(&Locale::en),
+                era_reward.validators,
+                era_reward.nominators,
+            );
+
+            // late payouts change the totals, each era is posted only once
+            let already_posted_hash = sha256::digest(format!("era_reward_{}", era_reward.era));
+            messages.push((already_posted_hash, message));
+        }
+
+        // ------------------------------------------------------- //
+
+        // only mainnet token is traded on exchanges
+        let mut non_grouped_exchanges_operations = Vec::new();
+        if network == Network::Alephzero {
+            let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
+            non_grouped_exchanges_operations = mongodb_client_exchanges
+                .get_filtered_trades(PrimaryToken::Azero, from_timestamp, None)
+                .await;
+        }
         let mut exchanges_operations: Vec<ExchangeTrade> = Vec::new();
         for e in non_grouped_exchanges_operations {
             let found = exchanges_operations.iter_mut().find(|p| {
@@ -344,13 +1035,13 @@ Bought <b>{}</b> AZERO for <b>{}</b> {} on {exchange_name}
                 ),
             };
 
-            messages.push(message);
+            messages.push((sha256::digest(&message), message));
 
             exchange_counter += 1;
         }
 
         let mut mongodb_client_telegram = MongoDbClientTelegram::new().await;
-        let telegram_hashes = messages.iter().map(sha256::digest).collect();
+        let telegram_hashes = messages.iter().map(|(p, _)| p.clone()).collect();
         let non_existing_hashes = mongodb_client_telegram
             .get_not_existing_telegrams(telegram_hashes)
             .await;
@@ -358,18 +1049,17 @@ Bought <b>{}</b> AZERO for <b>{}</b> {} on {exchange_name}
 
         let messages = messages
             .into_iter()
-            .filter(|p| non_existing_hashes.contains(&sha256::digest(p)))
-            .collect::<Vec<String>>();
+            .filter(|(p, _)| non_existing_hashes.contains(p))
+            .collect::<Vec<_>>();
         let skipped_counter = messages_len - messages.len();
 
         let mut telegram_posting = TelegramPosting::new(bot_father_key, channel_id).await;
-        for message in messages {
+        for (already_posted_hash, message) in messages {
             let message_with_advertisement = format!("{message}{advertisement}");
             telegram_posting
                 .post_message(&message_with_advertisement)
                 .await;
 
-            let already_posted_hash = sha256::digest(&message);
             mongodb_client_telegram
                 .import_telegrams(vec![Telegram {
                     already_posted_hash,
@@ -379,7 +1069,7 @@ Bought <b>{}</b> AZERO for <b>{}</b> {} on {exchange_name}
             sleep(Duration::from_millis(250)).await;
         }
 
-        info!(target: "telegram_posting", "Skipped {skipped_counter}. Posted {exchange_counter} trades and {subscan_counter} subscan operations. Sleeping 1 sec.");
+        info!(target: "telegram_posting", "Skipped {skipped_counter}. Posted {validator_event_counter} validator events, {whale_counter} whale balance changes, {exchange_counter} trades, {subscan_counter} subscan operations and {reward_counter} rewards. Sleeping 1 sec.");
 
         sleep(Duration::from_millis(1_000)).await;
     }
@@ -396,3 +1086,63 @@ fn get_circles(circle: &str, operation_usd: f64) -> String {
 
     circles
 }
+
+/// Stakes moving into a validator that already holds a large share of the active stake
+/// are posted from a lower amount, they make the set more concentrated.
+fn get_min_usd_staking(
+    operation_type: &OperationType,
+    validator_info: Option<&ValidatorInfo>,
+    total_active_stake: f64,
+) -> f64 {
+    let is_concentrated = validator_info
+        .map(|p| p.get_stake_share(total_active_stake) >= CONCENTRATED_STAKE_SHARE)
+        .unwrap_or_default();
+    match operation_type {
+        OperationType::Stake | OperationType::ReStake if is_concentrated => {
+            FILTER_MIN_USD_STAKING * CONCENTRATED_STAKE_FILTER_RATIO
+        }
+        _ => FILTER_MIN_USD_STAKING,
+    }
+}
+
+/// Short form like `12M` or `2.5K`, with at most one decimal.
+fn get_compact_number(number: f64) -> String {
+    let (number, suffix) = if number >= 1e9 {
+        (number / 1e9, "B")
+    } else if number >= 1e6 {
+        (number / 1e6, "M")
+    } else if number >= 1e3 {
+        (number / 1e3, "K")
+    } else {
+        (number, "")
+    };
+    let number = format!("{number:.1}");
+
+    format!("{}{suffix}", number.trim_end_matches(".0"))
+}
+
+/// Registered exchange wallet first, then our own label, then the on-chain identity,
+/// then the address itself.
+async fn get_address_name(
+    address: &str,
+    mongodb_client_exchange_wallets: &mut MongoDbClientExchangeWallets,
+    mongodb_client_label: &mut MongoDbClientLabel,
+    mongodb_client_identity: &mut MongoDbClientIdentity,
+) -> String {
+    if let Some(exchange_wallet) = mongodb_client_exchange_wallets
+        .get_exchange_wallet_by_address(address)
+        .await
+    {
+        return exchange_wallet.get_beautiful_name();
+    }
+
+    if let Some(label) = mongodb_client_label.get_label_by_address(address).await {
+        return label.label;
+    }
+
+    mongodb_client_identity
+        .get_identity_by_address(address)
+        .await
+        .map(|p| p.identity)
+        .unwrap_or(address.to_string())
+}